@@ -0,0 +1,64 @@
+// cargo bench -p lib --bench msm
+// cargo bench -p lib --bench msm --features bn254
+//
+// Compares Pippenger against the naive double-and-add sum, on the toy G1 and,
+// with the `bn254` feature, on BN254 G1. No bench harness is needed for numbers
+// this coarse, each size is timed over a fixed number of runs.
+
+use std::{hint::black_box, time::Instant};
+
+use lib::{
+    field::{Point, F17, G1},
+    msm::{msm, msm_naive, MsmPoint, MsmScalar},
+};
+
+const RUNS: u32 = 20;
+
+fn time<P>(f: impl Fn() -> P) -> f64 {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        black_box(f());
+    }
+    start.elapsed().as_secs_f64() * 1e6 / f64::from(RUNS)
+}
+
+fn compare<P, S>(points: &[P], scalars: &[S])
+where
+    P: MsmPoint + PartialEq + std::fmt::Debug,
+    S: MsmScalar,
+{
+    assert_eq!(msm(points, scalars), msm_naive(points, scalars));
+
+    let naive = time(|| msm_naive(black_box(points), black_box(scalars)));
+    let pippenger = time(|| msm(black_box(points), black_box(scalars)));
+    println!("{:>6} {:>12.1} {:>12.1}", points.len(), naive, pippenger);
+}
+
+fn main() {
+    println!("toy G1, F17 scalars");
+    println!("{:>6} {:>12} {:>12}", "n", "naive (us)", "msm (us)");
+    for log_n in 4..=14 {
+        let n = 1u32 << log_n;
+        let points: Vec<Point> = (0..n).map(|i| G1.mul(F17::new(i * 5 + 1))).collect();
+        let scalars: Vec<F17> = (0..n).map(|i| F17::new(i * i + 11)).collect();
+        compare(&points, &scalars);
+    }
+
+    #[cfg(feature = "bn254")]
+    {
+        use lib::bn254::{curve::G1Point, field::Fr};
+
+        println!("BN254 G1, Fr scalars");
+        println!("{:>6} {:>12} {:>12}", "n", "naive (us)", "msm (us)");
+        // full-size scalars, so every window of every scalar is used
+        let scalar = |i: u64| Fr::from_u64(i * i + 11).pow(&[i + 1_000_003]);
+        for log_n in 4..=10 {
+            let n = 1u64 << log_n;
+            let points: Vec<G1Point> = (0..n)
+                .map(|i| G1Point::generator().mul(Fr::from_u64(i * 5 + 1)))
+                .collect();
+            let scalars: Vec<Fr> = (0..n).map(scalar).collect();
+            compare(&points, &scalars);
+        }
+    }
+}
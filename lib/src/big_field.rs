@@ -0,0 +1,509 @@
+// Prime fields too large for a machine word, in Montgomery form.
+//
+// An element a is stored as a * R mod p with R = 2^(64N), in N little-endian
+// 64-bit limbs. Multiplication is Montgomery's CIOS method, which computes
+// a * b / R mod p with word multiplications and shifts, never dividing by p.
+//
+// The final "subtract p if the result is too big" step of add, sub and mul picks
+// its result with a mask instead of a branch, so those operations take the same
+// path for every input. Inversion is Fermat's a^(p - 2) by binary exponentiation;
+// the exponent is public, so its branches reveal nothing about a.
+//
+// Conversions from plain integers are const fns, so parameters such as curve
+// coefficients can be written as decimal constants.
+
+use std::{fmt, hash::Hash, marker::PhantomData};
+
+pub trait BigPrimeParams<const N: usize>:
+    Copy + fmt::Debug + PartialEq + Eq + Hash + 'static
+{
+    // Little-endian limbs of an odd prime
+    const MODULUS: [u64; N];
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BigPrimeField<P: BigPrimeParams<N>, const N: usize> {
+    // a * R mod p, always below p
+    limbs: [u64; N],
+    params: PhantomData<P>,
+}
+
+// a + b + carry, returning (sum, carry)
+const fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + b as u128 + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+// a - b - borrow, returning (difference, borrow)
+const fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let t = (a as u128).wrapping_sub(b as u128 + borrow as u128);
+    (t as u64, (t >> 127) as u64)
+}
+
+// acc + a * b + carry, which cannot overflow 128 bits
+const fn mac(acc: u64, a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = acc as u128 + (a as u128) * (b as u128) + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+const fn add_limbs<const N: usize>(a: &[u64; N], b: &[u64; N]) -> ([u64; N], u64) {
+    let mut result = [0u64; N];
+    let mut carry = 0;
+    let mut i = 0;
+    while i < N {
+        (result[i], carry) = adc(a[i], b[i], carry);
+        i += 1;
+    }
+    (result, carry)
+}
+
+const fn sub_limbs<const N: usize>(a: &[u64; N], b: &[u64; N]) -> ([u64; N], u64) {
+    let mut result = [0u64; N];
+    let mut borrow = 0;
+    let mut i = 0;
+    while i < N {
+        (result[i], borrow) = sbb(a[i], b[i], borrow);
+        i += 1;
+    }
+    (result, borrow)
+}
+
+// mask is all ones or all zeros: if_set where it is set, otherwise if_clear
+const fn select<const N: usize>(mask: u64, if_set: &[u64; N], if_clear: &[u64; N]) -> [u64; N] {
+    let mut result = [0u64; N];
+    let mut i = 0;
+    while i < N {
+        result[i] = (if_set[i] & mask) | (if_clear[i] & !mask);
+        i += 1;
+    }
+    result
+}
+
+// a - m if a plus a carried 2^(64N) is at least m, for inputs below 2m. Without a
+// branch: the subtraction always happens and a mask keeps one of the two results.
+const fn reduce_once<const N: usize>(a: &[u64; N], carry: u64, m: &[u64; N]) -> [u64; N] {
+    let (reduced, borrow) = sub_limbs(a, m);
+    // a - m did not go below zero, or only did because of the carried 2^(64N)
+    let keep_reduced = carry | (borrow ^ 1);
+    select(keep_reduced.wrapping_neg(), &reduced, a)
+}
+
+// -m^-1 mod 2^64 by Newton iteration, each step doubles the correct low bits
+const fn neg_inv(m0: u64) -> u64 {
+    let mut inv = 1u64;
+    let mut i = 0;
+    while i < 6 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(m0.wrapping_mul(inv)));
+        i += 1;
+    }
+    inv.wrapping_neg()
+}
+
+// 2^bits mod m, by doubling
+const fn pow2_mod<const N: usize>(bits: usize, m: &[u64; N]) -> [u64; N] {
+    let mut result = [0u64; N];
+    result[0] = 1;
+    let mut i = 0;
+    while i < bits {
+        let (doubled, carry) = add_limbs(&result, &result);
+        result = reduce_once(&doubled, carry, m);
+        i += 1;
+    }
+    result
+}
+
+// a * b / 2^(64N) mod m, for a * b < 2^(64N) * m
+const fn mont_mul<const N: usize>(a: &[u64; N], b: &[u64; N], m: &[u64; N], inv: u64) -> [u64; N] {
+    // t has N + 2 limbs: t[0..N], then high and top
+    let mut t = [0u64; N];
+    let mut high = 0;
+    let mut i = 0;
+    while i < N {
+        // t += a * b[i]
+        let mut carry = 0;
+        let mut j = 0;
+        while j < N {
+            (t[j], carry) = mac(t[j], a[j], b[i], carry);
+            j += 1;
+        }
+        let top;
+        (high, top) = adc(high, carry, 0);
+
+        // t = (t + k * m) / 2^64, with k chosen to clear the lowest limb
+        let k = t[0].wrapping_mul(inv);
+        let (_, mut carry) = mac(t[0], k, m[0], 0);
+        let mut j = 1;
+        while j < N {
+            (t[j - 1], carry) = mac(t[j], k, m[j], carry);
+            j += 1;
+        }
+        (t[N - 1], carry) = adc(high, carry, 0);
+        high = top + carry;
+        i += 1;
+    }
+    reduce_once(&t, high, m)
+}
+
+const fn is_below<const N: usize>(a: &[u64; N], m: &[u64; N]) -> bool {
+    sub_limbs(a, m).1 == 1
+}
+
+#[allow(clippy::should_implement_trait)]
+impl<P: BigPrimeParams<N>, const N: usize> BigPrimeField<P, N> {
+    const INV: u64 = neg_inv(P::MODULUS[0]);
+    // R^2 mod p, multiplying by it converts into Montgomery form
+    const R2: [u64; N] = pow2_mod(128 * N, &P::MODULUS);
+
+    pub const ZERO: Self = BigPrimeField {
+        limbs: [0; N],
+        params: PhantomData,
+    };
+    pub const ONE: Self = BigPrimeField {
+        limbs: pow2_mod(64 * N, &P::MODULUS),
+        params: PhantomData,
+    };
+
+    // From the plain little-endian value, which must be below p
+    pub const fn from_raw(limbs: [u64; N]) -> Self {
+        assert!(
+            is_below(&limbs, &P::MODULUS),
+            "value is not below the modulus"
+        );
+        Self::from_raw_unchecked(limbs)
+    }
+
+    // Any value below R is fine for the conversion, it comes out reduced
+    const fn from_raw_unchecked(limbs: [u64; N]) -> Self {
+        BigPrimeField {
+            limbs: mont_mul(&limbs, &Self::R2, &P::MODULUS, Self::INV),
+            params: PhantomData,
+        }
+    }
+
+    // n mod p
+    pub const fn from_u64(n: u64) -> Self {
+        let mut limbs = [0u64; N];
+        limbs[0] = n;
+        Self::from_raw_unchecked(limbs)
+    }
+
+    // From a decimal string of a value below p, for writing constants
+    pub const fn from_decimal(s: &str) -> Self {
+        let bytes = s.as_bytes();
+        assert!(!bytes.is_empty(), "empty decimal string");
+        let mut value = [0u64; N];
+        let mut i = 0;
+        while i < bytes.len() {
+            assert!(bytes[i].is_ascii_digit(), "not a decimal digit");
+            // value = value * 10 + digit
+            let mut carry = (bytes[i] - b'0') as u64;
+            let mut j = 0;
+            while j < N {
+                (value[j], carry) = mac(0, value[j], 10, carry);
+                j += 1;
+            }
+            assert!(carry == 0, "decimal value does not fit in the limbs");
+            i += 1;
+        }
+        Self::from_raw(value)
+    }
+
+    // The plain little-endian value, below p
+    pub const fn to_raw(self) -> [u64; N] {
+        let mut one = [0u64; N];
+        one[0] = 1;
+        mont_mul(&self.limbs, &one, &P::MODULUS, Self::INV)
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.limbs == [0; N]
+    }
+
+    pub fn add(self, other: Self) -> Self {
+        let (sum, carry) = add_limbs(&self.limbs, &other.limbs);
+        BigPrimeField {
+            limbs: reduce_once(&sum, carry, &P::MODULUS),
+            params: PhantomData,
+        }
+    }
+
+    pub fn sub(self, other: Self) -> Self {
+        let (difference, borrow) = sub_limbs(&self.limbs, &other.limbs);
+        // add p back when the subtraction went below zero, masked rather than branched
+        let correction = select(borrow.wrapping_neg(), &P::MODULUS, &[0; N]);
+        BigPrimeField {
+            limbs: add_limbs(&difference, &correction).0,
+            params: PhantomData,
+        }
+    }
+
+    pub fn neg(self) -> Self {
+        Self::ZERO.sub(self)
+    }
+
+    pub fn double(self) -> Self {
+        self.add(self)
+    }
+
+    pub fn mul(self, other: Self) -> Self {
+        BigPrimeField {
+            limbs: mont_mul(&self.limbs, &other.limbs, &P::MODULUS, Self::INV),
+            params: PhantomData,
+        }
+    }
+
+    pub fn square(self) -> Self {
+        self.mul(self)
+    }
+
+    // self^exp, with exp in little-endian limbs, by left-to-right square-and-multiply
+    pub fn pow(self, exp: &[u64]) -> Self {
+        let mut result = Self::ONE;
+        for &limb in exp.iter().rev() {
+            for i in (0..64).rev() {
+                result = result.square();
+                if (limb >> i) & 1 == 1 {
+                    result = result.mul(self);
+                }
+            }
+        }
+        result
+    }
+
+    // Fermat: a^(p - 2) = a^-1
+    pub fn inv(self) -> Option<Self> {
+        if self.is_zero() {
+            return None;
+        }
+        let mut two = [0u64; N];
+        two[0] = 2;
+        let (p_minus_2, _) = sub_limbs(&P::MODULUS, &two);
+        Some(self.pow(&p_minus_2))
+    }
+}
+
+impl<P: BigPrimeParams<N>, const N: usize> fmt::Debug for BigPrimeField<P, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x")?;
+        for limb in self.to_raw().iter().rev() {
+            write!(f, "{:016x}", limb)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2^64 - 59, the largest 64-bit prime, so sums and products carry past the top limb
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    struct P64;
+
+    impl BigPrimeParams<1> for P64 {
+        const MODULUS: [u64; 1] = [0xffff_ffff_ffff_ffc5];
+    }
+
+    // 2^127 - 1
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    struct P127;
+
+    impl BigPrimeParams<2> for P127 {
+        const MODULUS: [u64; 2] = [u64::MAX, u64::MAX >> 1];
+    }
+
+    fn to_u128<const N: usize>(limbs: [u64; N]) -> u128 {
+        limbs
+            .iter()
+            .rev()
+            .fold(0, |acc, &limb| (acc << 64) | u128::from(limb))
+    }
+
+    // Naive arithmetic modulo p < 2^127 on plain u128s
+    fn add_mod(a: u128, b: u128, p: u128) -> u128 {
+        (a + b) % p
+    }
+
+    fn sub_mod(a: u128, b: u128, p: u128) -> u128 {
+        (a + p - b) % p
+    }
+
+    // double-and-add, since a * b itself can overflow u128
+    fn mul_mod(a: u128, b: u128, p: u128) -> u128 {
+        let mut result = 0;
+        for i in (0..128).rev() {
+            result = add_mod(result, result, p);
+            if (b >> i) & 1 == 1 {
+                result = add_mod(result, a, p);
+            }
+        }
+        result
+    }
+
+    fn pow_mod(base: u128, exp: u128, p: u128) -> u128 {
+        let mut result = 1;
+        for i in (0..128).rev() {
+            result = mul_mod(result, result, p);
+            if (exp >> i) & 1 == 1 {
+                result = mul_mod(result, base, p);
+            }
+        }
+        result
+    }
+
+    // xorshift64
+    fn random_values(count: usize, p: u128) -> Vec<u128> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut values = vec![0, 1, p - 1, p - 2, p / 2, p / 2 + 1];
+        values.extend((0..count).map(|_| ((u128::from(next()) << 64) | u128::from(next())) % p));
+        values
+    }
+
+    fn element<P: BigPrimeParams<N>, const N: usize>(value: u128) -> BigPrimeField<P, N> {
+        let mut limbs = [0u64; N];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = (value >> (64 * i)) as u64;
+        }
+        BigPrimeField::from_raw(limbs)
+    }
+
+    // Random elements drawn limb by limb
+    fn random_elements<P: BigPrimeParams<N>, const N: usize>(
+        count: usize,
+    ) -> Vec<BigPrimeField<P, N>> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..count)
+            .map(|_| {
+                let mut limbs = [0u64; N];
+                limbs.iter_mut().for_each(|limb| *limb = next());
+                // a smaller top limb keeps the value below p
+                limbs[N - 1] %= P::MODULUS[N - 1];
+                BigPrimeField::from_raw(limbs)
+            })
+            .collect()
+    }
+
+    fn check_field<P: BigPrimeParams<N>, const N: usize>() {
+        let mut one = [0u64; N];
+        one[0] = 1;
+        let minus_one = BigPrimeField::<P, N>::from_raw(sub_limbs(&P::MODULUS, &one).0);
+        assert_eq!(minus_one.add(BigPrimeField::ONE), BigPrimeField::ZERO);
+        assert_eq!(minus_one.square(), BigPrimeField::ONE);
+        assert_eq!(BigPrimeField::<P, N>::ONE.to_raw(), one);
+        assert_eq!(BigPrimeField::<P, N>::ZERO.inv(), None);
+
+        let elements = random_elements::<P, N>(20);
+        for &a in &elements {
+            assert_eq!(BigPrimeField::<P, N>::from_raw(a.to_raw()), a);
+            assert_eq!(a.sub(a), BigPrimeField::ZERO);
+            assert_eq!(a.add(a.neg()), BigPrimeField::ZERO);
+            assert_eq!(a.double(), a.mul(BigPrimeField::from_u64(2)));
+            if !a.is_zero() {
+                assert_eq!(a.mul(a.inv().unwrap()), BigPrimeField::ONE);
+            }
+            for &b in &elements {
+                assert_eq!(a.mul(b), b.mul(a));
+                assert_eq!(a.add(b).sub(b), a);
+                // (a + b)^2 = a^2 + 2ab + b^2
+                assert_eq!(
+                    a.add(b).square(),
+                    a.square().add(a.mul(b).double()).add(b.square())
+                );
+            }
+        }
+    }
+
+    fn check_against_reference<P: BigPrimeParams<N>, const N: usize>() {
+        let p = to_u128(P::MODULUS);
+        let values = random_values(40, p);
+        for &a in &values {
+            let x = element::<P, N>(a);
+            assert_eq!(to_u128(x.to_raw()), a);
+            assert_eq!(to_u128(x.neg().to_raw()), sub_mod(0, a, p));
+            match x.inv() {
+                None => assert_eq!(a, 0),
+                Some(inv) => {
+                    assert_eq!(to_u128(inv.to_raw()), pow_mod(a, p - 2, p));
+                    assert_eq!(x.mul(inv), BigPrimeField::ONE);
+                }
+            }
+            for &b in &values {
+                let y = element::<P, N>(b);
+                assert_eq!(
+                    to_u128(x.add(y).to_raw()),
+                    add_mod(a, b, p),
+                    "{} + {}",
+                    a,
+                    b
+                );
+                assert_eq!(
+                    to_u128(x.sub(y).to_raw()),
+                    sub_mod(a, b, p),
+                    "{} - {}",
+                    a,
+                    b
+                );
+                assert_eq!(
+                    to_u128(x.mul(y).to_raw()),
+                    mul_mod(a, b, p),
+                    "{} * {}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn one_limb_reference_test() {
+        check_against_reference::<P64, 1>();
+        // u64 values at and above p reduce
+        let p = P64::MODULUS[0];
+        assert_eq!(BigPrimeField::<P64, 1>::from_u64(p), BigPrimeField::ZERO);
+        assert_eq!(
+            BigPrimeField::<P64, 1>::from_u64(u64::MAX).to_raw(),
+            [u64::MAX - p]
+        );
+    }
+
+    #[test]
+    fn two_limb_reference_test() {
+        check_against_reference::<P127, 2>();
+    }
+
+    #[test]
+    fn reduce_once_test() {
+        let m = [5u64, 1];
+        // below m, equal to m and above m
+        assert_eq!(reduce_once(&[4, 1], 0, &m), [4, 1]);
+        assert_eq!(reduce_once(&[5, 1], 0, &m), [0, 0]);
+        assert_eq!(reduce_once(&[7, 1], 0, &m), [2, 0]);
+        // 2^128 + 3 - (2^128 - 2^64 + 5) = 2^64 - 2, with the 2^128 in the carry
+        let m = [5u64, u64::MAX];
+        assert_eq!(reduce_once(&[3, 0], 1, &m), [u64::MAX - 1, 0]);
+    }
+
+    #[test]
+    fn one_limb_test() {
+        check_field::<P64, 1>();
+    }
+
+    #[test]
+    fn two_limb_test() {
+        check_field::<P127, 2>();
+        let x = BigPrimeField::<P127, 2>::from_decimal("170141183460469231731687303715884105726");
+        assert_eq!(x, BigPrimeField::ONE.neg());
+    }
+}
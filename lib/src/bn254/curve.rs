@@ -0,0 +1,430 @@
+// G1 and G2 of BN254, both of the form y^2 = x^3 + b:
+//
+//   G1: y^2 = x^3 + 3       over Fq,  generator (1, 2), cofactor 1
+//   G2: y^2 = x^3 + 3 / xi  over Fq2, the sextic twist, cofactor 2p - r
+//
+// Both have prime order r subgroups, so scalars are Fr elements. Scalar
+// multiplication runs in Jacobian coordinates (dbl-2009-l and madd-2007-bl, with
+// a = 0) and converts back to affine once.
+
+use std::fmt::Debug;
+
+use crate::{
+    big_field::BigPrimeParams,
+    bn254::{
+        field::{Fq, Fr, FrParams},
+        field_extension::Fq2,
+    },
+    fixed_base::TablePoint,
+    msm::MsmPoint,
+};
+
+// What the group law needs from a coordinate field
+pub trait CurveField: Copy + Debug + PartialEq + Eq {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn add(self, other: Self) -> Self;
+    fn sub(self, other: Self) -> Self;
+    fn mul(self, other: Self) -> Self;
+    fn neg(self) -> Self;
+    fn inv(self) -> Option<Self>;
+}
+
+impl CurveField for Fq {
+    const ZERO: Fq = Fq::ZERO;
+    const ONE: Fq = Fq::ONE;
+
+    fn add(self, other: Fq) -> Fq {
+        Fq::add(self, other)
+    }
+
+    fn sub(self, other: Fq) -> Fq {
+        Fq::sub(self, other)
+    }
+
+    fn mul(self, other: Fq) -> Fq {
+        Fq::mul(self, other)
+    }
+
+    fn neg(self) -> Fq {
+        Fq::neg(self)
+    }
+
+    fn inv(self) -> Option<Fq> {
+        Fq::inv(self)
+    }
+}
+
+impl CurveField for Fq2 {
+    const ZERO: Fq2 = Fq2::ZERO;
+    const ONE: Fq2 = Fq2::ONE;
+
+    fn add(self, other: Fq2) -> Fq2 {
+        Fq2::add(self, other)
+    }
+
+    fn sub(self, other: Fq2) -> Fq2 {
+        Fq2::sub(self, other)
+    }
+
+    fn mul(self, other: Fq2) -> Fq2 {
+        Fq2::mul(self, other)
+    }
+
+    fn neg(self) -> Fq2 {
+        Fq2::neg(self)
+    }
+
+    fn inv(self) -> Option<Fq2> {
+        Fq2::inv(self)
+    }
+}
+
+pub trait BnCurve: Clone + Copy + Debug + PartialEq + Eq + 'static {
+    type Base: CurveField;
+
+    const B: Self::Base;
+    const GENERATOR: (Self::Base, Self::Base);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct G1Curve;
+
+impl BnCurve for G1Curve {
+    type Base = Fq;
+
+    const B: Fq = Fq::from_u64(3);
+    const GENERATOR: (Fq, Fq) = (Fq::from_u64(1), Fq::from_u64(2));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct G2Curve;
+
+impl BnCurve for G2Curve {
+    type Base = Fq2;
+
+    // 3 / (9 + u)
+    const B: Fq2 = Fq2::new(
+        Fq::from_decimal(
+            "19485874751759354771024239261021720505790618469301721065564631296452457478373",
+        ),
+        Fq::from_decimal(
+            "266929791119991161246907387137283842545076965332900288569378510910307636690",
+        ),
+    );
+    const GENERATOR: (Fq2, Fq2) = (
+        Fq2::new(
+            Fq::from_decimal(
+                "10857046999023057135944570762232829481370756359578518086990519993285655852781",
+            ),
+            Fq::from_decimal(
+                "11559732032986387107991004021392285783925812861821192530917403151452391805634",
+            ),
+        ),
+        Fq2::new(
+            Fq::from_decimal(
+                "8495653923123431417604973247489272438418190587263600148770280649306958101930",
+            ),
+            Fq::from_decimal(
+                "4082367875863433681332203403145435568316851327593401208105741076214120093531",
+            ),
+        ),
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurvePoint<C: BnCurve> {
+    Infinity,
+    Point { x: C::Base, y: C::Base },
+}
+
+pub type G1Point = CurvePoint<G1Curve>;
+pub type G2Point = CurvePoint<G2Curve>;
+
+#[allow(clippy::should_implement_trait)]
+impl<C: BnCurve> CurvePoint<C> {
+    pub const fn generator() -> Self {
+        CurvePoint::Point {
+            x: C::GENERATOR.0,
+            y: C::GENERATOR.1,
+        }
+    }
+
+    pub fn neg(self) -> Self {
+        match self {
+            CurvePoint::Infinity => CurvePoint::Infinity,
+            CurvePoint::Point { x, y } => CurvePoint::Point { x, y: y.neg() },
+        }
+    }
+
+    pub fn add(self, other: Self) -> Self {
+        Jacobian::from_affine(self).add_affine(other).to_affine()
+    }
+
+    pub fn double(self) -> Self {
+        Jacobian::from_affine(self).double().to_affine()
+    }
+
+    // y^2 = x^3 + b
+    pub fn is_on_curve(self) -> bool {
+        match self {
+            CurvePoint::Infinity => true,
+            CurvePoint::Point { x, y } => y.mul(y) == x.mul(x).mul(x).add(C::B),
+        }
+    }
+
+    // k * self, with k in little-endian limbs
+    pub fn mul_limbs(self, k: &[u64]) -> Self {
+        let mut result = Jacobian::INFINITY;
+        for &limb in k.iter().rev() {
+            for i in (0..64).rev() {
+                result = result.double();
+                if (limb >> i) & 1 == 1 {
+                    result = result.add_affine(self);
+                }
+            }
+        }
+        result.to_affine()
+    }
+
+    pub fn mul(self, k: Fr) -> Self {
+        self.mul_limbs(&k.to_raw())
+    }
+
+    // [r]P = O. Every G1 point passes, G2 has a large cofactor.
+    pub fn is_in_prime_subgroup(self) -> bool {
+        self.is_on_curve() && self.mul_limbs(&FrParams::MODULUS) == CurvePoint::Infinity
+    }
+}
+
+// (X, Y, Z) for the affine point (X / Z^2, Y / Z^3), Z = 0 at infinity
+#[derive(Clone, Copy, Debug)]
+pub struct Jacobian<C: BnCurve> {
+    x: C::Base,
+    y: C::Base,
+    z: C::Base,
+}
+
+impl<C: BnCurve> Jacobian<C> {
+    const INFINITY: Jacobian<C> = Jacobian {
+        x: C::Base::ONE,
+        y: C::Base::ONE,
+        z: C::Base::ZERO,
+    };
+
+    fn from_affine(p: CurvePoint<C>) -> Self {
+        match p {
+            CurvePoint::Infinity => Jacobian::INFINITY,
+            CurvePoint::Point { x, y } => Jacobian {
+                x,
+                y,
+                z: C::Base::ONE,
+            },
+        }
+    }
+
+    fn to_affine(self) -> CurvePoint<C> {
+        match self.z.inv() {
+            None => CurvePoint::Infinity,
+            Some(z_inv) => {
+                let z_inv2 = z_inv.mul(z_inv);
+                CurvePoint::Point {
+                    x: self.x.mul(z_inv2),
+                    y: self.y.mul(z_inv2).mul(z_inv),
+                }
+            }
+        }
+    }
+
+    fn is_infinity(self) -> bool {
+        self.z == C::Base::ZERO
+    }
+
+    fn double(self) -> Self {
+        if self.is_infinity() {
+            return self;
+        }
+        let a = self.x.mul(self.x);
+        let b = self.y.mul(self.y);
+        let c = b.mul(b);
+        let x_plus_b = self.x.add(b);
+        let d = x_plus_b.mul(x_plus_b).sub(a).sub(c);
+        let d = d.add(d);
+        let e = a.add(a).add(a);
+        let f = e.mul(e);
+
+        let x3 = f.sub(d).sub(d);
+        let c2 = c.add(c);
+        let c4 = c2.add(c2);
+        let c8 = c4.add(c4);
+        let y3 = e.mul(d.sub(x3)).sub(c8);
+        let yz = self.y.mul(self.z);
+        Jacobian {
+            x: x3,
+            y: y3,
+            z: yz.add(yz),
+        }
+    }
+
+    // add-2007-bl
+    fn add(self, other: Self) -> Self {
+        if self.is_infinity() {
+            return other;
+        }
+        if other.is_infinity() {
+            return self;
+        }
+        let z1z1 = self.z.mul(self.z);
+        let z2z2 = other.z.mul(other.z);
+        let u1 = self.x.mul(z2z2);
+        let u2 = other.x.mul(z1z1);
+        let s1 = self.y.mul(other.z).mul(z2z2);
+        let s2 = other.y.mul(self.z).mul(z1z1);
+        let h = u2.sub(u1);
+        let r = s2.sub(s1);
+        if h == C::Base::ZERO {
+            return if r == C::Base::ZERO {
+                self.double()
+            } else {
+                Jacobian::INFINITY
+            };
+        }
+
+        let h2 = h.add(h);
+        let i = h2.mul(h2);
+        let j = h.mul(i);
+        let r = r.add(r);
+        let v = u1.mul(i);
+        let x3 = r.mul(r).sub(j).sub(v).sub(v);
+        let s1_j = s1.mul(j);
+        let y3 = r.mul(v.sub(x3)).sub(s1_j).sub(s1_j);
+        let z1_plus_z2 = self.z.add(other.z);
+        let z3 = z1_plus_z2.mul(z1_plus_z2).sub(z1z1).sub(z2z2).mul(h);
+        Jacobian {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    fn add_affine(self, other: CurvePoint<C>) -> Self {
+        let (x2, y2) = match other {
+            CurvePoint::Infinity => return self,
+            CurvePoint::Point { x, y } => (x, y),
+        };
+        if self.is_infinity() {
+            return Jacobian::from_affine(other);
+        }
+        let z1z1 = self.z.mul(self.z);
+        let u2 = x2.mul(z1z1);
+        let s2 = y2.mul(self.z).mul(z1z1);
+        let h = u2.sub(self.x);
+        let r = s2.sub(self.y);
+        if h == C::Base::ZERO {
+            return if r == C::Base::ZERO {
+                self.double()
+            } else {
+                Jacobian::INFINITY
+            };
+        }
+
+        let hh = h.mul(h);
+        let i = hh.add(hh).add(hh.add(hh));
+        let j = h.mul(i);
+        let r = r.add(r);
+        let v = self.x.mul(i);
+        let x3 = r.mul(r).sub(j).sub(v).sub(v);
+        let y1_j = self.y.mul(j);
+        let y3 = r.mul(v.sub(x3)).sub(y1_j).sub(y1_j);
+        let z1_plus_h = self.z.add(h);
+        let z3 = z1_plus_h.mul(z1_plus_h).sub(z1z1).sub(hh);
+        Jacobian {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+}
+
+// Looked up by index, also with the `constant-time` feature
+impl<C: BnCurve> TablePoint for CurvePoint<C> {}
+
+impl<C: BnCurve> MsmPoint for CurvePoint<C> {
+    type Projective = Jacobian<C>;
+
+    const IDENTITY: Jacobian<C> = Jacobian::INFINITY;
+
+    fn add_affine(acc: Jacobian<C>, point: Self) -> Jacobian<C> {
+        acc.add_affine(point)
+    }
+
+    fn add(a: Jacobian<C>, b: Jacobian<C>) -> Jacobian<C> {
+        a.add(b)
+    }
+
+    fn double(a: Jacobian<C>) -> Jacobian<C> {
+        a.double()
+    }
+
+    fn to_affine(a: Jacobian<C>) -> Self {
+        a.to_affine()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_group<C: BnCurve>() {
+        let g = CurvePoint::<C>::generator();
+        assert!(g.is_on_curve());
+        assert!(g.is_in_prime_subgroup());
+        assert_eq!(g.add(g.neg()), CurvePoint::Infinity);
+        assert_eq!(g.add(g), g.double());
+        assert_eq!(g.mul(Fr::from_u64(3)), g.double().add(g));
+
+        let a = Fr::from_u64(0x1234_5678_9abc);
+        let b = Fr::from_decimal("98765432109876543210987654321");
+        // (a + b)G = aG + bG and (ab)G = a(bG)
+        assert_eq!(g.mul(a.add(b)), g.mul(a).add(g.mul(b)));
+        assert_eq!(g.mul(a.mul(b)), g.mul(b).mul(a));
+        assert!(g.mul(a).is_on_curve());
+        // r - 1 = -1
+        assert_eq!(g.mul(Fr::ONE.neg()), g.neg());
+        assert_eq!(g.mul(Fr::ZERO), CurvePoint::Infinity);
+    }
+
+    #[test]
+    fn g1_test() {
+        check_group::<G1Curve>();
+        let off_curve = G1Point::Point {
+            x: Fq::from_u64(1),
+            y: Fq::from_u64(3),
+        };
+        assert!(!off_curve.is_in_prime_subgroup());
+    }
+
+    #[test]
+    fn g2_test() {
+        check_group::<G2Curve>();
+        // b' * xi = 3
+        let xi = Fq2::new(Fq::from_u64(9), Fq::ONE);
+        assert_eq!(G2Curve::B.mul(xi), Fq2::new(Fq::from_u64(3), Fq::ZERO));
+
+        // x = 1 is on the twist, but the twist has far more points than G2
+        let outside = G2Point::Point {
+            x: Fq2::ONE,
+            y: Fq2::new(
+                Fq::from_decimal(
+                    "18278151005453108793778860132295291098363647455926340152056652516292830556603",
+                ),
+                Fq::from_decimal(
+                    "5912654199736721486680175016176231956195085055698687135131307249486702594212",
+                ),
+            ),
+        };
+        assert!(outside.is_on_curve());
+        assert!(!outside.is_in_prime_subgroup());
+    }
+}
@@ -0,0 +1,57 @@
+// BN254 as a PLONK engine: Fr scalars, G1 and G2 from curve.rs and the optimal
+// ate pairing, so the toy prover and verifier run here unchanged.
+
+use std::sync::OnceLock;
+
+use crate::{
+    bn254::{
+        curve::{BnCurve, CurvePoint, G1Point, G2Point},
+        field::Fr,
+        field_extension::Fq12,
+        pairing::multi_pairing,
+    },
+    engine::{Engine, Group},
+    fixed_base::FixedBaseTable,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bn254;
+
+impl<C: BnCurve> Group<Fr> for CurvePoint<C> {
+    const IDENTITY: Self = CurvePoint::Infinity;
+
+    fn generator() -> Self {
+        CurvePoint::generator()
+    }
+
+    fn add(self, other: Self) -> Self {
+        CurvePoint::add(self, other)
+    }
+
+    fn neg(self) -> Self {
+        CurvePoint::neg(self)
+    }
+
+    fn mul(self, k: Fr) -> Self {
+        CurvePoint::mul(self, k)
+    }
+
+    fn is_valid(self) -> bool {
+        self.is_in_prime_subgroup()
+    }
+}
+
+impl Engine for Bn254 {
+    type Fr = Fr;
+    type G1 = G1Point;
+    type G2 = G2Point;
+
+    fn g1_table() -> &'static FixedBaseTable<G1Point> {
+        static TABLE: OnceLock<FixedBaseTable<G1Point>> = OnceLock::new();
+        TABLE.get_or_init(|| FixedBaseTable::for_scalars::<Fr>(G1Point::generator()))
+    }
+
+    fn pairing_product_is_one(pairs: &[(G1Point, G2Point)]) -> bool {
+        multi_pairing(pairs) == Fq12::ONE
+    }
+}
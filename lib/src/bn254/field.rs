@@ -0,0 +1,124 @@
+// The two 254-bit prime fields of BN254, as four-limb Montgomery fields.
+
+use crate::{
+    big_field::{BigPrimeField, BigPrimeParams},
+    field::PlonkField,
+    ntt::NttField,
+};
+
+// The BN254 base field, coordinates of G1 and G2 live here
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FqParams;
+
+impl BigPrimeParams<4> for FqParams {
+    // 21888242871839275222246405745257275088696311157297823662689037894645226208583
+    const MODULUS: [u64; 4] = [
+        0x3c208c16d87cfd47,
+        0x97816a916871ca8d,
+        0xb85045b68181585d,
+        0x30644e72e131a029,
+    ];
+}
+
+// The BN254 scalar field, the order of G1 and G2
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FrParams;
+
+impl BigPrimeParams<4> for FrParams {
+    // 21888242871839275222246405745257275088548364400416034343698204186575808495617
+    const MODULUS: [u64; 4] = [
+        0x43e1f593f0000001,
+        0x2833e84879b97091,
+        0xb85045b68181585d,
+        0x30644e72e131a029,
+    ];
+}
+
+pub type Fq = BigPrimeField<FqParams, 4>;
+pub type Fr = BigPrimeField<FrParams, 4>;
+
+// r - 1 = 2^28 * t with t odd, and 5 generates Fr^*
+const TWO_ADICITY: u32 = 28;
+
+// (r - 1) / 2^k, exact for k <= TWO_ADICITY
+fn r_minus_one_shr(k: u32) -> [u64; 4] {
+    let mut limbs = FrParams::MODULUS;
+    limbs[0] -= 1;
+    if k == 0 {
+        return limbs;
+    }
+    for i in 0..4 {
+        let high = if i < 3 { limbs[i + 1] << (64 - k) } else { 0 };
+        limbs[i] = (limbs[i] >> k) | high;
+    }
+    limbs
+}
+
+// Fr has every power-of-two subgroup up to 2^28, so it runs its own NTT
+impl NttField for Fr {
+    const MAX_DOMAIN_SIZE: usize = 1 << TWO_ADICITY;
+    const GENERATOR: Fr = Fr::from_u64(5);
+
+    fn root_of_unity(size: usize) -> Option<Fr> {
+        if !size.is_power_of_two() || size > Self::MAX_DOMAIN_SIZE {
+            return None;
+        }
+        Some(Self::GENERATOR.pow(&r_minus_one_shr(size.trailing_zeros())))
+    }
+}
+
+impl PlonkField for Fr {
+    type Ntt = Fr;
+
+    fn from_ntt(value: Fr) -> Option<Fr> {
+        Some(value)
+    }
+
+    fn root_of_unity(n: usize) -> Option<Fr> {
+        <Fr as NttField>::root_of_unity(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fq_test() {
+        let p_minus_1 = Fq::from_decimal(
+            "21888242871839275222246405745257275088696311157297823662689037894645226208582",
+        );
+        assert_eq!(p_minus_1, Fq::ONE.neg());
+        assert_eq!(p_minus_1.square(), Fq::ONE);
+        assert_eq!(Fq::from_decimal("12345").to_raw(), [12345, 0, 0, 0]);
+
+        let a = Fq::from_decimal("1234567890123456789012345678901234567890");
+        assert_eq!(a.mul(a.inv().unwrap()), Fq::ONE);
+        assert_eq!(Fq::ZERO.inv(), None);
+    }
+
+    #[test]
+    fn fr_test() {
+        let r_minus_1 = Fr::from_decimal(
+            "21888242871839275222246405745257275088548364400416034343698204186575808495616",
+        );
+        assert_eq!(r_minus_1, Fr::ONE.neg());
+        // 2^64 * 2^64 = 2^128
+        let two_64 = Fr::from_raw([0, 1, 0, 0]);
+        assert_eq!(two_64.square().to_raw(), [0, 0, 1, 0]);
+        let a = Fr::from_u64(3);
+        assert_eq!(a.mul(a.inv().unwrap()), Fr::ONE);
+    }
+
+    #[test]
+    fn fr_root_of_unity_test() {
+        let omega = <Fr as NttField>::root_of_unity(1 << 28).unwrap();
+        assert_eq!(omega.pow(&[1 << 27]), Fr::ONE.neg());
+        assert_eq!(<Fr as NttField>::root_of_unity(1 << 29), None);
+        assert_eq!(<Fr as NttField>::root_of_unity(6), None);
+
+        let h = Fr::domain(4);
+        assert_eq!(h[2], Fr::ONE.neg());
+        assert_eq!(h[1].pow(&[4]), Fr::ONE);
+    }
+}
@@ -0,0 +1,262 @@
+// The BN254 extension tower, built in steps of degree 2, 3 and 2:
+//
+//   Fq2  = Fq[u]  / (u^2 + 1)
+//   Fq6  = Fq2[v] / (v^3 - xi),  xi = 9 + u
+//   Fq12 = Fq6[w] / (w^2 - v)
+//
+// so w^6 = xi. G2 lives over Fq2 and pairings land in Fq12.
+
+use crate::bn254::field::Fq;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fq2 {
+    pub c0: Fq,
+    pub c1: Fq,
+}
+
+#[allow(clippy::should_implement_trait)]
+impl Fq2 {
+    pub const ZERO: Fq2 = Fq2::new(Fq::ZERO, Fq::ZERO);
+    pub const ONE: Fq2 = Fq2::new(Fq::ONE, Fq::ZERO);
+
+    pub const fn new(c0: Fq, c1: Fq) -> Self {
+        Fq2 { c0, c1 }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.c0.is_zero() && self.c1.is_zero()
+    }
+
+    pub fn add(self, other: Fq2) -> Fq2 {
+        Fq2::new(self.c0.add(other.c0), self.c1.add(other.c1))
+    }
+
+    pub fn sub(self, other: Fq2) -> Fq2 {
+        Fq2::new(self.c0.sub(other.c0), self.c1.sub(other.c1))
+    }
+
+    pub fn neg(self) -> Fq2 {
+        Fq2::new(self.c0.neg(), self.c1.neg())
+    }
+
+    pub fn double(self) -> Fq2 {
+        self.add(self)
+    }
+
+    // (a + bu)(c + du) = (ac - bd) + ((a + b)(c + d) - ac - bd)u
+    pub fn mul(self, other: Fq2) -> Fq2 {
+        let ac = self.c0.mul(other.c0);
+        let bd = self.c1.mul(other.c1);
+        let cross = self.c0.add(self.c1).mul(other.c0.add(other.c1));
+        Fq2::new(ac.sub(bd), cross.sub(ac).sub(bd))
+    }
+
+    pub fn square(self) -> Fq2 {
+        self.mul(self)
+    }
+
+    pub fn mul_by_fq(self, k: Fq) -> Fq2 {
+        Fq2::new(self.c0.mul(k), self.c1.mul(k))
+    }
+
+    // (a + bu)(9 + u) = (9a - b) + (a + 9b)u
+    pub fn mul_by_xi(self) -> Fq2 {
+        let nine = Fq::from_u64(9);
+        Fq2::new(
+            self.c0.mul(nine).sub(self.c1),
+            self.c0.add(self.c1.mul(nine)),
+        )
+    }
+
+    // a - bu, which is also the Frobenius map (a + bu)^p
+    pub fn conjugate(self) -> Fq2 {
+        Fq2::new(self.c0, self.c1.neg())
+    }
+
+    // 1 / (a + bu) = (a - bu) / (a^2 + b^2)
+    pub fn inv(self) -> Option<Fq2> {
+        let norm_inv = self.c0.square().add(self.c1.square()).inv()?;
+        Some(self.conjugate().mul_by_fq(norm_inv))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fq6 {
+    pub c0: Fq2,
+    pub c1: Fq2,
+    pub c2: Fq2,
+}
+
+#[allow(clippy::should_implement_trait)]
+impl Fq6 {
+    pub const ZERO: Fq6 = Fq6::new(Fq2::ZERO, Fq2::ZERO, Fq2::ZERO);
+    pub const ONE: Fq6 = Fq6::new(Fq2::ONE, Fq2::ZERO, Fq2::ZERO);
+
+    pub const fn new(c0: Fq2, c1: Fq2, c2: Fq2) -> Self {
+        Fq6 { c0, c1, c2 }
+    }
+
+    pub fn add(self, other: Fq6) -> Fq6 {
+        Fq6::new(
+            self.c0.add(other.c0),
+            self.c1.add(other.c1),
+            self.c2.add(other.c2),
+        )
+    }
+
+    pub fn sub(self, other: Fq6) -> Fq6 {
+        Fq6::new(
+            self.c0.sub(other.c0),
+            self.c1.sub(other.c1),
+            self.c2.sub(other.c2),
+        )
+    }
+
+    pub fn neg(self) -> Fq6 {
+        Fq6::new(self.c0.neg(), self.c1.neg(), self.c2.neg())
+    }
+
+    // Schoolbook, with v^3 = xi folding the degree 3 and 4 terms back down
+    pub fn mul(self, other: Fq6) -> Fq6 {
+        let (a0, a1, a2) = (self.c0, self.c1, self.c2);
+        let (b0, b1, b2) = (other.c0, other.c1, other.c2);
+        Fq6::new(
+            a0.mul(b0).add(a1.mul(b2).add(a2.mul(b1)).mul_by_xi()),
+            a0.mul(b1).add(a1.mul(b0)).add(a2.mul(b2).mul_by_xi()),
+            a0.mul(b2).add(a1.mul(b1)).add(a2.mul(b0)),
+        )
+    }
+
+    // v * (c0 + c1 v + c2 v^2) = xi c2 + c0 v + c1 v^2
+    pub fn mul_by_v(self) -> Fq6 {
+        Fq6::new(self.c2.mul_by_xi(), self.c0, self.c1)
+    }
+
+    pub fn inv(self) -> Option<Fq6> {
+        let (a0, a1, a2) = (self.c0, self.c1, self.c2);
+        let t0 = a0.square().sub(a1.mul(a2).mul_by_xi());
+        let t1 = a2.square().mul_by_xi().sub(a0.mul(a1));
+        let t2 = a1.square().sub(a0.mul(a2));
+        // the norm down to Fq2
+        let norm = a0.mul(t0).add(a2.mul(t1).add(a1.mul(t2)).mul_by_xi());
+        let norm_inv = norm.inv()?;
+        Some(Fq6::new(
+            t0.mul(norm_inv),
+            t1.mul(norm_inv),
+            t2.mul(norm_inv),
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fq12 {
+    pub c0: Fq6,
+    pub c1: Fq6,
+}
+
+#[allow(clippy::should_implement_trait)]
+impl Fq12 {
+    pub const ZERO: Fq12 = Fq12::new(Fq6::ZERO, Fq6::ZERO);
+    pub const ONE: Fq12 = Fq12::new(Fq6::ONE, Fq6::ZERO);
+
+    pub const fn new(c0: Fq6, c1: Fq6) -> Self {
+        Fq12 { c0, c1 }
+    }
+
+    pub fn add(self, other: Fq12) -> Fq12 {
+        Fq12::new(self.c0.add(other.c0), self.c1.add(other.c1))
+    }
+
+    pub fn sub(self, other: Fq12) -> Fq12 {
+        Fq12::new(self.c0.sub(other.c0), self.c1.sub(other.c1))
+    }
+
+    // (a0 + a1 w)(b0 + b1 w) = (a0 b0 + a1 b1 v) + (a0 b1 + a1 b0) w, Karatsuba style
+    pub fn mul(self, other: Fq12) -> Fq12 {
+        let a0b0 = self.c0.mul(other.c0);
+        let a1b1 = self.c1.mul(other.c1);
+        let cross = self.c0.add(self.c1).mul(other.c0.add(other.c1));
+        Fq12::new(a0b0.add(a1b1.mul_by_v()), cross.sub(a0b0).sub(a1b1))
+    }
+
+    pub fn square(self) -> Fq12 {
+        self.mul(self)
+    }
+
+    // a0 - a1 w, which is the p^6-th power
+    pub fn conjugate(self) -> Fq12 {
+        Fq12::new(self.c0, self.c1.neg())
+    }
+
+    // 1 / (a0 + a1 w) = (a0 - a1 w) / (a0^2 - a1^2 v)
+    pub fn inv(self) -> Option<Fq12> {
+        let norm = self.c0.mul(self.c0).sub(self.c1.mul(self.c1).mul_by_v());
+        let norm_inv = Fq12::new(norm.inv()?, Fq6::ZERO);
+        Some(self.conjugate().mul(norm_inv))
+    }
+
+    // self^exp, with exp in little-endian limbs
+    pub fn pow(self, exp: &[u64]) -> Fq12 {
+        let mut result = Fq12::ONE;
+        for &limb in exp.iter().rev() {
+            for i in (0..64).rev() {
+                result = result.square();
+                if (limb >> i) & 1 == 1 {
+                    result = result.mul(self);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fq2(c0: u64, c1: u64) -> Fq2 {
+        Fq2::new(Fq::from_u64(c0), Fq::from_u64(c1))
+    }
+
+    fn sample_fq6(seed: u64) -> Fq6 {
+        Fq6::new(fq2(seed, seed + 1), fq2(seed * 7, 3), fq2(11, seed * seed))
+    }
+
+    #[test]
+    fn fq2_test() {
+        let u = fq2(0, 1);
+        assert_eq!(u.square(), Fq2::ONE.neg());
+        let a = fq2(5, 12);
+        assert_eq!(a.mul(a.inv().unwrap()), Fq2::ONE);
+        assert_eq!(a.mul_by_xi(), a.mul(fq2(9, 1)));
+        assert_eq!(Fq2::ZERO.inv(), None);
+    }
+
+    #[test]
+    fn fq6_test() {
+        let v = Fq6::new(Fq2::ZERO, Fq2::ONE, Fq2::ZERO);
+        let xi = Fq6::new(fq2(9, 1), Fq2::ZERO, Fq2::ZERO);
+        assert_eq!(v.mul(v).mul(v), xi);
+
+        let a = sample_fq6(3);
+        let b = sample_fq6(10);
+        assert_eq!(a.mul_by_v(), a.mul(v));
+        assert_eq!(a.mul(b), b.mul(a));
+        assert_eq!(a.mul(a.inv().unwrap()), Fq6::ONE);
+        assert_eq!(a.mul(b.add(v)), a.mul(b).add(a.mul(v)));
+    }
+
+    #[test]
+    fn fq12_test() {
+        let w = Fq12::new(Fq6::ZERO, Fq6::ONE);
+        let v = Fq12::new(Fq6::new(Fq2::ZERO, Fq2::ONE, Fq2::ZERO), Fq6::ZERO);
+        assert_eq!(w.square(), v);
+
+        let a = Fq12::new(sample_fq6(2), sample_fq6(5));
+        let b = Fq12::new(sample_fq6(8), sample_fq6(1));
+        assert_eq!(a.mul(b), b.mul(a));
+        assert_eq!(a.mul(a.inv().unwrap()), Fq12::ONE);
+        assert_eq!(a.pow(&[5]), a.square().square().mul(a));
+        assert_eq!(a.sub(a), Fq12::ZERO);
+    }
+}
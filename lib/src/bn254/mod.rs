@@ -0,0 +1,16 @@
+// BN254 (alt_bn128), a real-size pairing-friendly curve, behind the `bn254` feature.
+//
+// With u = 4965661367192848881 the curve has 254-bit primes
+//   p = 36u^4 + 36u^3 + 24u^2 + 6u + 1   (base field Fq)
+//   r = 36u^4 + 36u^3 + 18u^2 + 6u + 1   (group order, scalar field Fr)
+// and embedding degree 12. Both fields are four-limb big_field::BigPrimeField
+// instances, and nothing comes from outside crates.
+//
+// engine::Bn254 plugs these into crate::engine::Engine, so the SRS, KZG, the
+// PLONK rounds and the verifier run over BN254 as they do over the toy curve.
+
+pub mod curve;
+pub mod engine;
+pub mod field;
+pub mod field_extension;
+pub mod pairing;
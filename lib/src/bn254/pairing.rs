@@ -0,0 +1,233 @@
+// Optimal ate pairing e: G1 x G2 -> Fq12 on BN254.
+//
+// e(P, Q) = (f_{6u+2,Q}(P) * l_{T,pi(Q)}(P) * l_{T',-pi^2(Q)}(P))^((p^12 - 1) / r)
+//
+// where T = [6u+2]Q, T' = T + pi(Q) and pi is the p-power Frobenius. Q stays on
+// the twist, and each line through twist points is mapped into Fq12 by the
+// untwisting (x, y) -> (x w^2, y w^3). Vertical lines lie in Fq6, which the
+// final exponentiation sends to 1, so they are dropped.
+
+use crate::bn254::{
+    curve::{CurvePoint, G1Point, G2Point},
+    field::Fq,
+    field_extension::{Fq12, Fq2, Fq6},
+};
+
+// 6u + 2 for u = 4965661367192848881
+const ATE_LOOP_COUNT: u128 = 29793968203157093288;
+
+// xi^((p - 1) / 3) and xi^((p - 1) / 2), the twisted Frobenius on x and y
+const FROBENIUS_X: Fq2 = Fq2::new(
+    Fq::from_decimal(
+        "21575463638280843010398324269430826099269044274347216827212613867836435027261",
+    ),
+    Fq::from_decimal(
+        "10307601595873709700152284273816112264069230130616436755625194854815875713954",
+    ),
+);
+const FROBENIUS_Y: Fq2 = Fq2::new(
+    Fq::from_decimal(
+        "2821565182194536844548159561693502659359617185244120367078079554186484126554",
+    ),
+    Fq::from_decimal(
+        "3505843767911556378687030309984248845540243509899259641013678093033130930403",
+    ),
+);
+
+// (p^6 + 1) / r in little-endian limbs. Together with the p^6 - 1 taken by
+// conjugation this is the full (p^12 - 1) / r.
+const FINAL_EXPONENT: [u64; 20] = [
+    0x5250a54036e3f812,
+    0xa5635f1596789051,
+    0xd1138bf54d5bd1d4,
+    0xa8ce2533be36c7a2,
+    0x94f69f6b84e09bf6,
+    0x42ad1f5e50ef3644,
+    0x0fcc420e48c3454c,
+    0x758e4408ecc9952c,
+    0xc901bf1887c6042c,
+    0xa733cd65b14bb3b5,
+    0xdf6d76bdcf51b0d8,
+    0xca64c0fd82eb59e1,
+    0x1d2e5726e39276a1,
+    0xc2d1ea74a391cae9,
+    0x07409206c82d647e,
+    0x051c6d1aa5afdd17,
+    0xb37f601919667af5,
+    0x150e578c5084015b,
+    0xfbdea556c23998e4,
+    0x000fd14cc52f5b83,
+];
+
+// pi(Q) on the twist: (conj(x) xi^((p - 1) / 3), conj(y) xi^((p - 1) / 2))
+fn frobenius(q: G2Point) -> G2Point {
+    match q {
+        CurvePoint::Infinity => CurvePoint::Infinity,
+        CurvePoint::Point { x, y } => CurvePoint::Point {
+            x: x.conjugate().mul(FROBENIUS_X),
+            y: y.conjugate().mul(FROBENIUS_Y),
+        },
+    }
+}
+
+// Slope of the line through t and q (tangent when t = q), None for vertical lines
+fn slope(t: (Fq2, Fq2), q: (Fq2, Fq2)) -> Option<Fq2> {
+    let ((x1, y1), (x2, y2)) = (t, q);
+    if x1 == x2 {
+        if y1 != y2 || y1.is_zero() {
+            return None;
+        }
+        // 3x^2 / 2y
+        let x_squared = x1.square();
+        Some(x_squared.double().add(x_squared).mul(y1.double().inv()?))
+    } else {
+        Some(y2.sub(y1).mul(x2.sub(x1).inv()?))
+    }
+}
+
+// y_P - m x_P w + (m x_T - y_T) w^3, the untwisted line through t with slope m at p
+fn line(t: (Fq2, Fq2), m: Fq2, p: (Fq, Fq)) -> Fq12 {
+    let (x_t, y_t) = t;
+    let (x_p, y_p) = p;
+    Fq12::new(
+        Fq6::new(Fq2::new(y_p, Fq::ZERO), Fq2::ZERO, Fq2::ZERO),
+        Fq6::new(m.mul_by_fq(x_p).neg(), m.mul(x_t).sub(y_t), Fq2::ZERO),
+    )
+}
+
+// Multiplies the line through t and q into f, and returns t + q along with it
+fn line_step(f: Fq12, t: G2Point, q: G2Point, p: (Fq, Fq)) -> (Fq12, G2Point) {
+    if let (CurvePoint::Point { x: x1, y: y1 }, CurvePoint::Point { x: x2, y: y2 }) = (t, q) {
+        if let Some(m) = slope((x1, y1), (x2, y2)) {
+            return (f.mul(line((x1, y1), m, p)), t.add(q));
+        }
+    }
+    (f, t.add(q))
+}
+
+fn miller_loop(p: (Fq, Fq), q: G2Point) -> Fq12 {
+    let mut f = Fq12::ONE;
+    let mut t = q;
+    let bits = u128::BITS - ATE_LOOP_COUNT.leading_zeros();
+    for i in (0..bits - 1).rev() {
+        (f, t) = line_step(f.square(), t, t, p);
+        if (ATE_LOOP_COUNT >> i) & 1 == 1 {
+            (f, t) = line_step(f, t, q, p);
+        }
+    }
+    let q1 = frobenius(q);
+    let q2 = frobenius(q1).neg();
+    (f, t) = line_step(f, t, q1, p);
+    (f, _) = line_step(f, t, q2, p);
+    f
+}
+
+fn final_exponentiation(f: Fq12) -> Fq12 {
+    // f^(p^6 - 1), conjugation being the p^6-th power
+    let f = f
+        .conjugate()
+        .mul(f.inv().expect("Miller loop output is non-zero"));
+    f.pow(&FINAL_EXPONENT)
+}
+
+pub fn pairing(p: G1Point, q: G2Point) -> Fq12 {
+    multi_pairing(&[(p, q)])
+}
+
+// prod e(p_i, q_i), sharing one final exponentiation across all the Miller loops
+pub fn multi_pairing(pairs: &[(G1Point, G2Point)]) -> Fq12 {
+    let mut f = Fq12::ONE;
+    for &(p, q) in pairs {
+        if let (CurvePoint::Point { x, y }, CurvePoint::Point { .. }) = (p, q) {
+            f = f.mul(miller_loop((x, y), q));
+        }
+    }
+    final_exponentiation(f)
+}
+
+// Checks e(p1, q1) = e(p2, q2), as e(p1, q1) * e(-p2, q2) = 1
+pub fn pairing_check(p1: G1Point, q1: G2Point, p2: G1Point, q2: G2Point) -> bool {
+    multi_pairing(&[(p1, q1), (p2.neg(), q2)]) == Fq12::ONE
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        big_field::BigPrimeParams,
+        bn254::field::{FqParams, Fr, FrParams},
+    };
+
+    use super::*;
+
+    #[test]
+    fn frobenius_test() {
+        // G2 is the eigenspace of pi with eigenvalue p
+        let q = G2Point::generator().mul(Fr::from_u64(1234567));
+        assert_eq!(frobenius(q), q.mul_limbs(&FqParams::MODULUS));
+        assert!(frobenius(q).is_in_prime_subgroup());
+    }
+
+    #[test]
+    fn pairing_test() {
+        let e = pairing(G1Point::generator(), G2Point::generator());
+        assert_ne!(e, Fq12::ONE);
+        // e lands in the order r subgroup of Fq12
+        assert_eq!(e.pow(&FrParams::MODULUS), Fq12::ONE);
+        assert_eq!(pairing(G1Point::Infinity, G2Point::generator()), Fq12::ONE);
+    }
+
+    #[test]
+    fn bilinearity_test() {
+        let g1 = G1Point::generator();
+        let g2 = G2Point::generator();
+        let e = pairing(g1, g2);
+        let a = Fr::from_decimal("1234567890123456789012345678901234567890");
+        let b = Fr::from_u64(0xdead_beef);
+        assert_eq!(pairing(g1.mul(a), g2.mul(b)), e.pow(&a.mul(b).to_raw()));
+        assert_eq!(pairing(g1.mul(a), g2), pairing(g1, g2.mul(a)));
+        assert!(pairing_check(g1.mul(a.mul(b)), g2, g1.mul(a), g2.mul(b)));
+        assert!(!pairing_check(g1.mul(a), g2, g1.mul(b), g2));
+    }
+
+    #[test]
+    fn kzg_opening_test() {
+        // f(x) = 3 + 5x + 7x^2 opened at z = 11 with a fixed toxic tau
+        let g1 = G1Point::generator();
+        let g2 = G2Point::generator();
+        let tau = Fr::from_decimal("987654321987654321987654321");
+        let f = |x: Fr| {
+            Fr::from_u64(3)
+                .add(Fr::from_u64(5).mul(x))
+                .add(Fr::from_u64(7).mul(x.square()))
+        };
+        let z = Fr::from_u64(11);
+        // (f(x) - f(z)) / (x - z) = 5 + 7z + 7x
+        let q = |x: Fr| {
+            Fr::from_u64(5)
+                .add(Fr::from_u64(7).mul(z))
+                .add(Fr::from_u64(7).mul(x))
+        };
+
+        let commitment = g1.mul(f(tau));
+        let proof = g1.mul(q(tau));
+        // e(C - f(z) G1, G2) = e(W, tau G2 - z G2)
+        let lhs = commitment.add(g1.mul(f(z)).neg());
+        let rhs = g2.mul(tau).add(g2.mul(z).neg());
+        assert!(pairing_check(lhs, g2, proof, rhs));
+        assert!(!pairing_check(commitment, g2, proof, rhs));
+    }
+
+    #[test]
+    fn multi_pairing_test() {
+        let g1 = G1Point::generator();
+        let g2 = G2Point::generator();
+        let pairs = [
+            (g1.mul(Fr::from_u64(3)), g2.mul(Fr::from_u64(5))),
+            (g1.mul(Fr::from_u64(7)), g2),
+            (G1Point::Infinity, g2),
+        ];
+        // 3 * 5 + 7 = 22
+        assert_eq!(multi_pairing(&pairs), pairing(g1, g2).pow(&[22]));
+        assert_eq!(multi_pairing(&[]), Fq12::ONE);
+    }
+}
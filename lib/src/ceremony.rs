@@ -0,0 +1,371 @@
+// Powers-of-tau trusted setup.
+//
+// The ceremony starts from tau = 1 (every G1 power is G1) and each participant
+// multiplies a secret tau_i into all powers: tau^j * G1 becomes (tau * tau_i)^j * G1.
+// No single participant learns the final tau = tau_1 * tau_2 * ..., so the SRS is
+// sound as long as one of them throws their secret away.
+//
+// Every contribution publishes tau_i * G1, tau_i * G2 and a Schnorr proof of
+// knowledge of tau_i, plus the running tau * G1 so the chain can be replayed
+// with pairings.
+
+use std::{fmt, fs, io, path::Path};
+
+use crate::{
+    fiat_shamir::FiatShamir,
+    field::{Point, F17, G1},
+    field_extension::{G2Point, G2},
+    fixed_base::g1_table,
+    pairing::pairing_check,
+    serialize::{Compressed, Decode, DecodeError, Encode, Reader},
+    srs::SRS,
+};
+
+pub const CEREMONY_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Contribution {
+    pub tau_g1: Point,
+    pub tau_g2: G2Point,
+    // tau * G1 after this contribution
+    pub running_tau_g1: Point,
+    // Schnorr proof of knowledge of tau_i: R = k * G1, s = k + c * tau_i
+    pub pok_r: Point,
+    pub pok_s: F17,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ceremony {
+    // tau^j * G1 for j = 0..=max_degree
+    pub g1_powers: Vec<Point>,
+    // G2, tau * G2
+    pub g2_powers: [G2Point; 2],
+    pub contributions: Vec<Contribution>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CeremonyError {
+    ZeroSecret,
+    WrongGenerator,
+    InvalidProofOfKnowledge { contribution: usize },
+    InconsistentContribution { contribution: usize },
+    InconsistentPowers { index: usize },
+    Decode(DecodeError),
+    Io(io::ErrorKind),
+}
+
+impl fmt::Display for CeremonyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CeremonyError::ZeroSecret => write!(f, "the secret contribution must be non-zero"),
+            CeremonyError::WrongGenerator => write!(f, "the powers do not start at G1 and G2"),
+            CeremonyError::InvalidProofOfKnowledge { contribution } => {
+                write!(
+                    f,
+                    "contribution {} has an invalid proof of knowledge",
+                    contribution
+                )
+            }
+            CeremonyError::InconsistentContribution { contribution } => {
+                write!(
+                    f,
+                    "contribution {} does not extend the previous one",
+                    contribution
+                )
+            }
+            CeremonyError::InconsistentPowers { index } => {
+                write!(f, "power {} is not tau times the previous power", index)
+            }
+            CeremonyError::Decode(err) => write!(f, "malformed transcript: {}", err),
+            CeremonyError::Io(kind) => write!(f, "i/o error: {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for CeremonyError {}
+
+impl From<DecodeError> for CeremonyError {
+    fn from(err: DecodeError) -> Self {
+        CeremonyError::Decode(err)
+    }
+}
+
+impl From<io::Error> for CeremonyError {
+    fn from(err: io::Error) -> Self {
+        CeremonyError::Io(err.kind())
+    }
+}
+
+fn pok_challenge(tau_g1: Point, previous: Point, pok_r: Point) -> F17 {
+    let mut fs = FiatShamir::new(b"ceremony-pok");
+    fs.absorb(&G1);
+    fs.absorb(&tau_g1);
+    fs.absorb(&previous);
+    fs.absorb(&pok_r);
+    fs.challenge()
+}
+
+// Deterministic nonce derived from the secret, so contributing needs no randomness
+fn pok_nonce(secret: F17, previous: Point) -> F17 {
+    let mut fs = FiatShamir::new(b"ceremony-nonce");
+    fs.absorb(&secret);
+    fs.absorb(&previous);
+    let k = fs.challenge();
+    if k == F17::ZERO {
+        F17::ONE
+    } else {
+        k
+    }
+}
+
+impl Ceremony {
+    pub fn new(max_degree: usize) -> Self {
+        Ceremony {
+            g1_powers: vec![G1; max_degree + 1],
+            g2_powers: [G2, G2],
+            contributions: Vec::new(),
+        }
+    }
+
+    pub fn running_tau_g1(&self) -> Point {
+        self.contributions
+            .last()
+            .map_or(G1, |contribution| contribution.running_tau_g1)
+    }
+
+    pub fn contribute(&mut self, secret: F17) -> Result<(), CeremonyError> {
+        if secret == F17::ZERO {
+            return Err(CeremonyError::ZeroSecret);
+        }
+        let previous = self.running_tau_g1();
+
+        for (j, power) in self.g1_powers.iter_mut().enumerate() {
+            *power = power.mul(secret.pow(j as u32));
+        }
+        self.g2_powers[1] = self.g2_powers[1].mul(secret);
+
+        let tau_g1 = g1_table().mul(secret);
+        let k = pok_nonce(secret, previous);
+        let pok_r = g1_table().mul(k);
+        let c = pok_challenge(tau_g1, previous, pok_r);
+        self.contributions.push(Contribution {
+            tau_g1,
+            tau_g2: G2.mul(secret),
+            running_tau_g1: previous.mul(secret),
+            pok_r,
+            pok_s: k.add(c.mul(secret)),
+        });
+        Ok(())
+    }
+
+    pub fn verify(&self) -> Result<(), CeremonyError> {
+        if self.g1_powers.first() != Some(&G1) || self.g2_powers[0] != G2 {
+            return Err(CeremonyError::WrongGenerator);
+        }
+
+        let mut previous = G1;
+        for (i, contribution) in self.contributions.iter().enumerate() {
+            let c = pok_challenge(contribution.tau_g1, previous, contribution.pok_r);
+            if contribution.tau_g1 == Point::Infinity
+                || g1_table().mul(contribution.pok_s)
+                    != contribution.pok_r.add(contribution.tau_g1.mul(c))
+            {
+                return Err(CeremonyError::InvalidProofOfKnowledge { contribution: i });
+            }
+            // tau_g1 and tau_g2 share tau_i, and running = tau_i * previous
+            if !pairing_check(contribution.tau_g1, G2, G1, contribution.tau_g2)
+                || !pairing_check(
+                    contribution.running_tau_g1,
+                    G2,
+                    previous,
+                    contribution.tau_g2,
+                )
+            {
+                return Err(CeremonyError::InconsistentContribution { contribution: i });
+            }
+            previous = contribution.running_tau_g1;
+        }
+
+        // the published powers must be the powers of the final tau
+        if let Some(&tau_g1) = self.g1_powers.get(1) {
+            if tau_g1 != previous {
+                return Err(CeremonyError::InconsistentPowers { index: 1 });
+            }
+        }
+        if !pairing_check(previous, G2, G1, self.g2_powers[1]) {
+            return Err(CeremonyError::InconsistentPowers { index: 1 });
+        }
+        // e(tau^i * G1, tau * G2) = e(tau^(i+1) * G1, G2)
+        for (i, pair) in self.g1_powers.windows(2).enumerate() {
+            if !pairing_check(pair[0], self.g2_powers[1], pair[1], G2) {
+                return Err(CeremonyError::InconsistentPowers { index: i + 1 });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn srs(&self) -> SRS {
+        SRS {
+            g1_points: self.g1_powers.clone(),
+            g2_points: self.g2_powers.to_vec(),
+        }
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), CeremonyError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    // Only decodes the transcript, call `verify` before trusting it
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<Ceremony, CeremonyError> {
+        let bytes = fs::read(path)?;
+        Ok(Ceremony::from_bytes(&bytes)?)
+    }
+}
+
+impl Encode for Contribution {
+    fn encode(&self, out: &mut Vec<u8>) {
+        Compressed(self.tau_g1).encode(out);
+        Compressed(self.tau_g2).encode(out);
+        Compressed(self.running_tau_g1).encode(out);
+        Compressed(self.pok_r).encode(out);
+        self.pok_s.encode(out);
+    }
+}
+
+impl Decode for Contribution {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(Contribution {
+            tau_g1: Compressed::<Point>::decode(reader)?.0,
+            tau_g2: Compressed::<G2Point>::decode(reader)?.0,
+            running_tau_g1: Compressed::<Point>::decode(reader)?.0,
+            pok_r: Compressed::<Point>::decode(reader)?.0,
+            pok_s: F17::decode(reader)?,
+        })
+    }
+}
+
+// version, #G1 powers (u32 LE), G1 powers, the two G2 powers,
+// #contributions (u32 LE), contributions. Points are compressed.
+impl Encode for Ceremony {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(CEREMONY_VERSION);
+        out.extend((self.g1_powers.len() as u32).to_le_bytes());
+        for power in &self.g1_powers {
+            Compressed(*power).encode(out);
+        }
+        for power in &self.g2_powers {
+            Compressed(*power).encode(out);
+        }
+        out.extend((self.contributions.len() as u32).to_le_bytes());
+        for contribution in &self.contributions {
+            contribution.encode(out);
+        }
+    }
+}
+
+impl Decode for Ceremony {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let version = reader.read_u8()?;
+        if version != CEREMONY_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let g1_powers = (0..reader.read_u32()?)
+            .map(|_| Compressed::<Point>::decode(reader).map(|c| c.0))
+            .collect::<Result<Vec<_>, _>>()?;
+        let g2_powers = [
+            Compressed::<G2Point>::decode(reader)?.0,
+            Compressed::<G2Point>::decode(reader)?.0,
+        ];
+        let contributions = (0..reader.read_u32()?)
+            .map(|_| Contribution::decode(reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Ceremony {
+            g1_powers,
+            g2_powers,
+            contributions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::srs::get_srs;
+
+    use super::*;
+
+    fn three_party_ceremony() -> Ceremony {
+        let mut ceremony = Ceremony::new(6);
+        for secret in [2, 5, 7] {
+            ceremony.contribute(F17(secret)).unwrap();
+        }
+        ceremony
+    }
+
+    #[test]
+    fn ceremony_test() {
+        assert_eq!(Ceremony::new(6).verify(), Ok(()));
+
+        let ceremony = three_party_ceremony();
+        assert_eq!(ceremony.verify(), Ok(()));
+
+        // 2 * 5 * 7 = 70 = 2 mod 17, the same tau as get_srs
+        let srs = ceremony.srs();
+        assert_eq!(srs.g1_points, get_srs().g1_points);
+        assert_eq!(srs.g2_points, get_srs().g2_points);
+
+        assert_eq!(
+            Ceremony::new(6).contribute(F17::ZERO),
+            Err(CeremonyError::ZeroSecret)
+        );
+    }
+
+    #[test]
+    fn tampered_ceremony_test() {
+        let mut ceremony = three_party_ceremony();
+        ceremony.g1_powers[4] = ceremony.g1_powers[4].add(G1);
+        assert_eq!(
+            ceremony.verify(),
+            Err(CeremonyError::InconsistentPowers { index: 4 })
+        );
+
+        let mut ceremony = three_party_ceremony();
+        ceremony.contributions[1].pok_s = ceremony.contributions[1].pok_s.add(F17::ONE);
+        assert_eq!(
+            ceremony.verify(),
+            Err(CeremonyError::InvalidProofOfKnowledge { contribution: 1 })
+        );
+
+        // a valid proof of knowledge for a secret that was not actually applied
+        let mut ceremony = three_party_ceremony();
+        let mut other = Ceremony::new(6);
+        other.contribute(F17(3)).unwrap();
+        ceremony.contributions[0] = other.contributions[0];
+        assert!(ceremony.verify().is_err());
+
+        let mut ceremony = three_party_ceremony();
+        ceremony.g2_powers[1] = ceremony.g2_powers[1].add(G2);
+        assert_eq!(
+            ceremony.verify(),
+            Err(CeremonyError::InconsistentPowers { index: 1 })
+        );
+    }
+
+    #[test]
+    fn ceremony_file_test() {
+        let ceremony = three_party_ceremony();
+        let path = std::env::temp_dir().join(format!("ceremony-{}.bin", std::process::id()));
+        ceremony.write_to_file(&path).unwrap();
+        let decoded = Ceremony::read_from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(decoded, ceremony);
+        assert_eq!(decoded.verify(), Ok(()));
+
+        let mut bytes = ceremony.to_bytes();
+        bytes[0] = CEREMONY_VERSION + 1;
+        assert_eq!(
+            Ceremony::from_bytes(&bytes),
+            Err(DecodeError::UnsupportedVersion(CEREMONY_VERSION + 1))
+        );
+    }
+}
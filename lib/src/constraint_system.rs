@@ -0,0 +1,397 @@
+// A constraint system for writing circuits as rows of the arithmetic gate
+//
+//   q_L a + q_R b + q_O c + q_M ab + q_C = 0
+//
+// over variables that carry their witness values, so a gadget assigns its outputs
+// and hints while it adds rows. Two rows that use the same variable are copy
+// constraints. Unlike `witness::Circuit`, a variable need not be the output of a
+// gate: hints such as an inverse are allocated with a value and then checked.
+//
+// to_circuit turns the rows into a PlonkCircuit and its witness. The circuit has
+// n = 4 rows, so only systems of at most 4 rows can be proved: is_zero, select,
+// inverse and a 2-bit range check fit, wider range checks, less_than and MiMC
+// do not.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    custom_gate::{arithmetic_gates, is_satisfied, CustomGate},
+    field::F17,
+    plonk_circuit::{PlonkCircuit, N},
+    prescribed_permutation::{coset_shifts, sigma_values},
+    witness::Variable,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selectors {
+    pub q_l: F17,
+    pub q_r: F17,
+    pub q_o: F17,
+    pub q_m: F17,
+    pub q_c: F17,
+}
+
+impl Default for Selectors {
+    fn default() -> Self {
+        Selectors {
+            q_l: F17::ZERO,
+            q_r: F17::ZERO,
+            q_o: F17::ZERO,
+            q_m: F17::ZERO,
+            q_c: F17::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub a: Variable,
+    pub b: Variable,
+    pub c: Variable,
+    pub selectors: Selectors,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsatisfiedRow {
+    pub row: usize,
+}
+
+impl fmt::Display for UnsatisfiedRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "row {} of the constraint system is unsatisfied",
+            self.row
+        )
+    }
+}
+
+impl std::error::Error for UnsatisfiedRow {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooManyRows {
+    pub rows: usize,
+}
+
+impl fmt::Display for TooManyRows {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} rows do not fit the {} rows of a circuit",
+            self.rows, N
+        )
+    }
+}
+
+impl std::error::Error for TooManyRows {}
+
+#[derive(Clone, Debug, Default)]
+pub struct ConstraintSystem {
+    values: Vec<F17>,
+    rows: Vec<Row>,
+}
+
+impl ConstraintSystem {
+    pub fn new() -> Self {
+        ConstraintSystem::default()
+    }
+
+    pub fn alloc(&mut self, value: F17) -> Variable {
+        self.values.push(value);
+        Variable(self.values.len() - 1)
+    }
+
+    pub fn value(&self, variable: Variable) -> F17 {
+        self.values[variable.0]
+    }
+
+    // Overwrite a witness value, e.g. to check that a gadget rejects a bad hint
+    pub fn assign(&mut self, variable: Variable, value: F17) {
+        self.values[variable.0] = value;
+    }
+
+    pub fn gate(&mut self, a: Variable, b: Variable, c: Variable, selectors: Selectors) {
+        self.rows.push(Row { a, b, c, selectors });
+    }
+
+    pub fn num_gates(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    // A variable fixed to `value` by the row x - value = 0
+    pub fn constant(&mut self, value: F17) -> Variable {
+        let x = self.alloc(value);
+        self.gate(
+            x,
+            x,
+            x,
+            Selectors {
+                q_l: F17::ONE,
+                q_c: value.neg(),
+                ..Selectors::default()
+            },
+        );
+        x
+    }
+
+    // out = left + right
+    pub fn add(&mut self, left: Variable, right: Variable) -> Variable {
+        let out = self.alloc(self.value(left).add(self.value(right)));
+        self.gate(
+            left,
+            right,
+            out,
+            Selectors {
+                q_l: F17::ONE,
+                q_r: F17::ONE,
+                q_o: F17::NEG_ONE,
+                ..Selectors::default()
+            },
+        );
+        out
+    }
+
+    // out = left + right + constant
+    pub fn add_with_constant(
+        &mut self,
+        left: Variable,
+        right: Variable,
+        constant: F17,
+    ) -> Variable {
+        let out = self.alloc(self.value(left).add(self.value(right)).add(constant));
+        self.gate(
+            left,
+            right,
+            out,
+            Selectors {
+                q_l: F17::ONE,
+                q_r: F17::ONE,
+                q_o: F17::NEG_ONE,
+                q_c: constant,
+                ..Selectors::default()
+            },
+        );
+        out
+    }
+
+    // out = left - right
+    pub fn sub(&mut self, left: Variable, right: Variable) -> Variable {
+        let out = self.alloc(self.value(left).sub(self.value(right)));
+        self.gate(
+            left,
+            right,
+            out,
+            Selectors {
+                q_l: F17::ONE,
+                q_r: F17::NEG_ONE,
+                q_o: F17::NEG_ONE,
+                ..Selectors::default()
+            },
+        );
+        out
+    }
+
+    // out = left * right
+    pub fn mul(&mut self, left: Variable, right: Variable) -> Variable {
+        let out = self.alloc(self.value(left).mul(self.value(right)));
+        self.gate(
+            left,
+            right,
+            out,
+            Selectors {
+                q_m: F17::ONE,
+                q_o: F17::NEG_ONE,
+                ..Selectors::default()
+            },
+        );
+        out
+    }
+
+    // left - right = 0, the c wire is unused and repeats left
+    pub fn assert_equal(&mut self, left: Variable, right: Variable) {
+        self.gate(
+            left,
+            right,
+            left,
+            Selectors {
+                q_l: F17::ONE,
+                q_r: F17::NEG_ONE,
+                ..Selectors::default()
+            },
+        );
+    }
+
+    // The a, b, c values of every row
+    pub fn wire_columns(&self) -> [Vec<F17>; 3] {
+        [
+            self.rows.iter().map(|row| self.value(row.a)).collect(),
+            self.rows.iter().map(|row| self.value(row.b)).collect(),
+            self.rows.iter().map(|row| self.value(row.c)).collect(),
+        ]
+    }
+
+    // The rows as the five arithmetic gates, one selector value per row
+    pub fn gates(&self) -> Vec<CustomGate> {
+        let column = |select: fn(&Selectors) -> F17| {
+            self.rows
+                .iter()
+                .map(|row| select(&row.selectors))
+                .collect::<Vec<_>>()
+        };
+        arithmetic_gates(
+            &column(|s| s.q_l),
+            &column(|s| s.q_r),
+            &column(|s| s.q_o),
+            &column(|s| s.q_m),
+            &column(|s| s.q_c),
+        )
+    }
+
+    // Ok when every row holds on the current values, otherwise the first row that
+    // does not
+    pub fn check(&self) -> Result<(), UnsatisfiedRow> {
+        let gates = self.gates();
+        let [a, b, c] = self.wire_columns();
+        match (0..self.rows.len()).find(|&row| !is_satisfied(&gates, row, &[&a, &b, &c])) {
+            Some(row) => Err(UnsatisfiedRow { row }),
+            None => Ok(()),
+        }
+    }
+
+    // The three-wire circuit of the rows and its witness. Rows past the last one
+    // are padded with 0 * 0 = 0 rows, and every variable used in more than one
+    // place becomes a copy cycle through all of its places.
+    pub fn to_circuit(&self) -> Result<(PlonkCircuit, Vec<[F17; N]>), TooManyRows> {
+        if self.rows.len() > N {
+            return Err(TooManyRows {
+                rows: self.rows.len(),
+            });
+        }
+        let mut padded = self.clone();
+        while padded.rows.len() < N {
+            let zero = padded.alloc(F17::ZERO);
+            padded.gate(zero, zero, zero, Selectors::default());
+        }
+
+        // (column, row) of every use, in row order
+        let mut uses: HashMap<Variable, Vec<(usize, usize)>> = HashMap::new();
+        for (i, row) in padded.rows.iter().enumerate() {
+            for (column, variable) in [row.a, row.b, row.c].into_iter().enumerate() {
+                uses.entry(variable).or_default().push((column, i));
+            }
+        }
+        let cycles: Vec<&[(usize, usize)]> = uses
+            .values()
+            .filter(|places| places.len() > 1)
+            .map(Vec::as_slice)
+            .collect();
+        let shifts = coset_shifts(3).expect("F17 has three cosets of H");
+
+        let circuit = PlonkCircuit::new(
+            3,
+            padded.gates(),
+            sigma_values(&shifts, &cycles),
+            vec![F17::ZERO; N],
+            (0..N as u32).map(F17).collect(),
+        )
+        .expect("the arithmetic gates on three wires are well formed");
+        let witness = padded
+            .wire_columns()
+            .map(|column| column.try_into().unwrap())
+            .to_vec();
+        Ok((circuit, witness))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constraint_system_test() {
+        // (x + y) * x - y
+        let mut cs = ConstraintSystem::new();
+        let x = cs.alloc(F17(5));
+        let y = cs.alloc(F17(6));
+        let sum = cs.add(x, y);
+        let product = cs.mul(sum, x);
+        let out = cs.sub(product, y);
+        assert_eq!(cs.num_gates(), 3);
+        // 11 * 5 = 55 = 4 mod 17, 4 - 6 = 15
+        assert_eq!(cs.value(out), F17(15));
+        assert_eq!(cs.check(), Ok(()));
+
+        let [a, b, c] = cs.wire_columns();
+        assert_eq!(a, vec![F17(5), F17(11), F17(4)]);
+        assert_eq!(b, vec![F17(6), F17(5), F17(6)]);
+        assert_eq!(c, vec![F17(11), F17(4), F17(15)]);
+
+        // y is shared by rows 0 and 2, so changing it breaks the first row using it
+        cs.assign(y, F17(7));
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 0 }));
+    }
+
+    #[test]
+    fn assert_equal_test() {
+        let mut cs = ConstraintSystem::new();
+        let x = cs.alloc(F17(3));
+        let y = cs.alloc(F17(3));
+        cs.assert_equal(x, y);
+        assert_eq!(cs.num_gates(), 1);
+        assert_eq!(cs.check(), Ok(()));
+        cs.assign(y, F17(4));
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 0 }));
+
+        let one = cs.constant(F17::ONE);
+        assert_eq!(cs.value(one), F17::ONE);
+        cs.assign(y, F17(3));
+        assert_eq!(cs.check(), Ok(()));
+        cs.assign(one, F17(2));
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 1 }));
+    }
+
+    #[test]
+    fn to_circuit_test() {
+        // (x + y) * x - y
+        let mut cs = ConstraintSystem::new();
+        let x = cs.alloc(F17(5));
+        let y = cs.alloc(F17(6));
+        let sum = cs.add(x, y);
+        let product = cs.mul(sum, x);
+        let out = cs.sub(product, y);
+        let (circuit, witness) = cs.to_circuit().unwrap();
+        assert_eq!(
+            witness,
+            vec![
+                [5, 11, 4, 0].map(F17),
+                [6, 5, 6, 0].map(F17),
+                [11, 4, 15, 0].map(F17)
+            ]
+        );
+
+        // the copy constraints come from the shared variables: x at a_0 and b_1,
+        // y at b_0 and b_2, the sum, the product, and the padding row's zero
+        let shifts = coset_shifts::<F17>(3).unwrap();
+        let expected = sigma_values(
+            &shifts,
+            &[
+                &[(0, 0), (1, 1)],
+                &[(1, 0), (1, 2)],
+                &[(2, 0), (0, 1)],
+                &[(2, 1), (0, 2)],
+                &[(0, 3), (1, 3), (2, 3)],
+            ],
+        );
+        assert_eq!(circuit.sigmas, expected);
+        assert_eq!(cs.value(out), witness[2][2]);
+
+        for _ in 0..2 {
+            cs.mul(x, x);
+        }
+        assert_eq!(cs.to_circuit().unwrap_err(), TooManyRows { rows: 5 });
+    }
+}
@@ -0,0 +1,233 @@
+// Short Weierstrass curves y^2 = x^3 + ax + b over a prime field.
+//
+// A curve is a marker type implementing CurveParams, and its points are
+// AffinePoint<C>. The curve used by the prover is F101Curve, whose points are
+// aliased as field::Point. F17Curve is a second, unrelated toy curve that keeps
+// the code honest about not assuming a = 0 or a particular base field.
+
+use std::{fmt::Debug, hash::Hash};
+
+use crate::{
+    field::{Field, F101, F17},
+    jacobian::JacobianPoint,
+};
+
+pub trait CurveParams: Clone + Copy + Debug + PartialEq + Eq + Hash + 'static {
+    type Base: Field;
+
+    const A: Self::Base;
+    const B: Self::Base;
+    const GENERATOR: (Self::Base, Self::Base);
+    // Order of the subgroup generated by GENERATOR, a prime
+    const ORDER: u32;
+    // Number of points on the curve divided by ORDER
+    const COFACTOR: u32;
+}
+
+// y^2 = x^3 + 3 over F101: 102 points, G1 = (1, 2) generates the order 17 subgroup
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct F101Curve;
+
+impl CurveParams for F101Curve {
+    type Base = F101;
+
+    const A: F101 = F101(0);
+    const B: F101 = F101(3);
+    const GENERATOR: (F101, F101) = (F101(1), F101(2));
+    const ORDER: u32 = 17;
+    const COFACTOR: u32 = 6;
+}
+
+// y^2 = x^3 + 2x + 2 over F17: 19 points, so every point but infinity generates it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct F17Curve;
+
+impl CurveParams for F17Curve {
+    type Base = F17;
+
+    const A: F17 = F17(2);
+    const B: F17 = F17(2);
+    const GENERATOR: (F17, F17) = (F17(5), F17(1));
+    const ORDER: u32 = 19;
+    const COFACTOR: u32 = 1;
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        rename_all = "snake_case",
+        try_from = "crate::serde_support::PointRepr<C>",
+        bound(
+            serialize = "C::Base: serde::Serialize",
+            deserialize = "C::Base: serde::Deserialize<'de>"
+        )
+    )
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AffinePoint<C: CurveParams> {
+    Infinity,
+    Point { x: C::Base, y: C::Base },
+}
+
+#[allow(clippy::should_implement_trait)]
+impl<C: CurveParams> AffinePoint<C> {
+    pub const fn generator() -> Self {
+        AffinePoint::Point {
+            x: C::GENERATOR.0,
+            y: C::GENERATOR.1,
+        }
+    }
+
+    pub fn point_neg(self) -> Self {
+        match self {
+            AffinePoint::Infinity => AffinePoint::Infinity,
+            AffinePoint::Point { x, y } => AffinePoint::Point { x, y: y.neg() },
+        }
+    }
+
+    pub fn add(self, other: Self) -> Self {
+        point_add(self, other)
+    }
+
+    // y^2 = x^3 + ax + b
+    pub fn is_on_curve(self) -> bool {
+        match self {
+            AffinePoint::Infinity => true,
+            AffinePoint::Point { x, y } => y.mul(y) == x.mul(x).mul(x).add(C::A.mul(x)).add(C::B),
+        }
+    }
+
+    // [ORDER]P = O. With a cofactor above 1 the curve has points of small order
+    // that satisfy the curve equation but lie outside the subgroup of GENERATOR.
+    pub fn is_in_prime_subgroup(self) -> bool {
+        self.is_on_curve() && scalar_mult(C::ORDER, self) == AffinePoint::Infinity
+    }
+
+    // [COFACTOR]P, which lies in the prime order subgroup for any point on the curve
+    pub fn clear_cofactor(self) -> Self {
+        scalar_mult(C::COFACTOR, self)
+    }
+}
+
+pub fn point_add<C: CurveParams>(p1: AffinePoint<C>, p2: AffinePoint<C>) -> AffinePoint<C> {
+    match (p1, p2) {
+        (AffinePoint::Infinity, _) => p2,
+        (_, AffinePoint::Infinity) => p1,
+        (AffinePoint::Point { x: x1, y: y1 }, AffinePoint::Point { x: x2, y: y2 }) => {
+            if x1 == x2 && y1.add(y2) == C::Base::ZERO {
+                AffinePoint::Infinity
+            } else {
+                let m = if x1 == x2 && y1 == y2 {
+                    // tangent slope (3x^2 + a) / 2y
+                    let numerator = x1.mul(x1).mul(C::Base::from_u32(3)).add(C::A);
+                    let denominator = y1.mul(C::Base::from_u32(2));
+                    match denominator.inv() {
+                        Some(inv) => numerator.mul(inv),
+                        None => return AffinePoint::Infinity,
+                    }
+                } else {
+                    let numerator = y2.sub(y1);
+                    let denominator = x2.sub(x1);
+                    match denominator.inv() {
+                        Some(inv) => numerator.mul(inv),
+                        None => return AffinePoint::Infinity,
+                    }
+                };
+                let x3 = m.mul(m).sub(x1).sub(x2);
+                let y3 = m.mul(x1.sub(x3)).sub(y1);
+                AffinePoint::Point { x: x3, y: y3 }
+            }
+        }
+    }
+}
+
+// Double-and-add in Jacobian coordinates, with a single inversion at the end
+pub fn scalar_mult<C: CurveParams>(k: u32, p: AffinePoint<C>) -> AffinePoint<C> {
+    let mut result = JacobianPoint::INFINITY;
+    for i in (0..u32::BITS - k.leading_zeros()).rev() {
+        result = result.double();
+        if (k >> i) & 1 == 1 {
+            result = result.add_affine(p);
+        }
+    }
+    result.to_affine()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::curve_analysis::CurveSpec;
+
+    use super::*;
+
+    fn check_curve<C: CurveParams>() {
+        let points = CurveSpec::curve_points::<C>();
+        assert_eq!(points.len() as u32, C::ORDER * C::COFACTOR);
+
+        let g = AffinePoint::<C>::generator();
+        assert!(g.is_on_curve());
+        assert_eq!(scalar_mult(C::ORDER, g), AffinePoint::Infinity);
+        for k in 1..C::ORDER {
+            assert_ne!(scalar_mult(k, g), AffinePoint::Infinity);
+        }
+
+        // the group law is closed and associative on every point
+        for &p in &points {
+            assert_eq!(p.add(p.point_neg()), AffinePoint::Infinity);
+            for &q in &points {
+                let sum = p.add(q);
+                assert!(sum.is_on_curve());
+                assert_eq!(sum, q.add(p));
+                assert_eq!(sum.add(g), p.add(q.add(g)));
+            }
+        }
+    }
+
+    #[test]
+    fn f101_curve_test() {
+        check_curve::<F101Curve>();
+    }
+
+    #[test]
+    fn subgroup_test() {
+        let points = CurveSpec::curve_points::<F101Curve>();
+        let in_subgroup = points.iter().filter(|p| p.is_in_prime_subgroup()).count();
+        assert_eq!(in_subgroup, 17);
+        for &p in &points {
+            assert!(p.clear_cofactor().is_in_prime_subgroup());
+        }
+
+        // (48, 0) has order 2: on the curve, but not in the order 17 subgroup
+        let small_order = AffinePoint::<F101Curve>::Point {
+            x: F101(48),
+            y: F101(0),
+        };
+        assert!(small_order.is_on_curve());
+        assert!(!small_order.is_in_prime_subgroup());
+        assert_eq!(small_order.clear_cofactor(), AffinePoint::Infinity);
+        let g = AffinePoint::<F101Curve>::generator();
+        assert!(!g.add(small_order).is_in_prime_subgroup());
+        assert_eq!(g.add(small_order).clear_cofactor(), scalar_mult(6, g));
+
+        // off-curve points are never in the subgroup
+        let off_curve = AffinePoint::<F101Curve>::Point {
+            x: F101(1),
+            y: F101(3),
+        };
+        assert!(!off_curve.is_in_prime_subgroup());
+
+        // with cofactor 1 every curve point is in the subgroup
+        assert!(CurveSpec::curve_points::<F17Curve>()
+            .iter()
+            .all(|p| p.is_in_prime_subgroup()));
+    }
+
+    #[test]
+    fn f17_curve_test() {
+        check_curve::<F17Curve>();
+        // every point except infinity has the prime order 19
+        for p in CurveSpec::curve_points::<F17Curve>().into_iter().skip(1) {
+            assert_eq!(scalar_mult(19, p), AffinePoint::Infinity);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+// Analysis of toy curves y^2 = x^3 + ax + b over F_p, for picking new parameter sets.
+//
+// Everything here works on plain integers so that any (p, a, b) can be examined
+// without first writing a CurveParams type for it. Points are enumerated by brute
+// force, which is fine for the small p this crate is about.
+
+use crate::{
+    curve::{AffinePoint, CurveParams},
+    field::Field,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CurveSpec {
+    pub p: u32,
+    pub a: u32,
+    pub b: u32,
+}
+
+// An affine point (x, y), None is the point at infinity
+pub type RawPoint = Option<(u32, u32)>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrimeSubgroup {
+    pub order: u32,
+    // smallest affine point of this order
+    pub generator: (u32, u32),
+    // smallest k with order | p^k - 1, None when order = p
+    pub embedding_degree: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurveAnalysis {
+    pub spec: CurveSpec,
+    // number of points, including the point at infinity
+    pub order: u32,
+    // (prime, exponent) pairs, smallest prime first
+    pub factorization: Vec<(u32, u32)>,
+    pub subgroups: Vec<PrimeSubgroup>,
+}
+
+fn mul_mod(x: u32, y: u32, p: u32) -> u32 {
+    (u64::from(x) * u64::from(y) % u64::from(p)) as u32
+}
+
+fn pow_mod(mut base: u32, mut exp: u32, p: u32) -> u32 {
+    let mut result = 1 % p;
+    while exp > 0 {
+        if exp % 2 == 1 {
+            result = mul_mod(result, base, p);
+        }
+        base = mul_mod(base, base, p);
+        exp >>= 1;
+    }
+    result
+}
+
+pub fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+pub fn is_prime(n: u32) -> bool {
+    n >= 2
+        && (2..)
+            .take_while(|d| d * d <= n)
+            .all(|d| !n.is_multiple_of(d))
+}
+
+pub fn factorize(mut n: u32) -> Vec<(u32, u32)> {
+    let mut factors = Vec::new();
+    let mut d = 2;
+    while d * d <= n {
+        let mut exponent = 0;
+        while n.is_multiple_of(d) {
+            n /= d;
+            exponent += 1;
+        }
+        if exponent > 0 {
+            factors.push((d, exponent));
+        }
+        d += 1;
+    }
+    if n > 1 {
+        factors.push((n, 1));
+    }
+    factors
+}
+
+// The smallest k such that r divides p^k - 1, that is the order of p modulo r.
+// G1 and G2 of a pairing on the r-torsion live over F_p and F_p^k.
+pub fn embedding_degree(r: u32, p: u32) -> Option<u32> {
+    if p.is_multiple_of(r) {
+        return None;
+    }
+    let mut power = p % r;
+    for k in 1..r {
+        if power == 1 {
+            return Some(k);
+        }
+        power = mul_mod(power, p, r);
+    }
+    None
+}
+
+impl CurveSpec {
+    pub fn new(p: u32, a: u32, b: u32) -> Self {
+        assert!(is_prime(p), "{} is not prime", p);
+        CurveSpec {
+            p,
+            a: a % p,
+            b: b % p,
+        }
+    }
+
+    pub fn of<C: CurveParams>() -> Self {
+        CurveSpec::new(C::Base::MODULUS, C::A.value(), C::B.value())
+    }
+
+    // 4a^3 + 27b^2 != 0, otherwise the cubic has a repeated root and this is no elliptic curve
+    pub fn is_nonsingular(&self) -> bool {
+        let p = self.p;
+        let a3 = mul_mod(mul_mod(self.a, self.a, p), self.a, p);
+        let b2 = mul_mod(self.b, self.b, p);
+        !(mul_mod(4, a3, p) + mul_mod(27, b2, p)).is_multiple_of(p)
+    }
+
+    pub fn is_on_curve(&self, point: RawPoint) -> bool {
+        match point {
+            None => true,
+            Some((x, y)) => {
+                let p = self.p;
+                let rhs = (mul_mod(mul_mod(x, x, p), x, p) + mul_mod(self.a, x, p) + self.b) % p;
+                x < p && y < p && mul_mod(y, y, p) == rhs
+            }
+        }
+    }
+
+    // Every affine point, sorted by x then y
+    pub fn points(&self) -> Vec<(u32, u32)> {
+        let p = self.p;
+        // index the squares once instead of testing every (x, y)
+        let mut roots: Vec<Vec<u32>> = vec![Vec::new(); p as usize];
+        for y in 0..p {
+            roots[mul_mod(y, y, p) as usize].push(y);
+        }
+        let mut points = Vec::new();
+        for x in 0..p {
+            let rhs = (mul_mod(mul_mod(x, x, p), x, p) + mul_mod(self.a, x, p) + self.b) % p;
+            points.extend(roots[rhs as usize].iter().map(|&y| (x, y)));
+        }
+        points
+    }
+
+    // The points of C as typed points, the point at infinity first
+    pub fn curve_points<C: CurveParams>() -> Vec<AffinePoint<C>> {
+        let affine = CurveSpec::of::<C>()
+            .points()
+            .into_iter()
+            .map(|(x, y)| AffinePoint::Point {
+                x: C::Base::from_u32(x),
+                y: C::Base::from_u32(y),
+            });
+        std::iter::once(AffinePoint::Infinity)
+            .chain(affine)
+            .collect()
+    }
+
+    pub fn add(&self, p1: RawPoint, p2: RawPoint) -> RawPoint {
+        let p = self.p;
+        let ((x1, y1), (x2, y2)) = match (p1, p2) {
+            (None, _) => return p2,
+            (_, None) => return p1,
+            (Some(p1), Some(p2)) => (p1, p2),
+        };
+        if x1 == x2 && (y1 + y2) % p == 0 {
+            return None;
+        }
+        let (numerator, denominator) = if x1 == x2 {
+            (
+                (mul_mod(3, mul_mod(x1, x1, p), p) + self.a) % p,
+                mul_mod(2, y1, p),
+            )
+        } else {
+            ((y2 + p - y1) % p, (x2 + p - x1) % p)
+        };
+        let m = mul_mod(numerator, pow_mod(denominator, p - 2, p), p);
+        let x3 = (mul_mod(m, m, p) + 2 * p - x1 - x2) % p;
+        let y3 = (mul_mod(m, (x1 + p - x3) % p, p) + p - y1) % p;
+        Some((x3, y3))
+    }
+
+    pub fn mul(&self, mut k: u32, point: RawPoint) -> RawPoint {
+        let mut result = None;
+        let mut addend = point;
+        while k > 0 {
+            if k % 2 == 1 {
+                result = self.add(result, addend);
+            }
+            addend = self.add(addend, addend);
+            k >>= 1;
+        }
+        result
+    }
+
+    pub fn analyze(&self) -> CurveAnalysis {
+        let points = self.points();
+        let order = points.len() as u32 + 1;
+        let factorization = factorize(order);
+        let subgroups = factorization
+            .iter()
+            .map(|&(r, _)| {
+                // r is prime, so any affine point with [r]P = O has order exactly r,
+                // and Cauchy's theorem guarantees there is one
+                let generator = points
+                    .iter()
+                    .copied()
+                    .find(|&point| self.mul(r, Some(point)).is_none())
+                    .unwrap();
+                PrimeSubgroup {
+                    order: r,
+                    generator,
+                    embedding_degree: embedding_degree(r, self.p),
+                }
+            })
+            .collect();
+        CurveAnalysis {
+            spec: *self,
+            order,
+            factorization,
+            subgroups,
+        }
+    }
+}
+
+impl CurveAnalysis {
+    pub fn cofactor(&self, r: u32) -> u32 {
+        self.order / r
+    }
+}
+
+// Non-singular curves over F_p with a prime subgroup of order at least
+// `min_subgroup_order` whose embedding degree is at most `max_embedding_degree`
+pub fn pairing_friendly_curves(
+    p: u32,
+    min_subgroup_order: u32,
+    max_embedding_degree: u32,
+) -> Vec<(CurveAnalysis, PrimeSubgroup)> {
+    let mut found = Vec::new();
+    for a in 0..p {
+        for b in 0..p {
+            let spec = CurveSpec::new(p, a, b);
+            if !spec.is_nonsingular() {
+                continue;
+            }
+            let analysis = spec.analyze();
+            let subgroup = analysis.subgroups.iter().rev().find(|subgroup| {
+                subgroup.order >= min_subgroup_order
+                    && subgroup
+                        .embedding_degree
+                        .is_some_and(|k| k <= max_embedding_degree)
+            });
+            if let Some(subgroup) = subgroup.cloned() {
+                found.push((analysis, subgroup));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::curve::{F101Curve, F17Curve};
+
+    use super::*;
+
+    #[test]
+    fn gcd_test() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(3, 16), 1);
+        assert_eq!(gcd(0, 7), 7);
+        assert!(is_prime(101) && !is_prime(15) && !is_prime(1));
+    }
+
+    #[test]
+    fn factorize_test() {
+        assert_eq!(factorize(102), vec![(2, 1), (3, 1), (17, 1)]);
+        assert_eq!(factorize(10404), vec![(2, 2), (3, 2), (17, 2)]);
+        assert_eq!(factorize(19), vec![(19, 1)]);
+        assert_eq!(factorize(1), vec![]);
+        assert!(is_prime(101) && !is_prime(102) && !is_prime(1));
+    }
+
+    #[test]
+    fn embedding_degree_test() {
+        // 17 | 101^2 - 1 = 10200 but not 101 - 1
+        assert_eq!(embedding_degree(17, 101), Some(2));
+        // 17 = -2 mod 19 and (-2)^9 = 1 mod 19
+        assert_eq!(embedding_degree(19, 17), Some(9));
+        assert_eq!(embedding_degree(5, 101), Some(1));
+        assert_eq!(embedding_degree(17, 17), None);
+    }
+
+    #[test]
+    fn f101_curve_analysis_test() {
+        let spec = CurveSpec::of::<F101Curve>();
+        assert_eq!(spec, CurveSpec::new(101, 0, 3));
+        let analysis = spec.analyze();
+        assert_eq!(analysis.order, 102);
+        assert_eq!(analysis.factorization, vec![(2, 1), (3, 1), (17, 1)]);
+        assert_eq!(analysis.cofactor(17), 6);
+
+        let orders: Vec<u32> = analysis.subgroups.iter().map(|s| s.order).collect();
+        assert_eq!(orders, vec![2, 3, 17]);
+        // (48, 0) is the only point of order 2
+        assert_eq!(analysis.subgroups[0].generator, (48, 0));
+        let g1 = &analysis.subgroups[2];
+        assert_eq!(g1.generator, (1, 2));
+        assert_eq!(g1.embedding_degree, Some(2));
+        assert_eq!(spec.mul(17, Some(g1.generator)), None);
+
+        for point in spec.points() {
+            assert!(spec.is_on_curve(Some(point)));
+        }
+    }
+
+    #[test]
+    fn f17_curve_analysis_test() {
+        let spec = CurveSpec::of::<F17Curve>();
+        let analysis = spec.analyze();
+        assert_eq!(analysis.order, 19);
+        assert_eq!(analysis.subgroups.len(), 1);
+        assert_eq!(analysis.subgroups[0].embedding_degree, Some(9));
+        assert!(spec.is_nonsingular());
+        assert!(!CurveSpec::new(17, 0, 0).is_nonsingular());
+    }
+
+    #[test]
+    fn pairing_friendly_curves_test() {
+        let found = pairing_friendly_curves(101, 17, 2);
+        assert!(found.iter().any(|(analysis, subgroup)| analysis.spec
+            == CurveSpec::new(101, 0, 3)
+            && subgroup.order == 17));
+        for (analysis, subgroup) in &found {
+            assert!(subgroup.order >= 17 && is_prime(subgroup.order));
+            assert!(subgroup.embedding_degree.unwrap() <= 2);
+            assert_eq!(analysis.order % subgroup.order, 0);
+        }
+    }
+}
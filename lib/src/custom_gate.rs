@@ -0,0 +1,317 @@
+// Declared gates: each contributes selector(x) * expression(wires) to the one gate
+// constraint, which must vanish on H. Terms of different gates add up on a row,
+// so a circuit turns on the selectors of one row equation at a time.
+//
+// An expression is built from constants and the wire columns at the current row
+// (omega^0) or the next one (omega^1), so a gate can relate two rows. The standard
+// q_L a + q_R b + q_O c + q_M ab + q_C is five of these. Round 3 evaluates the
+// expressions on the wire polynomials, round 4 on the openings at zeta and zeta
+// omega to get the selector scalars of the linearization, and the verifier uses
+// the same scalars on the selector commitments for [D].
+//
+// Every wire has degree n + 1 after blinding, so a gate of degree d adds a term of
+// degree (n - 1) + d (n + 1) to the quotient numerator. Over F17 round 3 evaluates
+// the numerator on a coset of at most MAX_DOMAIN_SIZE = 32 points, which with n = 4
+// limits expressions to degree 5: an x^5 S-box fits, and PlonkCircuit::new
+// rejects anything above with CircuitError::DegreeTooLarge.
+
+use crate::{
+    field::{PlonkField, ScalarField, F17},
+    plonk_circuit::omega,
+    polynomial::Polynomial,
+};
+
+// A wire column by index, a circuit has k of them
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Column(pub usize);
+
+impl Column {
+    pub const A: Column = Column(0);
+    pub const B: Column = Column(1);
+    pub const C: Column = Column(2);
+}
+
+// omega^0 or omega^1
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    Cur,
+    Next,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Expression<F = F17> {
+    Constant(F),
+    Wire(Column, Rotation),
+    Sum(Box<Expression<F>>, Box<Expression<F>>),
+    Product(Box<Expression<F>>, Box<Expression<F>>),
+    Neg(Box<Expression<F>>),
+}
+
+#[allow(clippy::should_implement_trait)]
+impl<F: ScalarField> Expression<F> {
+    pub fn wire(column: Column) -> Expression<F> {
+        Expression::Wire(column, Rotation::Cur)
+    }
+
+    pub fn next(column: Column) -> Expression<F> {
+        Expression::Wire(column, Rotation::Next)
+    }
+
+    pub fn constant(value: F) -> Expression<F> {
+        Expression::Constant(value)
+    }
+
+    pub fn add(self, other: Expression<F>) -> Expression<F> {
+        Expression::Sum(Box::new(self), Box::new(other))
+    }
+
+    pub fn sub(self, other: Expression<F>) -> Expression<F> {
+        self.add(other.neg())
+    }
+
+    pub fn mul(self, other: Expression<F>) -> Expression<F> {
+        Expression::Product(Box::new(self), Box::new(other))
+    }
+
+    pub fn neg(self) -> Expression<F> {
+        Expression::Neg(Box::new(self))
+    }
+
+    // Degree in the wires
+    pub fn degree(&self) -> usize {
+        match self {
+            Expression::Constant(_) => 0,
+            Expression::Wire(..) => 1,
+            Expression::Sum(left, right) => left.degree().max(right.degree()),
+            Expression::Product(left, right) => left.degree() + right.degree(),
+            Expression::Neg(inner) => inner.degree(),
+        }
+    }
+
+    // The highest column the expression reads, None for a constant
+    pub fn max_column(&self) -> Option<usize> {
+        match self {
+            Expression::Constant(_) => None,
+            Expression::Wire(column, _) => Some(column.0),
+            Expression::Sum(left, right) | Expression::Product(left, right) => {
+                left.max_column().max(right.max_column())
+            }
+            Expression::Neg(inner) => inner.max_column(),
+        }
+    }
+
+    pub fn evaluate(&self, wire: &impl Fn(Column, Rotation) -> F) -> F {
+        match self {
+            Expression::Constant(value) => *value,
+            Expression::Wire(column, rotation) => wire(*column, *rotation),
+            Expression::Sum(left, right) => left.evaluate(wire).add(right.evaluate(wire)),
+            Expression::Product(left, right) => left.evaluate(wire).mul(right.evaluate(wire)),
+            Expression::Neg(inner) => inner.evaluate(wire).neg(),
+        }
+    }
+
+    pub fn evaluate_poly(
+        &self,
+        wire: &impl Fn(Column, Rotation) -> Polynomial<F>,
+    ) -> Polynomial<F> {
+        match self {
+            Expression::Constant(value) => Polynomial {
+                coeffs: vec![*value],
+            },
+            Expression::Wire(column, rotation) => wire(*column, *rotation),
+            Expression::Sum(left, right) => {
+                left.evaluate_poly(wire).add(&right.evaluate_poly(wire))
+            }
+            Expression::Product(left, right) => {
+                left.evaluate_poly(wire).mul(&right.evaluate_poly(wire))
+            }
+            Expression::Neg(inner) => inner.evaluate_poly(wire).mul_by_monomial(F::ONE.neg(), 0),
+        }
+    }
+}
+
+// selector(x) * expression, the selector given by its values on H
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomGate<F = F17> {
+    pub name: &'static str,
+    pub selector: Vec<F>,
+    pub expression: Expression<F>,
+}
+
+impl<F: PlonkField> CustomGate<F> {
+    pub fn new(name: &'static str, selector: &[F], expression: Expression<F>) -> Self {
+        CustomGate {
+            name,
+            selector: selector.to_vec(),
+            expression,
+        }
+    }
+
+    // selector(x) interpolated on H
+    pub fn selector_poly(&self) -> Polynomial<F> {
+        let points = F::domain(self.selector.len())
+            .into_iter()
+            .zip(self.selector.iter().copied())
+            .collect::<Vec<_>>();
+        Polynomial::interpolate(&points)
+    }
+}
+
+// Whether the gate constraint holds on a row of the wire values, the next row of
+// the last one being the first
+pub fn is_satisfied<F: PlonkField>(gates: &[CustomGate<F>], row: usize, wires: &[&[F]]) -> bool {
+    let n = wires[0].len();
+    let wire = |column: Column, rotation| {
+        let i = match rotation {
+            Rotation::Cur => row,
+            Rotation::Next => (row + 1) % n,
+        };
+        wires[column.0][i]
+    };
+    gates.iter().fold(F::ZERO, |acc, gate| {
+        acc.add(gate.selector[row].mul(gate.expression.evaluate(&wire)))
+    }) == F::ZERO
+}
+
+// q_L a + q_R b + q_O c + q_M ab + q_C, one declared gate per selector
+pub fn arithmetic_gates<F: PlonkField>(
+    q_l: &[F],
+    q_r: &[F],
+    q_o: &[F],
+    q_m: &[F],
+    q_c: &[F],
+) -> Vec<CustomGate<F>> {
+    vec![
+        CustomGate::new("q_L", q_l, Expression::wire(Column::A)),
+        CustomGate::new("q_R", q_r, Expression::wire(Column::B)),
+        CustomGate::new("q_O", q_o, Expression::wire(Column::C)),
+        CustomGate::new(
+            "q_M",
+            q_m,
+            Expression::wire(Column::A).mul(Expression::wire(Column::B)),
+        ),
+        CustomGate::new("q_C", q_c, Expression::constant(F::ONE)),
+    ]
+}
+
+// sum selector_i(x) * expression_i, the gate part of the quotient numerator
+pub fn gate_constraint_poly<F: PlonkField>(
+    gates: &[CustomGate<F>],
+    wires: &[Polynomial<F>],
+) -> Polynomial<F> {
+    let omega = omega();
+    let shifted = wires
+        .iter()
+        .map(|wire| wire.evaluate_at_omega_x(omega))
+        .collect::<Vec<_>>();
+    gates.iter().fold(
+        Polynomial {
+            coeffs: vec![F::ZERO],
+        },
+        |acc, gate| {
+            let value = gate
+                .expression
+                .evaluate_poly(&|column, rotation| match rotation {
+                    Rotation::Cur => wires[column.0].clone(),
+                    Rotation::Next => shifted[column.0].clone(),
+                });
+            acc.add(&gate.selector_poly().mul(&value))
+        },
+    )
+}
+
+// The expressions evaluated at the openings, the selector scalars of the
+// linearization. `current` and `next` are the wire openings at zeta and
+// zeta omega.
+pub fn gate_scalars<F: ScalarField>(
+    expressions: &[&Expression<F>],
+    current: &[F],
+    next: &[F],
+) -> Vec<F> {
+    expressions
+        .iter()
+        .map(|expression| {
+            expression.evaluate(&|column, rotation| match rotation {
+                Rotation::Cur => current[column.0],
+                Rotation::Next => next[column.0],
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expression_test() {
+        const A: Column = Column::A;
+        const B: Column = Column::B;
+        const C: Column = Column::C;
+        // a^3 - c
+        let cube = Expression::wire(A)
+            .mul(Expression::wire(A))
+            .mul(Expression::wire(A))
+            .sub(Expression::wire(C));
+        assert_eq!(cube.degree(), 3);
+        let values = |column: Column, _| [F17(2), F17(5), F17(8)][column.0];
+        assert_eq!(cube.evaluate(&values), F17::ZERO);
+
+        // a + b - c(omega x)
+        let carry = Expression::wire(A)
+            .add(Expression::wire(B))
+            .sub(Expression::next(C));
+        let a = [F17(1), F17(2), F17(3), F17(4)];
+        let b = [F17(5), F17(6), F17(7), F17(8)];
+        let c = [F17(12), F17(6), F17(9), F17(10)];
+        let gates = [CustomGate::new("carry", &[F17::ONE; 4], carry)];
+        // row 3 wraps around to c_0
+        assert!(is_satisfied(&gates, 3, &[&a, &b, &c]));
+        assert!(is_satisfied(&gates, 0, &[&a, &b, &c]));
+        assert!(!is_satisfied(&gates, 1, &[&a, &b, &c]));
+
+        // a fourth column: a + b + c - d
+        let sum = Expression::wire(A)
+            .add(Expression::wire(B))
+            .add(Expression::wire(C))
+            .sub(Expression::wire(Column(3)));
+        let d = [F17(1), F17(13), F17(2), F17(5)];
+        let gates = [CustomGate::new("sum", &[F17::ONE; 4], sum)];
+        assert!(is_satisfied(&gates, 0, &[&a, &b, &c, &d]));
+        assert!(is_satisfied(&gates, 2, &[&a, &b, &c, &d]));
+        assert!(!is_satisfied(&gates, 1, &[&a, &b, &c, &d]));
+    }
+
+    #[test]
+    fn gate_constraint_poly_test() {
+        let (a, b, c) = crate::pythagorean_transcript::gen_transcript();
+        let wires = [a, b, c].map(|values| {
+            Polynomial::interpolate(&F17::H.into_iter().zip(values).collect::<Vec<_>>())
+        });
+        let gates = crate::pythagorean_circuit::gen_plonk_circuit().gates;
+        let constraint = gate_constraint_poly(&gates, &wires);
+        for x in F17::H {
+            assert_eq!(constraint.evaluate(x), F17::ZERO);
+        }
+        for row in 0..4 {
+            assert!(is_satisfied(&gates, row, &[&a, &b, &c]), "row {}", row);
+        }
+
+        // gate 3 checks d^2 + e^2 against its own output
+        let mut c = c;
+        c[3] = c[3].add(F17::ONE);
+        assert!(!is_satisfied(&gates, 3, &[&a, &b, &c]));
+        assert!(is_satisfied(&gates, 2, &[&a, &b, &c]));
+    }
+}
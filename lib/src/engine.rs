@@ -0,0 +1,106 @@
+// The curve a PLONK instance runs on: its scalar field, the two source groups of
+// the pairing and the pairing check itself. The SRS, KZG, the rounds and the
+// verifier only go through these traits, so the same code proves over the toy
+// curve of order 17 and, with the `bn254` feature, over BN254.
+
+use std::fmt::Debug;
+
+use crate::{
+    field::{PlonkField, Point, F17, G1},
+    field_extension::{G2Point, F1012, G2},
+    fixed_base::{g1_table, FixedBaseTable, TablePoint},
+    msm::MsmScalar,
+    pairing::multi_pairing,
+    serialize::Encode,
+};
+
+// A prime order group written additively, with scalars in F
+pub trait Group<F>: Copy + Debug + PartialEq + Eq {
+    const IDENTITY: Self;
+
+    fn generator() -> Self;
+    fn add(self, other: Self) -> Self;
+    fn neg(self) -> Self;
+    fn mul(self, k: F) -> Self;
+    // On the curve and in the subgroup of order |F|, the curve equation alone
+    // would let a prover mix in points of small order
+    fn is_valid(self) -> bool;
+}
+
+pub trait Engine: Clone + Copy + Debug + PartialEq + Eq {
+    type Fr: PlonkField + MsmScalar + Encode;
+    type G1: Group<Self::Fr> + TablePoint + Encode + 'static;
+    type G2: Group<Self::Fr>;
+
+    // Fixed-base table for the G1 generator, built once
+    fn g1_table() -> &'static FixedBaseTable<Self::G1>;
+
+    // prod e(p_i, q_i) = 1
+    fn pairing_product_is_one(pairs: &[(Self::G1, Self::G2)]) -> bool;
+}
+
+// y^2 = x^3 + 3 over F101 and F101^2, with F17 scalars
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Toy;
+
+impl Group<F17> for Point {
+    const IDENTITY: Point = Point::Infinity;
+
+    fn generator() -> Point {
+        G1
+    }
+
+    fn add(self, other: Point) -> Point {
+        Point::add(self, other)
+    }
+
+    fn neg(self) -> Point {
+        self.point_neg()
+    }
+
+    fn mul(self, k: F17) -> Point {
+        Point::mul(self, k)
+    }
+
+    fn is_valid(self) -> bool {
+        self.is_in_prime_subgroup()
+    }
+}
+
+impl Group<F17> for G2Point {
+    const IDENTITY: G2Point = G2Point::Infinity;
+
+    fn generator() -> G2Point {
+        G2
+    }
+
+    fn add(self, other: G2Point) -> G2Point {
+        G2Point::add(self, other)
+    }
+
+    fn neg(self) -> G2Point {
+        G2Point::neg(self)
+    }
+
+    fn mul(self, k: F17) -> G2Point {
+        G2Point::mul(self, k)
+    }
+
+    fn is_valid(self) -> bool {
+        self.is_in_prime_subgroup()
+    }
+}
+
+impl Engine for Toy {
+    type Fr = F17;
+    type G1 = Point;
+    type G2 = G2Point;
+
+    fn g1_table() -> &'static FixedBaseTable<Point> {
+        g1_table()
+    }
+
+    fn pairing_product_is_one(pairs: &[(Point, G2Point)]) -> bool {
+        multi_pairing(pairs) == F1012::ONE
+    }
+}
@@ -0,0 +1,62 @@
+// Fiat-Shamir challenges from a running FNV-1a hash of everything absorbed so far.
+// Not a cryptographic hash, but deterministic and stable across builds, which is
+// all the toy field needs.
+
+use crate::{field::F17, serialize::Encode};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[derive(Clone, Debug)]
+pub struct FiatShamir {
+    state: u64,
+}
+
+impl FiatShamir {
+    pub fn new(label: &[u8]) -> Self {
+        let mut fs = FiatShamir {
+            state: FNV_OFFSET_BASIS,
+        };
+        fs.absorb_bytes(label);
+        fs
+    }
+
+    pub fn absorb_bytes(&mut self, bytes: &[u8]) {
+        // length prefix, so that ("ab", "c") and ("a", "bc") differ
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.state ^= u64::from(*byte);
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn absorb<T: Encode>(&mut self, value: &T) {
+        self.absorb_bytes(&value.to_bytes());
+    }
+
+    pub fn challenge(&mut self) -> F17 {
+        let challenge = F17::new((self.state % u64::from(F17::P)) as u32);
+        self.absorb(&challenge);
+        challenge
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::field::G1;
+
+    use super::*;
+
+    #[test]
+    fn challenge_test() {
+        let mut fs1 = FiatShamir::new(b"test");
+        let mut fs2 = FiatShamir::new(b"test");
+        fs1.absorb(&G1);
+        fs2.absorb(&G1);
+        assert_eq!(fs1.challenge(), fs2.challenge());
+        assert_eq!(fs1.state, fs2.state);
+
+        let mut fs3 = FiatShamir::new(b"other");
+        fs3.absorb(&G1);
+        assert_ne!(fs3.state, fs1.state);
+    }
+}
@@ -1,8 +1,286 @@
-use std::{collections::HashMap, ops::Sub};
+use std::{collections::HashMap, fmt, fmt::Debug, hash::Hash};
+
+use crate::{
+    big_field::{BigPrimeField, BigPrimeParams},
+    curve::{AffinePoint, CurveParams, F101Curve},
+    field_extension::F172,
+    ntt::NttField,
+};
+
+// A prime field with elements below 2^16, so products fit in a u32
+pub trait Field: Copy + Debug + PartialEq + Eq + Hash {
+    const MODULUS: u32;
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_u32(n: u32) -> Self;
+    fn value(self) -> u32;
+    fn add(self, other: Self) -> Self;
+    fn sub(self, other: Self) -> Self;
+    fn mul(self, other: Self) -> Self;
+    fn neg(self) -> Self;
+    fn inv(self) -> Option<Self>;
+}
+
+impl Field for F101 {
+    const MODULUS: u32 = F101::P;
+    const ZERO: F101 = F101::ZERO;
+    const ONE: F101 = F101::ONE;
+
+    fn from_u32(n: u32) -> Self {
+        F101::new(n)
+    }
+
+    fn value(self) -> u32 {
+        self.0
+    }
+
+    fn add(self, other: F101) -> F101 {
+        F101::add(self, other)
+    }
+
+    fn sub(self, other: F101) -> F101 {
+        F101::sub(self, other)
+    }
+
+    fn mul(self, other: F101) -> F101 {
+        F101::mul(self, other)
+    }
+
+    fn neg(self) -> F101 {
+        F101::neg(self)
+    }
+
+    fn inv(self) -> Option<F101> {
+        F101::inv(self)
+    }
+}
+
+impl Field for F17 {
+    const MODULUS: u32 = F17::P;
+    const ZERO: F17 = F17::ZERO;
+    const ONE: F17 = F17::ONE;
+
+    fn from_u32(n: u32) -> Self {
+        F17::new(n)
+    }
+
+    fn value(self) -> u32 {
+        self.0
+    }
+
+    fn add(self, other: F17) -> F17 {
+        F17::add(self, other)
+    }
+
+    fn sub(self, other: F17) -> F17 {
+        F17::sub(self, other)
+    }
+
+    fn mul(self, other: F17) -> F17 {
+        F17::mul(self, other)
+    }
+
+    fn neg(self) -> F17 {
+        F17::neg(self)
+    }
+
+    fn inv(self) -> Option<F17> {
+        F17::inv(self)
+    }
+}
+
+// The scalar field of a PLONK instance: the toy fields through Field, and
+// BigPrimeField for BN254's Fr
+pub trait ScalarField: Copy + Debug + PartialEq + Eq + Hash {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn from_u64(n: u64) -> Self;
+    fn add(self, other: Self) -> Self;
+    fn sub(self, other: Self) -> Self;
+    fn mul(self, other: Self) -> Self;
+    fn neg(self) -> Self;
+    fn inv(self) -> Option<Self>;
+    // Whether the element is reduced, a deserialized one may not be
+    fn is_canonical(self) -> bool;
+
+    fn pow(self, mut exp: u64) -> Self {
+        let mut result = Self::ONE;
+        let mut base = self;
+        while exp > 0 {
+            if exp % 2 == 1 {
+                result = result.mul(base);
+            }
+            base = base.mul(base);
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+impl<F: Field> ScalarField for F {
+    const ZERO: Self = <F as Field>::ZERO;
+    const ONE: Self = <F as Field>::ONE;
+
+    fn from_u64(n: u64) -> Self {
+        F::from_u32((n % u64::from(F::MODULUS)) as u32)
+    }
+
+    fn add(self, other: Self) -> Self {
+        Field::add(self, other)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Field::sub(self, other)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Field::mul(self, other)
+    }
+
+    fn neg(self) -> Self {
+        Field::neg(self)
+    }
+
+    fn inv(self) -> Option<Self> {
+        Field::inv(self)
+    }
+
+    fn is_canonical(self) -> bool {
+        self.value() < F::MODULUS
+    }
+}
+
+impl<P: BigPrimeParams<N>, const N: usize> ScalarField for BigPrimeField<P, N> {
+    const ZERO: Self = BigPrimeField::ZERO;
+    const ONE: Self = BigPrimeField::ONE;
+
+    fn from_u64(n: u64) -> Self {
+        BigPrimeField::from_u64(n)
+    }
+
+    fn add(self, other: Self) -> Self {
+        BigPrimeField::add(self, other)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        BigPrimeField::sub(self, other)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        BigPrimeField::mul(self, other)
+    }
+
+    fn neg(self) -> Self {
+        BigPrimeField::neg(self)
+    }
+
+    fn inv(self) -> Option<Self> {
+        BigPrimeField::inv(self)
+    }
+
+    // Montgomery limbs are always kept below the modulus
+    fn is_canonical(self) -> bool {
+        true
+    }
+
+    fn pow(self, exp: u64) -> Self {
+        BigPrimeField::pow(self, &[exp])
+    }
+}
 
+// A scalar field PLONK can run in: H is a power-of-two subgroup of it, and
+// quotients are computed in Ntt, which has the larger subgroups the coset needs
+pub trait PlonkField: ScalarField {
+    type Ntt: NttField + From<Self>;
+
+    // Back from Ntt, None for an element outside this field
+    fn from_ntt(value: Self::Ntt) -> Option<Self>;
+
+    // A primitive n-th root of unity, the one H = <omega> uses for size n
+    fn root_of_unity(n: usize) -> Option<Self>;
+
+    // H = {1, omega, ..., omega^(n-1)}
+    fn domain(n: usize) -> Vec<Self> {
+        let omega = Self::root_of_unity(n).expect("no subgroup of that size");
+        (0..n as u64).map(|i| omega.pow(i)).collect()
+    }
+}
+
+impl PlonkField for F17 {
+    type Ntt = F172;
+
+    fn from_ntt(value: F172) -> Option<F17> {
+        match value.imag {
+            F17::ZERO => Some(value.real),
+            _ => None,
+        }
+    }
+
+    // 10 generates F17^*, and 10^4 = 4 is the omega of F17::H
+    fn root_of_unity(n: usize) -> Option<F17> {
+        if !n.is_power_of_two() || n > 16 {
+            return None;
+        }
+        Some(F17(10).pow((16 / n) as u32))
+    }
+}
+
+// The fixed challenges and blinding scalars are F17 constants; a larger field
+// reads them as the same integers
+pub fn lift<F: ScalarField>(value: F17) -> F {
+    F::from_u64(value.value().into())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZeroInverseError {
+    // position of the first zero element
+    pub index: usize,
+}
+
+impl fmt::Display for ZeroInverseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "element {} is zero and has no inverse", self.index)
+    }
+}
+
+impl std::error::Error for ZeroInverseError {}
+
+// Montgomery's trick: replaces every element by its inverse with one inversion and
+// 3(n - 1) multiplications. With a zero anywhere the slice is left untouched.
+pub fn batch_inverse<F: ScalarField>(elements: &mut [F]) -> Result<(), ZeroInverseError> {
+    if let Some(index) = elements.iter().position(|&x| x == F::ZERO) {
+        return Err(ZeroInverseError { index });
+    }
+
+    // prefix[i] = x_0 * ... * x_(i-1)
+    let mut prefix = Vec::with_capacity(elements.len());
+    let mut acc = F::ONE;
+    for &x in elements.iter() {
+        prefix.push(acc);
+        acc = acc.mul(x);
+    }
+
+    // acc^-1 = (x_0 * ... * x_(n-1))^-1, peeled off one element at a time from the back
+    let mut acc_inv = acc.inv().expect("product of non-zero elements is non-zero");
+    for (x, prefix) in elements.iter_mut().zip(prefix).rev() {
+        let x_inv = acc_inv.mul(prefix);
+        acc_inv = acc_inv.mul(*x);
+        *x = x_inv;
+    }
+    Ok(())
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u32")
+)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct F101(pub u32);
 
+#[allow(clippy::should_implement_trait)]
 impl F101 {
     pub const P: u32 = 101;
 
@@ -41,6 +319,61 @@ impl F101 {
     pub fn neg(self) -> F101 {
         F101::new(F101::P - self.0)
     }
+
+    pub fn pow(self, exp: u32) -> F101 {
+        F101::new(mod_pow(self.0, exp, Self::P))
+    }
+
+    pub fn is_odd(self) -> bool {
+        self.0 % 2 == 1
+    }
+
+    // Euler's criterion: a^((p-1)/2) = 1 for non-zero squares
+    pub fn is_square(self) -> bool {
+        self == F101::ZERO || self.pow((Self::P - 1) / 2) == F101::ONE
+    }
+
+    // Tonelli-Shanks, returns one of the two roots (the other is its negation)
+    pub fn sqrt(self) -> Option<F101> {
+        if self == F101::ZERO {
+            return Some(F101::ZERO);
+        }
+        if !self.is_square() {
+            return None;
+        }
+
+        // p - 1 = q * 2^s with q odd
+        let mut q = Self::P - 1;
+        let mut s = 0;
+        while q.is_multiple_of(2) {
+            q /= 2;
+            s += 1;
+        }
+        let z = (2..Self::P)
+            .map(F101::new)
+            .find(|z| !z.is_square())
+            .unwrap();
+
+        let mut m = s;
+        let mut c = z.pow(q);
+        let mut t = self.pow(q);
+        let mut r = self.pow(q.div_ceil(2));
+        while t != F101::ONE {
+            // the least i such that t^(2^i) = 1
+            let mut i = 0;
+            let mut t_2i = t;
+            while t_2i != F101::ONE {
+                t_2i = t_2i.mul(t_2i);
+                i += 1;
+            }
+            let b = c.pow(1 << (m - i - 1));
+            m = i;
+            c = b.mul(b);
+            t = t.mul(c);
+            r = r.mul(b);
+        }
+        Some(r)
+    }
 }
 
 pub fn mod_pow(mut base: u32, mut exp: u32, modulus: u32) -> u32 {
@@ -56,75 +389,22 @@ pub fn mod_pow(mut base: u32, mut exp: u32, modulus: u32) -> u32 {
     result
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub enum Point {
-    Infinity,
-    Point { x: F101, y: F101 },
-}
+pub use crate::curve::{point_add, scalar_mult};
 
-impl Point {
-    pub fn point_neg(self) -> Point {
-        match self {
-            Point::Infinity => Point::Infinity,
-            Point::Point { x, y } => Point::Point { x, y: y.neg() },
-        }
-    }
+// Points of y^2 = x^3 + 3 over F101
+pub type Point = AffinePoint<F101Curve>;
 
-    pub fn add(self, other: Point) -> Point {
-        point_add(self, other)
-    }
+// G1 = (1, 2), a point of order 17
+pub const G1: Point = Point::generator();
 
+#[allow(clippy::should_implement_trait)]
+impl Point {
     pub fn mul(self, k: F17) -> Point {
         scalar_mult(k.0, self)
     }
 }
 
-pub fn point_add(p1: Point, p2: Point) -> Point {
-    match (p1, p2) {
-        (Point::Infinity, _) => p2,
-        (_, Point::Infinity) => p1,
-        (Point::Point { x: x1, y: y1 }, Point::Point { x: x2, y: y2 }) => {
-            if x1 == x2 && (y1.add(y2)).value() == 0 {
-                Point::Infinity
-            } else {
-                let m = if x1 == x2 && y1 == y2 {
-                    let numerator = x1.mul(x1).mul(F101::new(3));
-                    let denominator = y1.mul(F101::new(2));
-                    match denominator.inv() {
-                        Some(inv) => numerator.mul(inv),
-                        None => return Point::Infinity,
-                    }
-                } else {
-                    let numerator = y2.sub(y1);
-                    let denominator = x2.sub(x1);
-                    match denominator.inv() {
-                        Some(inv) => numerator.mul(inv),
-                        None => return Point::Infinity,
-                    }
-                };
-                let x3 = m.mul(m).sub(x1).sub(x2);
-                let y3 = m.mul(x1.sub(x3)).sub(y1);
-                Point::Point { x: x3, y: y3 }
-            }
-        }
-    }
-}
-
-pub fn scalar_mult(mut k: u32, p: Point) -> Point {
-    let mut result = Point::Infinity;
-    let mut addend = p;
-
-    while k > 0 {
-        if k % 2 == 1 {
-            result = point_add(result, addend);
-        }
-        addend = point_add(addend, addend);
-        k >>= 1;
-    }
-    result
-}
-
-pub fn print_elliptic_curve_points(g: Point) {
+pub fn print_elliptic_curve_points<C: CurveParams>(g: AffinePoint<C>) {
     let mut points = HashMap::new();
     let mut order = 1u32;
     let mut p = g;
@@ -133,8 +413,8 @@ pub fn print_elliptic_curve_points(g: Point) {
         points.insert(order, p);
         order += 1;
         p = point_add(p, g);
-        if p == Point::Infinity {
-            points.insert(order, Point::Infinity);
+        if p == AffinePoint::Infinity {
+            points.insert(order, AffinePoint::Infinity);
             break;
         }
     }
@@ -147,16 +427,22 @@ pub fn print_elliptic_curve_points(g: Point) {
     for k in 1..=order {
         if let Some(point) = points.get(&k) {
             match point {
-                Point::Infinity => println!("{}: Infinity", k),
-                Point::Point { x, y } => println!("{}: ({}, {})", k, x.value(), y.value()),
+                AffinePoint::Infinity => println!("{}: Infinity", k),
+                AffinePoint::Point { x, y } => println!("{}: ({}, {})", k, x.value(), y.value()),
             }
         }
     }
 }
 
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u32")
+)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct F17(pub u32);
 
+#[allow(clippy::should_implement_trait)]
 impl F17 {
     pub const P: u32 = 17;
 
@@ -236,6 +522,7 @@ fn vander_matrix(points: &[(F17, F17)]) -> [[F17; 4]; 4] {
     matrix
 }
 
+#[allow(clippy::needless_range_loop)]
 fn matrix_inverse(matrix: [[F17; 4]; 4]) -> Option<[[F17; 4]; 4]> {
     let mut augmented_matrix = [[F17::new(0); 8]; 4];
 
@@ -313,7 +600,7 @@ pub fn solve_coefficients(points: &[(F17, F17); 4]) -> Option<[F17; 4]> {
     // println!("vandermonde_inverse: {:?}", inverse);
     // let y_values: [F17; 4] = [F17::new(3), F17::new(4), F17::new(5), F17::new(9)];
     let y_values: [F17; 4] = points
-        .into_iter()
+        .iter()
         .map(|point| point.1)
         .collect::<Vec<_>>()
         .try_into()
@@ -327,7 +614,7 @@ mod tests {
     use super::*;
 
     fn generate_coset(h: &[F17], k: F17) -> Vec<F17> {
-        h.iter().map(|&x| (x.mul(k))).collect()
+        h.iter().map(|&x| x.mul(k)).collect()
     }
 
     #[test]
@@ -339,6 +626,46 @@ mod tests {
         assert_eq!(F17::K2H.to_vec(), generate_coset(&F17::H, F17::new(3)));
     }
 
+    #[test]
+    fn batch_inverse_test() {
+        let mut elements: Vec<F17> = (1..F17::P).map(F17).collect();
+        batch_inverse(&mut elements).unwrap();
+        for (x, x_inv) in (1..F17::P).map(F17).zip(&elements) {
+            assert_eq!(x_inv, &x.inv().unwrap());
+        }
+
+        let mut elements = [F101(3), F101(50), F101(100)];
+        batch_inverse(&mut elements).unwrap();
+        assert_eq!(
+            elements,
+            [F101(3), F101(50), F101(100)].map(|x| x.inv().unwrap())
+        );
+
+        let mut empty: [F17; 0] = [];
+        assert_eq!(batch_inverse(&mut empty), Ok(()));
+
+        let mut with_zero = [F17(5), F17(0), F17(3), F17(0)];
+        assert_eq!(
+            batch_inverse(&mut with_zero),
+            Err(ZeroInverseError { index: 1 })
+        );
+        assert_eq!(with_zero, [F17(5), F17(0), F17(3), F17(0)]);
+    }
+
+    #[test]
+    fn f101_sqrt_test() {
+        let mut squares = 0;
+        for n in 0..F101::P {
+            let x = F101::new(n);
+            if let Some(root) = x.sqrt() {
+                assert_eq!(root.mul(root), x);
+                squares += 1;
+            }
+        }
+        // zero and the (p - 1) / 2 non-zero squares
+        assert_eq!(squares, 1 + (F101::P - 1) / 2);
+    }
+
     #[test]
     fn solve_coefficients_test() {
         let points: [(F17, F17); 4] = [
@@ -1,16 +1,430 @@
-use crate::field::F101;
+use crate::field::{ScalarField, F101, F17};
 
+// F101^2 = F101[u] / (u^2 + 2), elements are real + imag * u
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct F1012 {
-    real: F101,
-    imag: F101,
+    pub real: F101,
+    pub imag: F101,
 }
 
+#[allow(clippy::should_implement_trait)]
 impl F1012 {
+    pub const ZERO: F1012 = F1012 {
+        real: F101::ZERO,
+        imag: F101::ZERO,
+    };
+    pub const ONE: F1012 = F1012 {
+        real: F101::ONE,
+        imag: F101::ZERO,
+    };
+
+    // u^2 = -2
+    pub const U_SQUARE: F101 = F101(99);
+
     pub fn new(real: u32, imag: u32) -> Self {
         F1012 {
             real: F101::new(real),
             imag: F101::new(imag),
         }
     }
+
+    pub fn add(self, other: F1012) -> F1012 {
+        F1012 {
+            real: self.real.add(other.real),
+            imag: self.imag.add(other.imag),
+        }
+    }
+
+    pub fn sub(self, other: F1012) -> F1012 {
+        F1012 {
+            real: self.real.sub(other.real),
+            imag: self.imag.sub(other.imag),
+        }
+    }
+
+    // (a + bu)(c + du) = (ac + bd * u^2) + (ad + bc)u
+    pub fn mul(self, other: F1012) -> F1012 {
+        F1012 {
+            real: self
+                .real
+                .mul(other.real)
+                .add(self.imag.mul(other.imag).mul(Self::U_SQUARE)),
+            imag: self.real.mul(other.imag).add(self.imag.mul(other.real)),
+        }
+    }
+
+    pub fn neg(self) -> F1012 {
+        F1012 {
+            real: self.real.neg(),
+            imag: self.imag.neg(),
+        }
+    }
+
+    // 1 / (a + bu) = (a - bu) / (a^2 + 2b^2)
+    pub fn inv(self) -> Option<F1012> {
+        let norm = self
+            .real
+            .mul(self.real)
+            .sub(self.imag.mul(self.imag).mul(Self::U_SQUARE));
+        let norm_inv = norm.inv()?;
+        Some(F1012 {
+            real: self.real.mul(norm_inv),
+            imag: self.imag.neg().mul(norm_inv),
+        })
+    }
+
+    pub fn pow(self, mut exp: u32) -> F1012 {
+        let mut result = F1012::ONE;
+        let mut base = self;
+        while exp > 0 {
+            if exp % 2 == 1 {
+                result = result.mul(base);
+            }
+            base = base.mul(base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    // Sign used by point compression: the parity of the imaginary part,
+    // or of the real part when the imaginary part is zero
+    pub fn is_odd(self) -> bool {
+        if self.imag != F101::ZERO {
+            self.imag.is_odd()
+        } else {
+            self.real.is_odd()
+        }
+    }
+
+    pub fn is_square(self) -> bool {
+        self == F1012::ZERO || self.pow((F101::P * F101::P - 1) / 2) == F1012::ONE
+    }
+
+    // Tonelli-Shanks over the p^2 - 1 = 2^3 * 1275 multiplicative group
+    pub fn sqrt(self) -> Option<F1012> {
+        if self == F1012::ZERO {
+            return Some(F1012::ZERO);
+        }
+        if !self.is_square() {
+            return None;
+        }
+
+        let mut q = F101::P * F101::P - 1;
+        let mut s = 0;
+        while q.is_multiple_of(2) {
+            q /= 2;
+            s += 1;
+        }
+        let z = (0..F101::P)
+            .flat_map(|real| (1..F101::P).map(move |imag| F1012::new(real, imag)))
+            .find(|z| !z.is_square())
+            .unwrap();
+
+        let mut m = s;
+        let mut c = z.pow(q);
+        let mut t = self.pow(q);
+        let mut r = self.pow(q.div_ceil(2));
+        while t != F1012::ONE {
+            let mut i = 0;
+            let mut t_2i = t;
+            while t_2i != F1012::ONE {
+                t_2i = t_2i.mul(t_2i);
+                i += 1;
+            }
+            let b = c.pow(1 << (m - i - 1));
+            m = i;
+            c = b.mul(b);
+            t = t.mul(c);
+            r = r.mul(b);
+        }
+        Some(r)
+    }
+}
+
+// F17^2 = F17[u] / (u^2 - 3), elements are real + imag * u. F17 itself has only
+// 16 non-zero elements, too few to evaluate the round 3 numerator on, so the
+// quotient NTT runs over this extension instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct F172 {
+    pub real: F17,
+    pub imag: F17,
+}
+
+#[allow(clippy::should_implement_trait)]
+impl F172 {
+    pub const ZERO: F172 = F172 {
+        real: F17::ZERO,
+        imag: F17::ZERO,
+    };
+    pub const ONE: F172 = F172 {
+        real: F17::ONE,
+        imag: F17::ZERO,
+    };
+
+    // u^2 = 3, a non-residue mod 17
+    pub const U_SQUARE: F17 = F17(3);
+
+    pub fn new(real: u32, imag: u32) -> Self {
+        F172 {
+            real: F17::new(real),
+            imag: F17::new(imag),
+        }
+    }
+
+    pub fn add(self, other: F172) -> F172 {
+        F172 {
+            real: self.real.add(other.real),
+            imag: self.imag.add(other.imag),
+        }
+    }
+
+    pub fn sub(self, other: F172) -> F172 {
+        F172 {
+            real: self.real.sub(other.real),
+            imag: self.imag.sub(other.imag),
+        }
+    }
+
+    // (a + bu)(c + du) = (ac + bd * u^2) + (ad + bc)u
+    pub fn mul(self, other: F172) -> F172 {
+        F172 {
+            real: self
+                .real
+                .mul(other.real)
+                .add(self.imag.mul(other.imag).mul(Self::U_SQUARE)),
+            imag: self.real.mul(other.imag).add(self.imag.mul(other.real)),
+        }
+    }
+
+    // 1 / (a + bu) = (a - bu) / (a^2 - 3b^2)
+    pub fn inv(self) -> Option<F172> {
+        let norm = self
+            .real
+            .mul(self.real)
+            .sub(self.imag.mul(self.imag).mul(Self::U_SQUARE));
+        let norm_inv = norm.inv()?;
+        Some(F172 {
+            real: self.real.mul(norm_inv),
+            imag: self.imag.neg().mul(norm_inv),
+        })
+    }
+
+    pub fn pow(self, mut exp: u32) -> F172 {
+        let mut result = F172::ONE;
+        let mut base = self;
+        while exp > 0 {
+            if exp % 2 == 1 {
+                result = result.mul(base);
+            }
+            base = base.mul(base);
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+impl From<F17> for F172 {
+    fn from(real: F17) -> Self {
+        F172 {
+            real,
+            imag: F17::ZERO,
+        }
+    }
+}
+
+impl ScalarField for F172 {
+    const ZERO: F172 = F172::ZERO;
+    const ONE: F172 = F172::ONE;
+
+    fn from_u64(n: u64) -> Self {
+        F172::from(F17::from_u64(n))
+    }
+
+    fn add(self, other: F172) -> F172 {
+        F172::add(self, other)
+    }
+
+    fn sub(self, other: F172) -> F172 {
+        F172::sub(self, other)
+    }
+
+    fn mul(self, other: F172) -> F172 {
+        F172::mul(self, other)
+    }
+
+    fn neg(self) -> F172 {
+        F172::ZERO.sub(self)
+    }
+
+    fn inv(self) -> Option<F172> {
+        F172::inv(self)
+    }
+
+    fn is_canonical(self) -> bool {
+        self.real.is_canonical() && self.imag.is_canonical()
+    }
+}
+
+// Points of y^2 = x^3 + 3 over F101^2
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        rename_all = "snake_case",
+        try_from = "crate::serde_support::G2PointRepr"
+    )
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum G2Point {
+    Infinity,
+    Point { x: F1012, y: F1012 },
+}
+
+// G2 = (36, 31u), a point of order 17
+pub const G2: G2Point = G2Point::Point {
+    x: F1012 {
+        real: F101(36),
+        imag: F101(0),
+    },
+    y: F1012 {
+        real: F101(0),
+        imag: F101(31),
+    },
+};
+
+#[allow(clippy::should_implement_trait)]
+impl G2Point {
+    pub fn neg(self) -> G2Point {
+        match self {
+            G2Point::Infinity => G2Point::Infinity,
+            G2Point::Point { x, y } => G2Point::Point { x, y: y.neg() },
+        }
+    }
+
+    pub fn add(self, other: G2Point) -> G2Point {
+        match (self, other) {
+            (G2Point::Infinity, _) => other,
+            (_, G2Point::Infinity) => self,
+            (G2Point::Point { x: x1, y: y1 }, G2Point::Point { x: x2, y: y2 }) => {
+                if x1 == x2 && y1.add(y2) == F1012::ZERO {
+                    return G2Point::Infinity;
+                }
+                let m = if x1 == x2 && y1 == y2 {
+                    let numerator = x1.mul(x1).mul(F1012::new(3, 0));
+                    let denominator = y1.mul(F1012::new(2, 0));
+                    numerator.mul(denominator.inv().unwrap())
+                } else {
+                    y2.sub(y1).mul(x2.sub(x1).inv().unwrap())
+                };
+                let x3 = m.mul(m).sub(x1).sub(x2);
+                let y3 = m.mul(x1.sub(x3)).sub(y1);
+                G2Point::Point { x: x3, y: y3 }
+            }
+        }
+    }
+
+    pub fn mul(self, k: F17) -> G2Point {
+        self.mul_u32(k.value())
+    }
+
+    fn mul_u32(self, mut k: u32) -> G2Point {
+        let mut result = G2Point::Infinity;
+        let mut addend = self;
+        while k > 0 {
+            if k % 2 == 1 {
+                result = result.add(addend);
+            }
+            addend = addend.add(addend);
+            k >>= 1;
+        }
+        result
+    }
+
+    pub fn is_on_curve(self) -> bool {
+        match self {
+            G2Point::Infinity => true,
+            G2Point::Point { x, y } => y.mul(y) == x.mul(x).mul(x).add(F1012::new(3, 0)),
+        }
+    }
+
+    // [17]Q = O, the curve over F101^2 has 10404 = 2^2 * 3^2 * 17^2 points
+    pub fn is_in_prime_subgroup(self) -> bool {
+        self.is_on_curve() && self.mul_u32(F17::P) == G2Point::Infinity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f1012_arithmetic_test() {
+        let u = F1012::new(0, 1);
+        assert_eq!(u.mul(u), F1012::new(99, 0));
+
+        let x = F1012::new(36, 31);
+        assert_eq!(x.mul(x.inv().unwrap()), F1012::ONE);
+        assert_eq!(F1012::ZERO.inv(), None);
+        // Frobenius: x^p is the conjugate
+        assert_eq!(x.pow(F101::P), F1012::new(36, 70));
+    }
+
+    #[test]
+    fn f172_arithmetic_test() {
+        let u = F172::new(0, 1);
+        assert_eq!(u.mul(u), F172::new(3, 0));
+
+        let x = F172::new(1, 2);
+        assert_eq!(x.mul(x.inv().unwrap()), F172::ONE);
+        assert_eq!(F172::ZERO.inv(), None);
+        // 1 + 2u generates the whole 288 element multiplicative group
+        assert_eq!(x.pow(288), F172::ONE);
+        assert_ne!(x.pow(144), F172::ONE);
+        assert_ne!(x.pow(96), F172::ONE);
+    }
+
+    #[test]
+    fn f1012_sqrt_test() {
+        let mut squares = 0;
+        for real in 0..F101::P {
+            for imag in 0..F101::P {
+                let x = F1012::new(real, imag);
+                if let Some(root) = x.sqrt() {
+                    assert_eq!(root.mul(root), x);
+                    squares += 1;
+                }
+            }
+        }
+        assert_eq!(squares, 1 + (F101::P * F101::P - 1) / 2);
+    }
+
+    #[test]
+    fn g2_test() {
+        assert!(G2.is_on_curve());
+        // tau * G2 in the SRS, tau = 2
+        assert_eq!(
+            G2.mul(F17(2)),
+            G2Point::Point {
+                x: F1012::new(90, 0),
+                y: F1012::new(0, 82)
+            }
+        );
+        assert_eq!(G2.mul(F17(16)).add(G2), G2Point::Infinity);
+        assert_eq!(G2.mul(F17(5)).add(G2.mul(F17(12))), G2Point::Infinity);
+    }
+
+    #[test]
+    fn g2_subgroup_test() {
+        assert!(G2.is_in_prime_subgroup());
+        assert!(G2.mul(F17(9)).is_in_prime_subgroup());
+        // (48, 0) is also a point of order 2 over F101^2
+        let small_order = G2Point::Point {
+            x: F1012::new(48, 0),
+            y: F1012::ZERO,
+        };
+        assert!(small_order.is_on_curve());
+        assert!(!small_order.is_in_prime_subgroup());
+        assert!(!G2.add(small_order).is_in_prime_subgroup());
+    }
 }
@@ -0,0 +1,192 @@
+// Fixed-base scalar multiplication with precomputed windows.
+//
+// For a base P and w-bit windows, row i of the table holds j * 2^(w*i) * P for
+// every digit j < 2^w. Then k * P is one table entry per window added together,
+// with no doublings at all. Building a table costs about as much as a few
+// scalar multiplications, so it pays off for bases that are used over and over:
+// G1, and the SRS points behind every commitment.
+//
+// Tables work for any TablePoint with MsmScalar scalars, so each Engine keeps one
+// for its G1. With the `constant-time` feature, lookups on the toy curves read
+// every entry of a row and keep the wanted one with a mask, so the memory access
+// pattern does not depend on the scalar. The point additions themselves still
+// branch, and the BN254 points are read by index.
+
+use std::sync::OnceLock;
+
+use crate::{
+    curve::{AffinePoint, CurveParams},
+    field::{Point, G1},
+    msm::{window_digit, MsmPoint, MsmScalar},
+};
+
+pub const DEFAULT_WINDOW_BITS: u32 = 4;
+
+// A point a table can hold. lookup returns row[digit].
+pub trait TablePoint: MsmPoint {
+    fn lookup(row: &[Self], digit: u32) -> Self {
+        row[digit as usize]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixedBaseTable<P: TablePoint> {
+    window_bits: u32,
+    rows: Vec<Vec<P>>,
+}
+
+impl<P: TablePoint> FixedBaseTable<P> {
+    // ceil(scalar_bits / w) rows of 2^w points
+    pub fn with_scalar_bits(base: P, window_bits: u32, scalar_bits: u32) -> Self {
+        assert!(
+            (1..=16).contains(&window_bits),
+            "window size must be between 1 and 16 bits"
+        );
+        let num_rows = scalar_bits.div_ceil(window_bits);
+
+        let mut rows = Vec::with_capacity(num_rows as usize);
+        // 2^(w*i) * base
+        let mut row_base = P::add_affine(P::IDENTITY, base);
+        for _ in 0..num_rows {
+            let mut row = Vec::with_capacity(1 << window_bits);
+            let mut entry = P::IDENTITY;
+            for _ in 0..1u32 << window_bits {
+                row.push(P::to_affine(entry));
+                entry = P::add(entry, row_base);
+            }
+            rows.push(row);
+            for _ in 0..window_bits {
+                row_base = P::double(row_base);
+            }
+        }
+        FixedBaseTable { window_bits, rows }
+    }
+
+    // A table for every scalar of S
+    pub fn for_scalars<S: MsmScalar>(base: P) -> Self {
+        FixedBaseTable::with_scalar_bits(base, DEFAULT_WINDOW_BITS, S::BITS)
+    }
+
+    pub fn base(&self) -> P {
+        self.rows[0][1]
+    }
+
+    // k * base, with k in little-endian limbs below 2^(w * rows)
+    pub fn mul_limbs(&self, k: &[u64]) -> P {
+        #[cfg(test)]
+        MULS.with(|muls| muls.set(muls.get() + 1));
+        let mut result = P::IDENTITY;
+        for (i, row) in self.rows.iter().enumerate() {
+            let digit = window_digit(k, self.window_bits * i as u32, self.window_bits);
+            result = P::add_affine(result, P::lookup(row, digit));
+        }
+        P::to_affine(result)
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn mul<S: MsmScalar>(&self, k: S) -> P {
+        self.mul_limbs(&k.to_limbs())
+    }
+}
+
+impl<C: CurveParams> FixedBaseTable<AffinePoint<C>> {
+    // Tables have ceil(log2(order) / w) rows of 2^w points
+    pub fn new(base: AffinePoint<C>, window_bits: u32) -> Self {
+        let scalar_bits = u32::BITS - (C::ORDER - 1).leading_zeros();
+        FixedBaseTable::with_scalar_bits(base, window_bits, scalar_bits)
+    }
+
+    // k * base. The base is expected to be in the prime order subgroup, so k is
+    // reduced modulo the subgroup order first.
+    pub fn mul_u32(&self, k: u32) -> AffinePoint<C> {
+        self.mul_limbs(&[u64::from(k % C::ORDER)])
+    }
+}
+
+impl<C: CurveParams> TablePoint for AffinePoint<C> {
+    #[cfg(feature = "constant-time")]
+    fn lookup(row: &[AffinePoint<C>], digit: u32) -> AffinePoint<C> {
+        use crate::field::Field;
+
+        let (mut x, mut y, mut infinity) = (0u32, 0u32, 0u32);
+        for (j, entry) in row.iter().enumerate() {
+            // all ones when j = digit, zero otherwise, without a branch
+            let mask = (((j as u32) ^ digit).wrapping_sub(1) >> 31).wrapping_neg();
+            let (entry_x, entry_y, entry_infinity) = match *entry {
+                AffinePoint::Infinity => (0, 0, 1),
+                AffinePoint::Point { x, y } => (x.value(), y.value(), 0),
+            };
+            x |= entry_x & mask;
+            y |= entry_y & mask;
+            infinity |= entry_infinity & mask;
+        }
+        if infinity == 1 {
+            AffinePoint::Infinity
+        } else {
+            AffinePoint::Point {
+                x: C::Base::from_u32(x),
+                y: C::Base::from_u32(y),
+            }
+        }
+    }
+}
+
+// The table for G1, built on first use
+pub fn g1_table() -> &'static FixedBaseTable<Point> {
+    static TABLE: OnceLock<FixedBaseTable<Point>> = OnceLock::new();
+    TABLE.get_or_init(|| FixedBaseTable::new(G1, DEFAULT_WINDOW_BITS))
+}
+
+// Table multiplications on this thread, so tests can check a path goes through
+// the tables
+#[cfg(test)]
+thread_local! {
+    static MULS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+pub(crate) fn table_muls() -> usize {
+    MULS.with(|muls| muls.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        curve::{scalar_mult, F17Curve},
+        field::F17,
+    };
+
+    use super::*;
+
+    fn check_table<C: CurveParams>(base: AffinePoint<C>) {
+        for window_bits in 1..=6 {
+            let table = FixedBaseTable::new(base, window_bits);
+            assert_eq!(table.base(), base);
+            for k in 0..2 * C::ORDER {
+                assert_eq!(
+                    table.mul_u32(k),
+                    scalar_mult(k, base),
+                    "k = {}, w = {}",
+                    k,
+                    window_bits
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_base_table_test() {
+        check_table(G1);
+        check_table(G1.mul(F17(5)));
+        check_table(AffinePoint::<F17Curve>::generator());
+    }
+
+    #[test]
+    fn g1_table_test() {
+        for k in 0..F17::P {
+            assert_eq!(g1_table().mul(F17(k)), G1.mul(F17(k)));
+        }
+        // reused, not rebuilt
+        assert!(std::ptr::eq(g1_table(), g1_table()));
+    }
+}
@@ -0,0 +1,384 @@
+// Reusable gadgets on the constraint system. Each one assigns its outputs and
+// hints from the values of its inputs and adds the rows that check them, so a
+// dishonest hint leaves an unsatisfied row behind.
+//
+// Binary decompositions only mean something while 2^bits <= p: in F17 a range
+// check covers at most 4 bits, and less_than compares at most 3-bit values.
+//
+// Only gadgets of at most 4 rows can be proved, see ConstraintSystem::to_circuit.
+
+use crate::{
+    constraint_system::{ConstraintSystem, Selectors},
+    field::F17,
+    witness::Variable,
+};
+
+pub const MAX_RANGE_BITS: usize = 4;
+
+// x (x - 1) = 0, one gate
+pub fn assert_boolean(cs: &mut ConstraintSystem, x: Variable) {
+    cs.gate(
+        x,
+        x,
+        x,
+        Selectors {
+            q_l: F17::NEG_ONE,
+            q_m: F17::ONE,
+            ..Selectors::default()
+        },
+    );
+}
+
+// x = sum 2^i bit_i with every bit boolean, 2 * bits gates. Returns the bits,
+// least significant first.
+pub fn range_check(cs: &mut ConstraintSystem, x: Variable, bits: usize) -> Vec<Variable> {
+    assert!(
+        (1..=MAX_RANGE_BITS).contains(&bits),
+        "a range check covers 1 to {} bits",
+        MAX_RANGE_BITS
+    );
+    let value = cs.value(x).0;
+    let bit_vars = (0..bits)
+        .map(|i| {
+            let bit = cs.alloc(F17((value >> i) & 1));
+            assert_boolean(cs, bit);
+            bit
+        })
+        .collect::<Vec<_>>();
+
+    // acc_i = acc_(i-1) + 2^i bit_i
+    let mut acc = bit_vars[0];
+    for (i, &bit) in bit_vars.iter().enumerate().skip(1) {
+        let weight = F17(1 << i);
+        let next = cs.alloc(cs.value(acc).add(weight.mul(cs.value(bit))));
+        cs.gate(
+            acc,
+            bit,
+            next,
+            Selectors {
+                q_l: F17::ONE,
+                q_r: weight,
+                q_o: F17::NEG_ONE,
+                ..Selectors::default()
+            },
+        );
+        acc = next;
+    }
+    cs.assert_equal(acc, x);
+    bit_vars
+}
+
+// cond (a - b) + b, so a when cond is 1 and b when it is 0. cond must be boolean,
+// see assert_boolean. 3 gates.
+pub fn select(cs: &mut ConstraintSystem, cond: Variable, a: Variable, b: Variable) -> Variable {
+    let difference = cs.sub(a, b);
+    let scaled = cs.mul(cond, difference);
+    cs.add(scaled, b)
+}
+
+// x^-1 as a hint, checked by x * inv = 1. There is no inverse of zero, so x = 0
+// gets the hint 0 and an unsatisfied row. 1 gate.
+pub fn inverse(cs: &mut ConstraintSystem, x: Variable) -> Variable {
+    let inv = cs.alloc(cs.value(x).inv().unwrap_or(F17::ZERO));
+    cs.gate(
+        x,
+        inv,
+        x,
+        Selectors {
+            q_m: F17::ONE,
+            q_c: F17::NEG_ONE,
+            ..Selectors::default()
+        },
+    );
+    inv
+}
+
+// 1 if x = 0, else 0. With the hint inv = x^-1 (0 for x = 0):
+//   out = 1 - x inv
+//   x out = 0
+// 3 gates.
+pub fn is_zero(cs: &mut ConstraintSystem, x: Variable) -> Variable {
+    let inv = cs.alloc(cs.value(x).inv().unwrap_or(F17::ZERO));
+    let product = cs.mul(x, inv);
+    let out = cs.alloc(F17::ONE.sub(cs.value(product)));
+    cs.gate(
+        product,
+        product,
+        out,
+        Selectors {
+            q_l: F17::NEG_ONE,
+            q_o: F17::NEG_ONE,
+            q_c: F17::ONE,
+            ..Selectors::default()
+        },
+    );
+    cs.gate(
+        x,
+        out,
+        x,
+        Selectors {
+            q_m: F17::ONE,
+            ..Selectors::default()
+        },
+    );
+    out
+}
+
+// 1 if a < b, else 0, for a and b already range checked to `bits` bits.
+// d = a - b + 2^bits lies in [0, 2^(bits + 1)) and its top bit is 1 exactly when
+// a >= b. 2 * bits + 4 gates.
+pub fn less_than(cs: &mut ConstraintSystem, a: Variable, b: Variable, bits: usize) -> Variable {
+    assert!(
+        (1..MAX_RANGE_BITS).contains(&bits),
+        "less_than compares 1 to {} bits",
+        MAX_RANGE_BITS - 1
+    );
+    let offset = F17(1 << bits);
+    let d = cs.alloc(cs.value(a).sub(cs.value(b)).add(offset));
+    cs.gate(
+        a,
+        b,
+        d,
+        Selectors {
+            q_l: F17::ONE,
+            q_r: F17::NEG_ONE,
+            q_o: F17::NEG_ONE,
+            q_c: offset,
+            ..Selectors::default()
+        },
+    );
+    let top = range_check(cs, d, bits + 1)[bits];
+    // out = 1 - top
+    let out = cs.alloc(F17::ONE.sub(cs.value(top)));
+    cs.gate(
+        top,
+        top,
+        out,
+        Selectors {
+            q_l: F17::NEG_ONE,
+            q_o: F17::NEG_ONE,
+            q_c: F17::ONE,
+            ..Selectors::default()
+        },
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        constraint_system::UnsatisfiedRow,
+        plonk_circuit::ProvingKey,
+        round5::gen_round5_result,
+        verifier::{gen_verifying_key, verifier_process},
+    };
+
+    use super::*;
+
+    #[test]
+    fn assert_boolean_test() {
+        for value in 0..17 {
+            let mut cs = ConstraintSystem::new();
+            let x = cs.alloc(F17(value));
+            assert_boolean(&mut cs, x);
+            assert_eq!(cs.num_gates(), 1);
+            assert_eq!(cs.check().is_ok(), value < 2, "x = {}", value);
+        }
+    }
+
+    #[test]
+    fn range_check_test() {
+        for bits in 1..=MAX_RANGE_BITS {
+            for value in 0..17 {
+                let mut cs = ConstraintSystem::new();
+                let x = cs.alloc(F17(value));
+                let bit_vars = range_check(&mut cs, x, bits);
+                assert_eq!(cs.num_gates(), 2 * bits);
+                assert_eq!(bit_vars.len(), bits);
+                let in_range = value < 1 << bits;
+                assert_eq!(cs.check().is_ok(), in_range, "{} in {} bits", value, bits);
+            }
+        }
+
+        // 5 = 101b, and a non-boolean bit that still sums to 5 is caught
+        let mut cs = ConstraintSystem::new();
+        let x = cs.alloc(F17(5));
+        let bit_vars = range_check(&mut cs, x, 3);
+        let values = bit_vars
+            .iter()
+            .map(|&bit| cs.value(bit))
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![F17(1), F17(0), F17(1)]);
+        // 5 = 3 + 2 * 1 + 4 * 0 with consistent accumulators, only the boolean
+        // check of bit_0 fails
+        cs.assign(bit_vars[0], F17(3));
+        cs.assign(bit_vars[1], F17(1));
+        cs.assign(bit_vars[2], F17(0));
+        let accs = [cs.rows()[3].c, cs.rows()[4].c];
+        cs.assign(accs[0], F17(5));
+        cs.assign(accs[1], F17(5));
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 0 }));
+    }
+
+    #[test]
+    fn select_test() {
+        for cond in [0, 1] {
+            let mut cs = ConstraintSystem::new();
+            let c = cs.alloc(F17(cond));
+            let a = cs.alloc(F17(7));
+            let b = cs.alloc(F17(12));
+            let out = select(&mut cs, c, a, b);
+            assert_eq!(cs.num_gates(), 3);
+            assert_eq!(cs.check(), Ok(()));
+            assert_eq!(cs.value(out), if cond == 1 { F17(7) } else { F17(12) });
+
+            // a claimed output other than the selected value
+            cs.assign(out, F17(7 + 12 - cs.value(out).0));
+            assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 2 }));
+        }
+    }
+
+    #[test]
+    fn inverse_test() {
+        let mut cs = ConstraintSystem::new();
+        let x = cs.alloc(F17(5));
+        let inv = inverse(&mut cs, x);
+        assert_eq!(cs.num_gates(), 1);
+        // 5 * 7 = 35 = 1 mod 17
+        assert_eq!(cs.value(inv), F17(7));
+        assert_eq!(cs.check(), Ok(()));
+        cs.assign(inv, F17(8));
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 0 }));
+
+        let mut cs = ConstraintSystem::new();
+        let zero = cs.alloc(F17::ZERO);
+        inverse(&mut cs, zero);
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 0 }));
+    }
+
+    #[test]
+    fn is_zero_test() {
+        for value in 0..17 {
+            let mut cs = ConstraintSystem::new();
+            let x = cs.alloc(F17(value));
+            let out = is_zero(&mut cs, x);
+            assert_eq!(cs.num_gates(), 3);
+            assert_eq!(cs.check(), Ok(()));
+            assert_eq!(cs.value(out), if value == 0 { F17::ONE } else { F17::ZERO });
+        }
+
+        // claiming x = 3 is zero needs out = 1, which x * out = 0 rejects
+        let mut cs = ConstraintSystem::new();
+        let x = cs.alloc(F17(3));
+        let out = is_zero(&mut cs, x);
+        let product = cs.rows()[0].c;
+        cs.assign(product, F17::ZERO);
+        cs.assign(out, F17::ONE);
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 0 }));
+        // with a hint of 0 for the inverse only the last row can fail
+        let inv = cs.rows()[0].b;
+        cs.assign(inv, F17::ZERO);
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 2 }));
+    }
+
+    #[test]
+    fn less_than_test() {
+        for bits in 1..MAX_RANGE_BITS {
+            for a_value in 0..1 << bits {
+                for b_value in 0..1 << bits {
+                    let mut cs = ConstraintSystem::new();
+                    let a = cs.alloc(F17(a_value));
+                    let b = cs.alloc(F17(b_value));
+                    let out = less_than(&mut cs, a, b, bits);
+                    assert_eq!(cs.num_gates(), 2 * bits + 4);
+                    assert_eq!(cs.check(), Ok(()));
+                    let expected = if a_value < b_value {
+                        F17::ONE
+                    } else {
+                        F17::ZERO
+                    };
+                    assert_eq!(cs.value(out), expected, "{} < {}", a_value, b_value);
+                }
+            }
+        }
+
+        // flipping the answer needs a different top bit, which the
+        // decomposition of d rejects
+        let mut cs = ConstraintSystem::new();
+        let a = cs.alloc(F17(2));
+        let b = cs.alloc(F17(5));
+        let out = less_than(&mut cs, a, b, 3);
+        assert_eq!(cs.value(out), F17::ONE);
+        let top = cs.rows()[4].a;
+        cs.assign(top, F17::ONE);
+        cs.assign(out, F17::ZERO);
+        assert!(cs.check().is_err());
+    }
+
+    #[test]
+    fn gadgets_compose_test() {
+        // max(a, b) for 3-bit a and b: range checks, less_than, then select
+        let mut cs = ConstraintSystem::new();
+        let a = cs.alloc(F17(6));
+        let b = cs.alloc(F17(3));
+        range_check(&mut cs, a, 3);
+        range_check(&mut cs, b, 3);
+        let a_less = less_than(&mut cs, a, b, 3);
+        let max = select(&mut cs, a_less, b, a);
+        assert_eq!(cs.num_gates(), 6 + 6 + 10 + 3);
+        assert_eq!(cs.value(max), F17(6));
+        assert_eq!(cs.check(), Ok(()));
+        // 25 rows, far past the 4 of a circuit
+        assert!(cs.to_circuit().is_err());
+    }
+
+    fn prove_and_verify(cs: &ConstraintSystem) -> bool {
+        let (circuit, witness) = cs.to_circuit().unwrap();
+        let vk = gen_verifying_key(&circuit);
+        let proof = gen_round5_result(&ProvingKey::new(circuit), &witness).unwrap();
+        verifier_process(&vk, &proof)
+    }
+
+    #[test]
+    fn gadgets_prove_test() {
+        for value in 0..17 {
+            let mut cs = ConstraintSystem::new();
+            let x = cs.alloc(F17(value));
+            is_zero(&mut cs, x);
+            assert!(prove_and_verify(&cs), "is_zero({})", value);
+        }
+
+        // a boolean condition and the select, 4 rows
+        for cond in [0, 1] {
+            let mut cs = ConstraintSystem::new();
+            let c = cs.alloc(F17(cond));
+            let a = cs.alloc(F17(7));
+            let b = cs.alloc(F17(12));
+            assert_boolean(&mut cs, c);
+            select(&mut cs, c, a, b);
+            assert!(prove_and_verify(&cs), "select({})", cond);
+        }
+
+        for value in 0..4 {
+            let mut cs = ConstraintSystem::new();
+            let x = cs.alloc(F17(value));
+            range_check(&mut cs, x, 2);
+            assert!(prove_and_verify(&cs), "{} in 2 bits", value);
+        }
+
+        // the proof is bound to the circuit, select's rows do not verify is_zero
+        let mut cs = ConstraintSystem::new();
+        let x = cs.alloc(F17(0));
+        is_zero(&mut cs, x);
+        let (is_zero_circuit, _) = cs.to_circuit().unwrap();
+        let mut cs = ConstraintSystem::new();
+        let [c, a, b] = [1, 7, 12].map(|value| cs.alloc(F17(value)));
+        select(&mut cs, c, a, b);
+        let (circuit, witness) = cs.to_circuit().unwrap();
+        let proof = gen_round5_result(&ProvingKey::new(circuit), &witness).unwrap();
+        assert!(!verifier_process(
+            &gen_verifying_key(&is_zero_circuit),
+            &proof
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+// Jacobian coordinates for points of y^2 = x^3 + ax + b.
+//
+// (X, Y, Z) stands for the affine point (X / Z^2, Y / Z^3), and Z = 0 for the point
+// at infinity. Adding and doubling need no inversion, only `to_affine` does, so
+// long chains of group operations convert back once at the end.
+// Formulas are from the Explicit-Formulas Database: dbl-2007-bl, add-2007-bl and
+// madd-2007-bl. Only doubling depends on the curve, through a.
+
+use crate::{
+    curve::{AffinePoint, CurveParams, F101Curve},
+    field::{Field, F17},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct JacobianPoint<C: CurveParams> {
+    pub x: C::Base,
+    pub y: C::Base,
+    pub z: C::Base,
+}
+
+#[allow(clippy::should_implement_trait)]
+impl<C: CurveParams> JacobianPoint<C> {
+    pub const INFINITY: JacobianPoint<C> = JacobianPoint {
+        x: C::Base::ONE,
+        y: C::Base::ONE,
+        z: C::Base::ZERO,
+    };
+
+    pub fn from_affine(p: AffinePoint<C>) -> JacobianPoint<C> {
+        match p {
+            AffinePoint::Infinity => JacobianPoint::INFINITY,
+            AffinePoint::Point { x, y } => JacobianPoint {
+                x,
+                y,
+                z: C::Base::ONE,
+            },
+        }
+    }
+
+    pub fn to_affine(self) -> AffinePoint<C> {
+        match self.z.inv() {
+            None => AffinePoint::Infinity,
+            Some(z_inv) => {
+                let z_inv2 = z_inv.mul(z_inv);
+                AffinePoint::Point {
+                    x: self.x.mul(z_inv2),
+                    y: self.y.mul(z_inv2).mul(z_inv),
+                }
+            }
+        }
+    }
+
+    pub fn is_infinity(self) -> bool {
+        self.z == C::Base::ZERO
+    }
+
+    pub fn neg(self) -> JacobianPoint<C> {
+        JacobianPoint {
+            y: self.y.neg(),
+            ..self
+        }
+    }
+
+    pub fn double(self) -> JacobianPoint<C> {
+        if self.is_infinity() {
+            return self;
+        }
+        let xx = self.x.mul(self.x);
+        let yy = self.y.mul(self.y);
+        let yyyy = yy.mul(yy);
+        let zz = self.z.mul(self.z);
+        let x_plus_yy = self.x.add(yy);
+        let s = x_plus_yy.mul(x_plus_yy).sub(xx).sub(yyyy);
+        let s = s.add(s);
+        // 3x^2 + a z^4, the tangent slope numerator
+        let m = xx.add(xx).add(xx).add(C::A.mul(zz).mul(zz));
+
+        let x3 = m.mul(m).sub(s).sub(s);
+        let yyyy2 = yyyy.add(yyyy);
+        let yyyy4 = yyyy2.add(yyyy2);
+        let yyyy8 = yyyy4.add(yyyy4);
+        let y3 = m.mul(s.sub(x3)).sub(yyyy8);
+        let y_plus_z = self.y.add(self.z);
+        // a point with y = 0 has order 2, and z3 = 0 makes its double the point at infinity
+        let z3 = y_plus_z.mul(y_plus_z).sub(yy).sub(zz);
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    pub fn add(self, other: JacobianPoint<C>) -> JacobianPoint<C> {
+        if self.is_infinity() {
+            return other;
+        }
+        if other.is_infinity() {
+            return self;
+        }
+        let z1z1 = self.z.mul(self.z);
+        let z2z2 = other.z.mul(other.z);
+        let u1 = self.x.mul(z2z2);
+        let u2 = other.x.mul(z1z1);
+        let s1 = self.y.mul(other.z).mul(z2z2);
+        let s2 = other.y.mul(self.z).mul(z1z1);
+        let h = u2.sub(u1);
+        let r = s2.sub(s1);
+        if h == C::Base::ZERO {
+            // same x: either the same point or its negation
+            return if r == C::Base::ZERO {
+                self.double()
+            } else {
+                JacobianPoint::INFINITY
+            };
+        }
+
+        let h2 = h.add(h);
+        let i = h2.mul(h2);
+        let j = h.mul(i);
+        let r = r.add(r);
+        let v = u1.mul(i);
+        let x3 = r.mul(r).sub(j).sub(v).sub(v);
+        let s1_j = s1.mul(j);
+        let y3 = r.mul(v.sub(x3)).sub(s1_j).sub(s1_j);
+        let z1_plus_z2 = self.z.add(other.z);
+        let z3 = z1_plus_z2.mul(z1_plus_z2).sub(z1z1).sub(z2z2).mul(h);
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    // Mixed addition, other is affine (Z = 1), which saves a few multiplications
+    pub fn add_affine(self, other: AffinePoint<C>) -> JacobianPoint<C> {
+        let (x2, y2) = match other {
+            AffinePoint::Infinity => return self,
+            AffinePoint::Point { x, y } => (x, y),
+        };
+        if self.is_infinity() {
+            return JacobianPoint::from_affine(other);
+        }
+        let z1z1 = self.z.mul(self.z);
+        let u2 = x2.mul(z1z1);
+        let s2 = y2.mul(self.z).mul(z1z1);
+        let h = u2.sub(self.x);
+        let r = s2.sub(self.y);
+        if h == C::Base::ZERO {
+            return if r == C::Base::ZERO {
+                self.double()
+            } else {
+                JacobianPoint::INFINITY
+            };
+        }
+
+        let hh = h.mul(h);
+        let i = hh.add(hh).add(hh.add(hh));
+        let j = h.mul(i);
+        let r = r.add(r);
+        let v = self.x.mul(i);
+        let x3 = r.mul(r).sub(j).sub(v).sub(v);
+        let y1_j = self.y.mul(j);
+        let y3 = r.mul(v.sub(x3)).sub(y1_j).sub(y1_j);
+        let z1_plus_h = self.z.add(h);
+        let z3 = z1_plus_h.mul(z1_plus_h).sub(z1z1).sub(hh);
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    // Left-to-right double-and-add
+    pub fn mul_u32(self, k: u32) -> JacobianPoint<C> {
+        let mut result = JacobianPoint::INFINITY;
+        for i in (0..u32::BITS - k.leading_zeros()).rev() {
+            result = result.double();
+            if (k >> i) & 1 == 1 {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+}
+
+#[allow(clippy::should_implement_trait)]
+impl JacobianPoint<F101Curve> {
+    pub fn mul(self, k: F17) -> Self {
+        self.mul_u32(k.value())
+    }
+}
+
+impl<C: CurveParams> From<AffinePoint<C>> for JacobianPoint<C> {
+    fn from(p: AffinePoint<C>) -> Self {
+        JacobianPoint::from_affine(p)
+    }
+}
+
+impl<C: CurveParams> From<JacobianPoint<C>> for AffinePoint<C> {
+    fn from(p: JacobianPoint<C>) -> Self {
+        p.to_affine()
+    }
+}
+
+// Equal when they represent the same affine point: X1 Z2^2 = X2 Z1^2 and Y1 Z2^3 = Y2 Z1^3
+impl<C: CurveParams> PartialEq for JacobianPoint<C> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.is_infinity(), other.is_infinity()) {
+            (true, true) => true,
+            (false, false) => {
+                let z1z1 = self.z.mul(self.z);
+                let z2z2 = other.z.mul(other.z);
+                self.x.mul(z2z2) == other.x.mul(z1z1)
+                    && self.y.mul(z2z2).mul(other.z) == other.y.mul(z1z1).mul(self.z)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<C: CurveParams> Eq for JacobianPoint<C> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        curve::{point_add, F17Curve},
+        curve_analysis::CurveSpec,
+        field::{Point, G1},
+    };
+
+    use super::*;
+
+    fn check_matches_affine<C: CurveParams>() {
+        // a non-trivial Z, so the inputs are not all normalized
+        let scaled = |p: AffinePoint<C>| {
+            let j = JacobianPoint::from_affine(p);
+            let z = C::Base::from_u32(7);
+            JacobianPoint::<C> {
+                x: j.x.mul(z.mul(z)),
+                y: j.y.mul(z.mul(z).mul(z)),
+                z: j.z.mul(z),
+            }
+        };
+        let points = CurveSpec::curve_points::<C>();
+        for &p in &points {
+            assert_eq!(scaled(p).to_affine(), p);
+            assert_eq!(scaled(p).double().to_affine(), point_add(p, p));
+            assert_eq!(scaled(p).neg().to_affine(), p.point_neg());
+            for &q in &points {
+                let expected = point_add(p, q);
+                assert_eq!(scaled(p).add(scaled(q)).to_affine(), expected);
+                assert_eq!(scaled(p).add_affine(q).to_affine(), expected);
+                assert_eq!(scaled(p).add(JacobianPoint::from(q)), scaled(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn jacobian_matches_affine_test() {
+        // y^2 = x^3 + 3 includes the order 2 point (48, 0)
+        check_matches_affine::<F101Curve>();
+        // a = 2 exercises the a * z^4 term of doubling
+        check_matches_affine::<F17Curve>();
+    }
+
+    #[test]
+    fn jacobian_mul_test() {
+        let g = JacobianPoint::from(G1);
+        let mut expected = Point::Infinity;
+        for k in 0..F17::P {
+            assert_eq!(Point::from(g.mul(F17(k))), expected);
+            expected = point_add(expected, G1);
+        }
+        assert!(g.mul(F17(16)).add(g).is_infinity());
+        assert_ne!(g, g.double());
+        assert_eq!(g.double(), g.add_affine(G1));
+    }
+}
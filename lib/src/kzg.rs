@@ -0,0 +1,492 @@
+// KZG polynomial commitments over any Engine.
+//
+// commit: [p(tau)]_1
+// open:   v = p(z) and the witness [q(tau)]_1 for q(x) = (p(x) - v) / (x - z)
+// verify: e([p] - v*G1, G2) = e([q], tau*G2 - z*G2)
+//
+// batch_open / batch_verify open many polynomials at many points with two group
+// elements and one pairing equation (Shplonk, BDFG20). With T the union of all
+// points, S_i the points of f_i and r_i the interpolant of f_i on S_i:
+//   W  = [h],  h(x) = sum gamma^i Z_{T\S_i}(x) (f_i(x) - r_i(x)) / Z_T(x)
+//   L(x) = sum gamma^i Z_{T\S_i}(z) (f_i(x) - r_i(z)) - Z_T(z) h(x), L(z) = 0
+//   W' = [L(x) / (x - z)]
+// and the verifier checks e([L] + z*W', G2) = e(W', tau*G2).
+
+use std::fmt;
+
+pub use crate::srs::{commit, CommitError};
+use crate::{
+    engine::{Engine, Group, Toy},
+    fiat_shamir::FiatShamir,
+    field::{lift, ScalarField},
+    msm::msm,
+    polynomial::Polynomial,
+    srs::SRS,
+};
+
+// q(x) = (p(x) - p(z)) / (x - z), the remainder is zero by construction
+pub fn witness_poly<F: ScalarField>(poly: &Polynomial<F>, z: F) -> Polynomial<F> {
+    let shifted = poly.sub(&Polynomial {
+        coeffs: vec![poly.evaluate(z)],
+    });
+    if shifted.coeffs.len() < 2 {
+        return Polynomial { coeffs: vec![] };
+    }
+    let (quotient, _remainder) = shifted.long_div(&Polynomial {
+        coeffs: vec![z.neg(), F::ONE],
+    });
+    quotient
+}
+
+pub fn open<E: Engine>(
+    srs: &SRS<E>,
+    poly: &Polynomial<E::Fr>,
+    z: E::Fr,
+) -> Result<(E::Fr, E::G1), CommitError> {
+    let value = poly.evaluate(z);
+    let witness = commit(srs, &witness_poly(poly, z))?;
+    Ok((value, witness))
+}
+
+pub fn verify<E: Engine>(
+    srs: &SRS<E>,
+    commitment: E::G1,
+    z: E::Fr,
+    value: E::Fr,
+    witness: E::G1,
+) -> bool {
+    let g2 = srs.g2_points[0];
+    let tau_g2 = srs.g2_points[1];
+    E::pairing_product_is_one(&[
+        (commitment.add(E::g1_table().mul(value).neg()), g2),
+        (witness.neg(), tau_g2.add(g2.mul(z).neg())),
+    ])
+}
+
+// The claim that the polynomial behind `commitment` takes `values[i]` at `points[i]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchOpening<E: Engine = Toy> {
+    pub commitment: E::G1,
+    pub points: Vec<E::Fr>,
+    pub values: Vec<E::Fr>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchProof<E: Engine = Toy> {
+    pub w: E::G1,
+    pub w_prime: E::G1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchOpenError {
+    Commit(CommitError),
+    // the points of one query are not distinct
+    RepeatedPoint { query: usize },
+    // every draw of z fell in T
+    NoEvaluationPoint,
+}
+
+impl From<CommitError> for BatchOpenError {
+    fn from(err: CommitError) -> Self {
+        BatchOpenError::Commit(err)
+    }
+}
+
+impl fmt::Display for BatchOpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchOpenError::Commit(err) => write!(f, "{}", err),
+            BatchOpenError::RepeatedPoint { query } => {
+                write!(f, "query {} opens at the same point twice", query)
+            }
+            BatchOpenError::NoEvaluationPoint => {
+                write!(f, "no evaluation point outside the opening points")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BatchOpenError {}
+
+// Draws of z before giving up, each lands in T with probability |T| / 17
+const MAX_EVALUATION_DRAWS: usize = 64;
+
+// gamma binds every claim, z is drawn after W is fixed
+fn batch_transcript<E: Engine>(openings: &[BatchOpening<E>]) -> FiatShamir {
+    let mut fs = FiatShamir::new(b"kzg-batch-open");
+    for opening in openings {
+        fs.absorb(&opening.commitment);
+        for (point, value) in opening.points.iter().zip(&opening.values) {
+            fs.absorb(point);
+            fs.absorb(value);
+        }
+    }
+    fs
+}
+
+// z outside T: at a point of T, Z_T(z) = 0 takes W out of the check and the
+// claimed values are no longer bound
+fn evaluation_point<F: ScalarField>(fs: &mut FiatShamir, all_points: &[F]) -> Option<F> {
+    (0..MAX_EVALUATION_DRAWS)
+        .map(|_| lift(fs.challenge()))
+        .find(|z| !all_points.contains(z))
+}
+
+fn union_of_points<E: Engine>(openings: &[BatchOpening<E>]) -> Vec<E::Fr> {
+    let mut all_points: Vec<E::Fr> = Vec::new();
+    for point in openings.iter().flat_map(|opening| &opening.points) {
+        if !all_points.contains(point) {
+            all_points.push(*point);
+        }
+    }
+    all_points
+}
+
+// T \ S_i
+fn other_points<F: ScalarField>(all_points: &[F], points: &[F]) -> Vec<F> {
+    all_points
+        .iter()
+        .copied()
+        .filter(|point| !points.contains(point))
+        .collect()
+}
+
+fn interpolant<E: Engine>(opening: &BatchOpening<E>) -> Polynomial<E::Fr> {
+    let points: Vec<(E::Fr, E::Fr)> = opening
+        .points
+        .iter()
+        .copied()
+        .zip(opening.values.iter().copied())
+        .collect();
+    Polynomial::interpolate(&points)
+}
+
+fn has_distinct_points<F: ScalarField>(points: &[F]) -> bool {
+    points
+        .iter()
+        .enumerate()
+        .all(|(i, point)| !points[..i].contains(point))
+}
+
+// Opens each polynomial at its own set of points, the points of one polynomial must be distinct
+#[allow(clippy::type_complexity)]
+pub fn batch_open<E: Engine>(
+    srs: &SRS<E>,
+    queries: &[(&Polynomial<E::Fr>, &[E::Fr])],
+) -> Result<(Vec<BatchOpening<E>>, BatchProof<E>), BatchOpenError> {
+    let mut openings = Vec::with_capacity(queries.len());
+    for (query, &(poly, points)) in queries.iter().enumerate() {
+        if !has_distinct_points(points) {
+            return Err(BatchOpenError::RepeatedPoint { query });
+        }
+        openings.push(BatchOpening {
+            commitment: commit(srs, poly)?,
+            points: points.to_vec(),
+            values: points.iter().map(|&point| poly.evaluate(point)).collect(),
+        });
+    }
+
+    let all_points = union_of_points(&openings);
+    let z_t = Polynomial::vanishing(&all_points);
+    let mut fs = batch_transcript(&openings);
+    let gamma: E::Fr = lift(fs.challenge());
+
+    // f(x) = sum gamma^i Z_{T\S_i}(x) (f_i(x) - r_i(x)) vanishes on all of T
+    let mut f = Polynomial { coeffs: vec![] };
+    let mut gamma_i = E::Fr::ONE;
+    for (&(poly, _), opening) in queries.iter().zip(&openings) {
+        let z_rest = Polynomial::vanishing(&other_points(&all_points, &opening.points));
+        let term = z_rest.mul(&poly.sub(&interpolant(opening)));
+        f = f.add(&term.mul_by_monomial(gamma_i, 0));
+        gamma_i = gamma_i.mul(gamma);
+    }
+    let h = match f.degree() {
+        Some(degree) if degree >= all_points.len() => {
+            let (quotient, remainder) = f.long_div(&z_t);
+            debug_assert_eq!(remainder.degree(), None);
+            quotient
+        }
+        _ => Polynomial { coeffs: vec![] },
+    };
+    let w = commit(srs, &h)?;
+
+    fs.absorb(&w);
+    let z = evaluation_point(&mut fs, &all_points).ok_or(BatchOpenError::NoEvaluationPoint)?;
+
+    let mut l = h.mul_by_monomial(z_t.evaluate(z).neg(), 0);
+    let mut gamma_i = E::Fr::ONE;
+    for (&(poly, _), opening) in queries.iter().zip(&openings) {
+        let z_rest = Polynomial::vanishing(&other_points(&all_points, &opening.points));
+        let shifted = poly.sub(&Polynomial {
+            coeffs: vec![interpolant(opening).evaluate(z)],
+        });
+        l = l.add(&shifted.mul_by_monomial(gamma_i.mul(z_rest.evaluate(z)), 0));
+        gamma_i = gamma_i.mul(gamma);
+    }
+    let w_prime = commit(srs, &witness_poly(&l, z))?;
+
+    Ok((openings, BatchProof { w, w_prime }))
+}
+
+pub fn batch_verify<E: Engine>(
+    srs: &SRS<E>,
+    openings: &[BatchOpening<E>],
+    proof: &BatchProof<E>,
+) -> bool {
+    if openings.iter().any(|opening| {
+        opening.points.len() != opening.values.len() || !has_distinct_points(&opening.points)
+    }) {
+        return false;
+    }
+    // every point on the curve and in the subgroup of order |Fr|
+    let commitments = openings.iter().map(|opening| opening.commitment);
+    if !commitments
+        .chain([proof.w, proof.w_prime])
+        .all(Group::is_valid)
+    {
+        return false;
+    }
+
+    let all_points = union_of_points(openings);
+    let mut fs = batch_transcript(openings);
+    let gamma: E::Fr = lift(fs.challenge());
+    fs.absorb(&proof.w);
+    let Some(z) = evaluation_point(&mut fs, &all_points) else {
+        return false;
+    };
+
+    // [L] = sum gamma^i Z_{T\S_i}(z) ([f_i] - r_i(z) G1) - Z_T(z) W, where the G1 terms
+    // collapse into a single scalar
+    let mut points = vec![proof.w];
+    let mut scalars = vec![Polynomial::vanishing(&all_points).evaluate(z).neg()];
+    let mut g1_scalar = E::Fr::ZERO;
+    let mut gamma_i = E::Fr::ONE;
+    for opening in openings {
+        let z_rest = Polynomial::vanishing(&other_points(&all_points, &opening.points));
+        let scalar = gamma_i.mul(z_rest.evaluate(z));
+        points.push(opening.commitment);
+        scalars.push(scalar);
+        g1_scalar = g1_scalar.sub(scalar.mul(interpolant(opening).evaluate(z)));
+        gamma_i = gamma_i.mul(gamma);
+    }
+    points.push(E::G1::generator());
+    scalars.push(g1_scalar);
+    let l = msm(&points, &scalars);
+
+    // e([L] + z W', [1]_2) e(-W', [tau]_2) = 1
+    let g2 = srs.g2_points[0];
+    let tau_g2 = srs.g2_points[1];
+    E::pairing_product_is_one(&[
+        (l.add(proof.w_prime.mul(z)), g2),
+        (proof.w_prime.neg(), tau_g2),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        field::{Point, F101, F17, G1},
+        srs::get_srs,
+    };
+
+    use super::*;
+
+    #[test]
+    fn kzg_test() {
+        let srs = get_srs();
+        let poly = Polynomial {
+            coeffs: vec![F17(3), F17(0), F17(5), F17(1), F17(16)],
+        };
+        let commitment = commit(&srs, &poly).unwrap();
+        for z in 0..F17::P {
+            let z = F17(z);
+            let (value, witness) = open(&srs, &poly, z).unwrap();
+            assert_eq!(value, poly.evaluate(z));
+            assert!(verify(&srs, commitment, z, value, witness));
+            assert!(!verify(&srs, commitment, z, value.add(F17::ONE), witness));
+        }
+
+        // constant polynomials open with the trivial witness
+        let constant = Polynomial {
+            coeffs: vec![F17(9)],
+        };
+        let (value, witness) = open(&srs, &constant, F17(4)).unwrap();
+        assert_eq!((value, witness), (F17(9), Point::Infinity));
+        assert!(verify(
+            &srs,
+            commit(&srs, &constant).unwrap(),
+            F17(4),
+            value,
+            witness
+        ));
+    }
+
+    #[test]
+    fn kzg_degree_test() {
+        let srs = get_srs();
+        let poly = Polynomial {
+            coeffs: vec![F17::ONE; 9],
+        };
+        assert_eq!(
+            open(&srs, &poly, F17(2)),
+            Err(CommitError::DegreeTooLarge {
+                degree: 7,
+                max_degree: 6
+            })
+        );
+    }
+
+    #[test]
+    fn batch_open_test() {
+        let srs = get_srs();
+        let a = Polynomial {
+            coeffs: vec![F17(3), F17(0), F17(5), F17(1), F17(16)],
+        };
+        let b = Polynomial {
+            coeffs: vec![F17(7), F17(2), F17(11)],
+        };
+        let z = Polynomial {
+            coeffs: vec![F17(1), F17(9), F17(0), F17(4), F17(8), F17(2)],
+        };
+        // a and b at zeta, z at zeta and zeta * omega, like round 5
+        let zeta = F17(5);
+        let zeta_omega = zeta.mul(F17::H[1]);
+        let queries: [(&Polynomial, &[F17]); 3] =
+            [(&a, &[zeta]), (&b, &[zeta]), (&z, &[zeta, zeta_omega])];
+
+        let (openings, proof) = batch_open(&srs, &queries).unwrap();
+        assert_eq!(
+            openings[2].values,
+            vec![z.evaluate(zeta), z.evaluate(zeta_omega)]
+        );
+        assert!(batch_verify(&srs, &openings, &proof));
+
+        for i in 0..openings.len() {
+            let mut tampered = openings.clone();
+            tampered[i].values[0] = tampered[i].values[0].add(F17::ONE);
+            assert!(!batch_verify(&srs, &tampered, &proof));
+        }
+        let mut tampered = openings.clone();
+        tampered[0].commitment = tampered[0].commitment.add(G1);
+        assert!(!batch_verify(&srs, &tampered, &proof));
+        let mut tampered = openings.clone();
+        tampered[1].points.push(zeta);
+        tampered[1].values.push(b.evaluate(zeta));
+        assert!(!batch_verify(&srs, &tampered, &proof));
+        assert!(!batch_verify(
+            &srs,
+            &openings,
+            &BatchProof {
+                w: proof.w_prime,
+                w_prime: proof.w
+            }
+        ));
+
+        // polynomials of degree below their number of points, and disjoint point sets
+        let constant = Polynomial {
+            coeffs: vec![F17(9)],
+        };
+        let points = [F17(0), F17(1), F17(2)];
+        let queries: [(&Polynomial, &[F17]); 2] = [(&constant, &points), (&b, &[F17(3), F17(4)])];
+        let (openings, proof) = batch_open(&srs, &queries).unwrap();
+        assert!(batch_verify(&srs, &openings, &proof));
+    }
+
+    #[test]
+    fn batch_open_degree_test() {
+        let srs = get_srs();
+        let poly = Polynomial {
+            coeffs: vec![F17::ONE; 8],
+        };
+        assert_eq!(
+            batch_open(&srs, &[(&poly, &[F17(2)])]),
+            Err(BatchOpenError::Commit(CommitError::DegreeTooLarge {
+                degree: 7,
+                max_degree: 6
+            }))
+        );
+    }
+
+    #[test]
+    fn batch_open_rejects_bad_queries_test() {
+        let srs = get_srs();
+        let poly = Polynomial {
+            coeffs: vec![F17(7), F17(2), F17(11)],
+        };
+        assert_eq!(
+            batch_open(&srs, &[(&poly, &[F17(1)]), (&poly, &[F17(2), F17(2)])]),
+            Err(BatchOpenError::RepeatedPoint { query: 1 })
+        );
+
+        // with T all of F17 there is no z outside T, for the prover nor the verifier
+        let all: Vec<F17> = (0..F17::P).map(F17).collect();
+        assert_eq!(
+            batch_open(&srs, &[(&poly, &all)]),
+            Err(BatchOpenError::NoEvaluationPoint)
+        );
+        let (mut openings, proof) = batch_open(&srs, &[(&poly, &all[..16])]).unwrap();
+        assert!(batch_verify(&srs, &openings, &proof));
+        openings[0].points.push(all[16]);
+        openings[0].values.push(poly.evaluate(all[16]));
+        assert!(!batch_verify(&srs, &openings, &proof));
+    }
+
+    #[test]
+    fn batch_verify_rejects_small_order_points_test() {
+        let srs = get_srs();
+        let poly = Polynomial {
+            coeffs: vec![F17(3), F17(0), F17(5)],
+        };
+        let (openings, proof) = batch_open(&srs, &[(&poly, &[F17(2), F17(9)])]).unwrap();
+        assert!(batch_verify(&srs, &openings, &proof));
+        // (48, 0) satisfies y^2 = x^3 + 3 but has order 2
+        let small_order = Point::Point {
+            x: F101(48),
+            y: F101(0),
+        };
+        assert!(small_order.is_on_curve());
+
+        let mut tampered = openings.clone();
+        tampered[0].commitment = openings[0].commitment.add(small_order);
+        assert!(!batch_verify(&srs, &tampered, &proof));
+        for tampered in [
+            BatchProof {
+                w: proof.w.add(small_order),
+                ..proof
+            },
+            BatchProof {
+                w_prime: proof.w_prime.add(small_order),
+                ..proof
+            },
+        ] {
+            assert!(!batch_verify(&srs, &openings, &tampered));
+        }
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn batch_open_bn254_test() {
+        use crate::bn254::{curve::G1Point, engine::Bn254, field::Fr};
+
+        let srs = SRS::<Bn254>::new(6, Fr::from_u64(0x1234_5678_9abc_def0));
+        let a = Polynomial {
+            coeffs: [3, 0, 5, 1, 16].map(Fr::from_u64).to_vec(),
+        };
+        let b = Polynomial {
+            coeffs: [7, 2, 11].map(Fr::from_u64).to_vec(),
+        };
+        let points = [Fr::from_u64(5), Fr::from_u64(1000)];
+        let queries: [(&Polynomial<Fr>, &[Fr]); 2] = [(&a, &points), (&b, &points[..1])];
+        let (openings, proof) = batch_open(&srs, &queries).unwrap();
+        assert!(batch_verify(&srs, &openings, &proof));
+
+        let mut tampered = openings.clone();
+        tampered[1].values[0] = tampered[1].values[0].add(Fr::ONE);
+        assert!(!batch_verify(&srs, &tampered, &proof));
+        let tampered = BatchProof {
+            w: proof.w.add(G1Point::generator()),
+            ..proof
+        };
+        assert!(!batch_verify(&srs, &openings, &tampered));
+    }
+}
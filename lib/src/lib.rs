@@ -1,5 +1,25 @@
+pub mod big_field;
+#[cfg(feature = "bn254")]
+pub mod bn254;
+pub mod ceremony;
+pub mod constraint_system;
+pub mod curve;
+pub mod curve_analysis;
+pub mod custom_gate;
+pub mod engine;
+pub mod fiat_shamir;
 pub mod field;
 pub mod field_extension;
+pub mod fixed_base;
+pub mod gadgets;
+pub mod jacobian;
+pub mod kzg;
+pub mod lookup;
+pub mod mimc;
+pub mod msm;
+pub mod ntt;
+pub mod pairing;
+pub mod plonk_circuit;
 pub mod polynomial;
 pub mod prescribed_permutation;
 pub mod pythagorean_circuit;
@@ -9,8 +29,12 @@ pub mod round2;
 pub mod round3;
 pub mod round4;
 pub mod round5;
+#[cfg(feature = "serde")]
+pub mod serde_support;
+pub mod serialize;
 pub mod srs;
 pub mod verifier;
+pub mod witness;
 
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
@@ -0,0 +1,190 @@
+// Plookup over a single table column, in the cyclic form used by plonkup.
+//
+// Rows with q_K = 1 claim that their a wire value is in the table t. The prover
+// commits to the lookup values f (a on lookup rows, t_0 elsewhere) and to s, the
+// multiset union of f and t sorted by the order of t, split into alternating
+// halves h1 = s_0, s_2, ... and h2 = s_1, s_3, .... Writing s_{2n} = s_0 and
+// t_n = t_0, the neighbour pairs of s are exactly the pairs (t_i, t_{i+1}) plus
+// one (f_i, f_i) pair for each lookup, which the second grand product checks:
+//
+//   z2(1) = 1
+//   z2(omega^(i+1)) = z2(omega^i) * (1 + delta)(epsilon + f_i)
+//                                 * (epsilon(1 + delta) + t_i + delta t_(i+1))
+//                     / ((epsilon(1 + delta) + h1_i + delta h2_i)
+//                        (epsilon(1 + delta) + h2_i + delta h1_(i+1)))
+//
+// and q_K (a - f) = 0 ties f to the a wire.
+
+use std::fmt;
+
+use crate::field::{batch_inverse, ScalarField, ZeroInverseError, F17};
+
+// generate random b10..b18 in F17 for f, h1, h2 and z2
+// Prover roll the dice and generate the random number
+pub const B_RANDS: [F17; 9] = [
+    F17(5),
+    F17(9),
+    F17(3),
+    F17(14),
+    F17(8),
+    F17(1),
+    F17(10),
+    F17(6),
+    F17(13),
+];
+
+// Challenges
+// Verifier roll the dice and generate the random number
+pub const DELTA: F17 = F17(7);
+pub const EPSILON: F17 = F17(9);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LookupError<F = F17> {
+    pub row: usize,
+    pub value: F,
+}
+
+impl<F: fmt::Debug> fmt::Display for LookupError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lookup value {:?} at row {} is not in the table",
+            self.value, self.row
+        )
+    }
+}
+
+impl<F: fmt::Debug> std::error::Error for LookupError<F> {}
+
+// f_i = a_i on lookup rows, t_0 on the others so that every f_i is in the table
+pub fn lookup_values<F: ScalarField>(a: &[F], q_k: &[F], table: &[F]) -> Vec<F> {
+    a.iter()
+        .zip(q_k)
+        .map(|(&a_i, &q_k_i)| if q_k_i == F::ZERO { table[0] } else { a_i })
+        .collect()
+}
+
+// (h1, h2), the alternating halves of f and t merged in table order. Each f_i
+// goes right after the first table entry equal to it.
+pub fn sort_by_table<F: ScalarField>(
+    f: &[F],
+    table: &[F],
+) -> Result<(Vec<F>, Vec<F>), LookupError<F>> {
+    let mut copies = vec![0usize; table.len()];
+    for (row, &value) in f.iter().enumerate() {
+        let position = table
+            .iter()
+            .position(|&t| t == value)
+            .ok_or(LookupError { row, value })?;
+        copies[position] += 1;
+    }
+
+    let mut sorted = Vec::with_capacity(f.len() + table.len());
+    for (&t, &count) in table.iter().zip(&copies) {
+        sorted.push(t);
+        sorted.extend(std::iter::repeat_n(t, count));
+    }
+    let h1 = sorted.iter().step_by(2).copied().collect();
+    let h2 = sorted.iter().skip(1).step_by(2).copied().collect();
+    Ok((h1, h2))
+}
+
+// z2(omega^i) for i = 0..n, see the module comment. All n denominators are
+// inverted together, and a zero one is returned as an error.
+pub fn compute_lookup_product<F: ScalarField>(
+    f: &[F],
+    h1: &[F],
+    h2: &[F],
+    table: &[F],
+    delta: F,
+    epsilon: F,
+) -> Result<Vec<F>, ZeroInverseError> {
+    let n = table.len();
+    assert!(
+        [f, h1, h2].iter().all(|column| column.len() == n),
+        "lookup columns must have one value per table row"
+    );
+    let one_delta = F::ONE.add(delta);
+    let epsilon_one_delta = epsilon.mul(one_delta);
+
+    let mut numerators = Vec::with_capacity(n);
+    let mut denominators = Vec::with_capacity(n);
+    for i in 0..n {
+        let next = (i + 1) % n;
+        numerators.push(
+            one_delta
+                .mul(epsilon.add(f[i]))
+                .mul(epsilon_one_delta.add(table[i]).add(delta.mul(table[next]))),
+        );
+        denominators.push(
+            epsilon_one_delta
+                .add(h1[i])
+                .add(delta.mul(h2[i]))
+                .mul(epsilon_one_delta.add(h2[i]).add(delta.mul(h1[next]))),
+        );
+    }
+    batch_inverse(&mut denominators)?;
+
+    let mut accs = Vec::with_capacity(n);
+    let mut acc = F::ONE;
+    for (numerator, denominator_inv) in numerators.into_iter().zip(denominators) {
+        accs.push(acc);
+        acc = acc.mul(numerator).mul(denominator_inv);
+    }
+    assert_eq!(
+        acc,
+        F::ONE,
+        "lookup product does not wrap back to 1, some value is not in the table"
+    );
+    Ok(accs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: [F17; 4] = [F17(0), F17(1), F17(2), F17(3)];
+
+    #[test]
+    fn sort_by_table_test() {
+        let f = [F17(2), F17(0), F17(2), F17(3)];
+        let (h1, h2) = sort_by_table(&f, &TABLE).unwrap();
+        // s = 0 0 1 2 2 2 3 3
+        assert_eq!(h1, vec![F17(0), F17(1), F17(2), F17(3)]);
+        assert_eq!(h2, vec![F17(0), F17(2), F17(2), F17(3)]);
+
+        assert_eq!(
+            sort_by_table(&[F17(1), F17(5)], &TABLE),
+            Err(LookupError {
+                row: 1,
+                value: F17(5)
+            })
+        );
+    }
+
+    #[test]
+    fn compute_lookup_product_test() {
+        let a = [F17(3), F17(9), F17(1), F17(1)];
+        let q_k = [F17::ONE, F17::ZERO, F17::ONE, F17::ONE];
+        let f = lookup_values(&a, &q_k, &TABLE);
+        assert_eq!(f, vec![F17(3), F17(0), F17(1), F17(1)]);
+        let (h1, h2) = sort_by_table(&f, &TABLE).unwrap();
+        let accs = compute_lookup_product(&f, &h1, &h2, &TABLE, DELTA, EPSILON);
+        assert_eq!(accs, Ok(vec![F17(1), F17(7), F17(7), F17(8)]));
+
+        // with epsilon = 0, row 0 has the factor h1_0 + delta h2_0 = 0 + 0
+        assert_eq!(
+            compute_lookup_product(&f, &h1, &h2, &TABLE, DELTA, F17::ZERO),
+            Err(ZeroInverseError { index: 0 })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "some value is not in the table")]
+    fn compute_lookup_product_missing_value_test() {
+        // 9 is not in the table, so no sorted h1, h2 exist; reuse those of 1
+        let (h1, h2) = sort_by_table(&[F17(1); 4], &TABLE).unwrap();
+        let f = [F17(1), F17(1), F17(9), F17(1)];
+        let _ = compute_lookup_product(&f, &h1, &h2, &TABLE, DELTA, EPSILON);
+    }
+}
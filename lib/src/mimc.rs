@@ -0,0 +1,280 @@
+// MiMC over the scalar field, natively and as a gadget on the constraint system.
+//
+// The keyed permutation runs r rounds of
+//
+//   x <- (x + k + c_i)^d
+//
+// and adds k once more at the end. x^d permutes F_p when gcd(d, p - 1) = 1, and
+// r = ceil(log_d p) rounds make the degree of the whole map reach p. The first
+// round constant is 0, the others are Fiat-Shamir challenges of a label.
+//
+// The hash chains the permutation in Miyaguchi-Preneel mode, with the previous
+// digest as the key: h_0 = 0, h_i = E_(h_(i-1))(m_i) + m_i + h_(i-1).
+//
+// With p = 17 this is x^3 over three rounds: fine for exercising circuits, but
+// every preimage is one of 17 values.
+//
+// The permutation takes 10 rows and a two-block hash 27, while a circuit has
+// n = 4 rows, so a MiMC preimage cannot be proved yet. One round with the final
+// key addition is 4 rows and is the largest piece that proves.
+
+use crate::{
+    constraint_system::ConstraintSystem,
+    curve_analysis::{gcd, is_prime},
+    fiat_shamir::FiatShamir,
+    field::F17,
+    witness::Variable,
+};
+
+// The smallest d >= 3 with x^d a non-linear permutation of F_p: gcd(d, p - 1) = 1,
+// and d != 1 mod p - 1 so that x^d is not the identity
+pub fn mimc_exponent(p: u32) -> u32 {
+    assert!(
+        is_prime(p) && p >= 5,
+        "MiMC needs a prime p >= 5, got {}",
+        p
+    );
+    (3..)
+        .find(|&d| gcd(d, p - 1) == 1 && d % (p - 1) != 1)
+        .unwrap()
+}
+
+// ceil(log_d p), the smallest r with d^r >= p
+pub fn mimc_rounds(p: u32, d: u32) -> usize {
+    let mut rounds = 0;
+    let mut power = 1u64;
+    while power < u64::from(p) {
+        power *= u64::from(d);
+        rounds += 1;
+    }
+    rounds
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MimcParams {
+    pub exponent: u32,
+    pub round_constants: Vec<F17>,
+}
+
+impl MimcParams {
+    // Parameters for F17, with the round constants derived from `label`
+    pub fn generate(label: &[u8]) -> Self {
+        let exponent = mimc_exponent(F17::P);
+        let rounds = mimc_rounds(F17::P, exponent);
+        let mut fs = FiatShamir::new(label);
+        let round_constants = std::iter::once(F17::ZERO)
+            .chain((1..rounds).map(|_| fs.challenge()))
+            .collect();
+        MimcParams {
+            exponent,
+            round_constants,
+        }
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_constants.len()
+    }
+}
+
+// E_k(x)
+pub fn mimc_permutation(params: &MimcParams, x: F17, key: F17) -> F17 {
+    params
+        .round_constants
+        .iter()
+        .fold(x, |x, &c| x.add(key).add(c).pow(params.exponent))
+        .add(key)
+}
+
+pub fn mimc_hash(params: &MimcParams, inputs: &[F17]) -> F17 {
+    inputs.iter().fold(F17::ZERO, |h, &m| {
+        mimc_permutation(params, m, h).add(m).add(h)
+    })
+}
+
+// x^d by square and multiply, one gate per multiplication
+fn pow_gadget(cs: &mut ConstraintSystem, x: Variable, exponent: u32) -> Variable {
+    let mut result = None;
+    for i in (0..u32::BITS - exponent.leading_zeros()).rev() {
+        result = result.map(|r| cs.mul(r, r));
+        if exponent >> i & 1 == 1 {
+            result = Some(match result {
+                Some(r) => cs.mul(r, x),
+                None => x,
+            });
+        }
+    }
+    result.expect("exponent is at least 1")
+}
+
+// E_k(x) as rows: per round one gate for x + k + c_i and the multiplications of
+// x^d, then one gate for the final + k
+pub fn mimc_gadget(
+    cs: &mut ConstraintSystem,
+    params: &MimcParams,
+    x: Variable,
+    key: Variable,
+) -> Variable {
+    let mut x = x;
+    for &c in &params.round_constants {
+        let shifted = cs.add_with_constant(x, key, c);
+        x = pow_gadget(cs, shifted, params.exponent);
+    }
+    cs.add(x, key)
+}
+
+// The hash of the inputs, with h_0 fixed to 0 by a constant row
+pub fn mimc_hash_gadget(
+    cs: &mut ConstraintSystem,
+    params: &MimcParams,
+    inputs: &[Variable],
+) -> Variable {
+    let zero = cs.constant(F17::ZERO);
+    inputs.iter().fold(zero, |h, &m| {
+        let encrypted = mimc_gadget(cs, params, m, h);
+        let with_message = cs.add(encrypted, m);
+        cs.add(with_message, h)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        constraint_system::{TooManyRows, UnsatisfiedRow},
+        plonk_circuit::ProvingKey,
+        round5::gen_round5_result,
+        verifier::{gen_verifying_key, verifier_process},
+    };
+
+    use super::*;
+
+    #[test]
+    fn params_test() {
+        // p - 1 = 16, 100, 6, 22
+        assert_eq!(mimc_exponent(17), 3);
+        assert_eq!(mimc_exponent(101), 3);
+        assert_eq!(mimc_exponent(7), 5);
+        assert_eq!(mimc_exponent(23), 3);
+        // 3 divides 12
+        assert_eq!(mimc_exponent(13), 5);
+        assert_eq!(mimc_exponent(5), 3);
+
+        assert_eq!(mimc_rounds(17, 3), 3);
+        assert_eq!(mimc_rounds(101, 3), 5);
+        assert_eq!(mimc_rounds(7, 5), 2);
+
+        let params = MimcParams::generate(b"mimc");
+        assert_eq!(params.exponent, 3);
+        assert_eq!(params.rounds(), 3);
+        assert_eq!(params.round_constants[0], F17::ZERO);
+        assert_eq!(params, MimcParams::generate(b"mimc"));
+    }
+
+    #[test]
+    #[should_panic(expected = "MiMC needs a prime p >= 5")]
+    fn params_reject_composite_test() {
+        mimc_exponent(15);
+    }
+
+    #[test]
+    fn mimc_permutation_test() {
+        let params = MimcParams::generate(b"mimc");
+        // a permutation of F17 for every key
+        for key in 0..17 {
+            let mut images = (0..17)
+                .map(|x| mimc_permutation(&params, F17(x), F17(key)).0)
+                .collect::<Vec<_>>();
+            images.sort();
+            assert_eq!(images, (0..17).collect::<Vec<_>>());
+        }
+        assert_ne!(
+            mimc_hash(&params, &[F17(1), F17(2)]),
+            mimc_hash(&params, &[F17(2), F17(1)])
+        );
+    }
+
+    #[test]
+    fn mimc_gadget_matches_native_test() {
+        let params = MimcParams::generate(b"mimc");
+        for x in 0..17 {
+            for key in [0, 5, 16] {
+                let mut cs = ConstraintSystem::new();
+                let x_var = cs.alloc(F17(x));
+                let key_var = cs.alloc(F17(key));
+                let out = mimc_gadget(&mut cs, &params, x_var, key_var);
+                // three rounds of one addition and two multiplications, then + k
+                assert_eq!(cs.num_gates(), 3 * 3 + 1);
+                assert_eq!(cs.check(), Ok(()));
+                assert_eq!(cs.value(out), mimc_permutation(&params, F17(x), F17(key)));
+            }
+        }
+
+        // x^5 and x^6 go through the square and multiply path
+        for exponent in [5, 6] {
+            let mut cs = ConstraintSystem::new();
+            let x = cs.alloc(F17(3));
+            let out = pow_gadget(&mut cs, x, exponent);
+            assert_eq!(cs.value(out), F17(3).pow(exponent));
+            assert_eq!(cs.num_gates(), 3);
+            assert_eq!(cs.check(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn mimc_preimage_test() {
+        // knowledge of (m_1, m_2) with hash equal to a public digest
+        let params = MimcParams::generate(b"mimc");
+        let preimage = [F17(3), F17(11)];
+        let digest = mimc_hash(&params, &preimage);
+
+        let mut cs = ConstraintSystem::new();
+        let inputs = preimage.map(|m| cs.alloc(m));
+        let out = mimc_hash_gadget(&mut cs, &params, &inputs);
+        let expected = cs.constant(digest);
+        cs.assert_equal(out, expected);
+        assert_eq!(cs.value(out), digest);
+        // the zero row, two blocks of ten rows and two additions, the digest rows
+        assert_eq!(cs.num_gates(), 1 + 2 * 12 + 2);
+        assert_eq!(cs.check(), Ok(()));
+
+        // a different first block breaks the first row that reads it
+        cs.assign(inputs[0], F17(4));
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 1 }));
+
+        let mut cs = ConstraintSystem::new();
+        let inputs = [F17(4), F17(11)].map(|m| cs.alloc(m));
+        let out = mimc_hash_gadget(&mut cs, &params, &inputs);
+        let expected = cs.constant(digest);
+        cs.assert_equal(out, expected);
+        assert_ne!(cs.value(out), digest);
+        assert_eq!(cs.check(), Err(UnsatisfiedRow { row: 26 }));
+    }
+
+    #[test]
+    fn mimc_prove_test() {
+        // the preimage circuit does not fit the 4 rows of a circuit
+        let params = MimcParams::generate(b"mimc");
+        let mut cs = ConstraintSystem::new();
+        let inputs = [F17(3), F17(11)].map(|m| cs.alloc(m));
+        let out = mimc_hash_gadget(&mut cs, &params, &inputs);
+        let expected = cs.constant(mimc_hash(&params, &[F17(3), F17(11)]));
+        cs.assert_equal(out, expected);
+        assert_eq!(cs.to_circuit().unwrap_err(), TooManyRows { rows: 27 });
+
+        // one round, (x + k)^3 + k, proves for every x
+        let one_round = MimcParams {
+            exponent: params.exponent,
+            round_constants: params.round_constants[..1].to_vec(),
+        };
+        for x in 0..17 {
+            let mut cs = ConstraintSystem::new();
+            let x_var = cs.alloc(F17(x));
+            let key = cs.alloc(F17(5));
+            let out = mimc_gadget(&mut cs, &one_round, x_var, key);
+            assert_eq!(cs.value(out), mimc_permutation(&one_round, F17(x), F17(5)));
+            let (circuit, witness) = cs.to_circuit().unwrap();
+            let vk = gen_verifying_key(&circuit);
+            let proof = gen_round5_result(&ProvingKey::new(circuit), &witness).unwrap();
+            assert!(verifier_process(&vk, &proof), "x = {}", x);
+        }
+    }
+}
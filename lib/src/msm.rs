@@ -0,0 +1,224 @@
+// Multi-scalar multiplication: sum of k_i * P_i.
+//
+// msm uses Pippenger's bucket method. Scalars are cut into c-bit windows; in each
+// window every point is added once into the bucket of its digit, and the buckets
+// are combined with a running sum, so a window costs about n + 2^c additions
+// instead of n scalar multiplications. All the sums stay in Jacobian coordinates
+// and are converted back once at the end.
+//
+// Both functions are generic over the group and the scalars: every CurveParams
+// curve through JacobianPoint, and the BN254 curves with the `bn254` feature.
+
+use crate::{
+    big_field::{BigPrimeField, BigPrimeParams},
+    curve::{AffinePoint, CurveParams},
+    field::Field,
+    jacobian::JacobianPoint,
+};
+
+// What msm needs from a group: affine points in and out, with the sums kept in
+// projective coordinates in between
+pub trait MsmPoint: Copy {
+    type Projective: Copy;
+
+    const IDENTITY: Self::Projective;
+
+    fn add_affine(acc: Self::Projective, point: Self) -> Self::Projective;
+    fn add(a: Self::Projective, b: Self::Projective) -> Self::Projective;
+    fn double(a: Self::Projective) -> Self::Projective;
+    fn to_affine(a: Self::Projective) -> Self;
+}
+
+impl<C: CurveParams> MsmPoint for AffinePoint<C> {
+    type Projective = JacobianPoint<C>;
+
+    const IDENTITY: JacobianPoint<C> = JacobianPoint::INFINITY;
+
+    fn add_affine(acc: JacobianPoint<C>, point: Self) -> JacobianPoint<C> {
+        acc.add_affine(point)
+    }
+
+    fn add(a: JacobianPoint<C>, b: JacobianPoint<C>) -> JacobianPoint<C> {
+        a.add(b)
+    }
+
+    fn double(a: JacobianPoint<C>) -> JacobianPoint<C> {
+        a.double()
+    }
+
+    fn to_affine(a: JacobianPoint<C>) -> Self {
+        a.to_affine()
+    }
+}
+
+// Scalars as plain integers, cut into windows by msm
+pub trait MsmScalar: Copy {
+    // Bits of the largest scalar, 5 for F17
+    const BITS: u32;
+
+    // The canonical value in little-endian limbs
+    fn to_limbs(self) -> Vec<u64>;
+}
+
+impl<F: Field> MsmScalar for F {
+    const BITS: u32 = u32::BITS - (F::MODULUS - 1).leading_zeros();
+
+    fn to_limbs(self) -> Vec<u64> {
+        vec![u64::from(self.value())]
+    }
+}
+
+impl<P: BigPrimeParams<N>, const N: usize> MsmScalar for BigPrimeField<P, N> {
+    const BITS: u32 = 64 * N as u32 - P::MODULUS[N - 1].leading_zeros();
+
+    fn to_limbs(self) -> Vec<u64> {
+        self.to_raw().to_vec()
+    }
+}
+
+// The `width` bits of `limbs` starting at bit `offset`, width at most 32
+pub(crate) fn window_digit(limbs: &[u64], offset: u32, width: u32) -> u32 {
+    (offset..offset + width).rev().fold(0, |digit, bit| {
+        let limb = limbs.get((bit / 64) as usize).copied().unwrap_or(0);
+        (digit << 1) | ((limb >> (bit % 64)) & 1) as u32
+    })
+}
+
+// Reference implementation, one double-and-add per term
+pub fn msm_naive<P: MsmPoint, S: MsmScalar>(points: &[P], scalars: &[S]) -> P {
+    assert_eq!(
+        points.len(),
+        scalars.len(),
+        "msm needs one scalar per point"
+    );
+    let mut result = P::IDENTITY;
+    for (&point, &scalar) in points.iter().zip(scalars) {
+        let limbs = scalar.to_limbs();
+        let mut term = P::IDENTITY;
+        for bit in (0..S::BITS).rev() {
+            term = P::double(term);
+            if window_digit(&limbs, bit, 1) == 1 {
+                term = P::add_affine(term, point);
+            }
+        }
+        result = P::add(result, term);
+    }
+    P::to_affine(result)
+}
+
+// Roughly log2(n), the usual choice balancing n additions against 2^c buckets
+fn window_size(n: usize, scalar_bits: u32) -> u32 {
+    let log_n = usize::BITS - n.leading_zeros();
+    log_n.saturating_sub(2).clamp(1, scalar_bits.min(16))
+}
+
+pub fn msm<P: MsmPoint, S: MsmScalar>(points: &[P], scalars: &[S]) -> P {
+    assert_eq!(
+        points.len(),
+        scalars.len(),
+        "msm needs one scalar per point"
+    );
+    let c = window_size(points.len(), S::BITS);
+    let limbs: Vec<Vec<u64>> = scalars.iter().map(|scalar| scalar.to_limbs()).collect();
+
+    let mut result = P::IDENTITY;
+    for window in (0..S::BITS.div_ceil(c)).rev() {
+        for _ in 0..c {
+            result = P::double(result);
+        }
+
+        // bucket j holds the sum of the points whose digit in this window is j + 1
+        let mut buckets = vec![P::IDENTITY; (1 << c) - 1];
+        for (&point, scalar) in points.iter().zip(&limbs) {
+            let digit = window_digit(scalar, window * c, c);
+            if digit != 0 {
+                let bucket = &mut buckets[digit as usize - 1];
+                *bucket = P::add_affine(*bucket, point);
+            }
+        }
+
+        // sum of j * bucket_j, from the top: running = bucket_top + ... + bucket_j
+        let mut running = P::IDENTITY;
+        let mut window_sum = P::IDENTITY;
+        for &bucket in buckets.iter().rev() {
+            running = P::add(running, bucket);
+            window_sum = P::add(window_sum, running);
+        }
+        result = P::add(result, window_sum);
+    }
+    P::to_affine(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        curve::scalar_mult,
+        field::{Point, F101, F17, G1},
+    };
+
+    use super::*;
+
+    #[test]
+    fn msm_test() {
+        // every point of the order 17 subgroup, with scalars covering all of F17
+        let points: Vec<Point> = (0..F17::P).map(|i| G1.mul(F17(i))).collect();
+        for n in 0..=points.len() {
+            let scalars: Vec<F17> = (0..n as u32).map(|i| F17::new(i * 7 + 3)).collect();
+            assert_eq!(
+                msm(&points[..n], &scalars),
+                msm_naive(&points[..n], &scalars),
+                "n = {}",
+                n
+            );
+        }
+
+        // 16 * G1 + G1 + 0 * G1 = O
+        assert_eq!(
+            msm(&[G1, G1, G1], &[F17(16), F17(1), F17(0)]),
+            Point::Infinity
+        );
+        assert_eq!(msm::<Point, F17>(&[], &[]), Point::Infinity);
+    }
+
+    #[test]
+    fn msm_large_test() {
+        let points: Vec<Point> = (0..500u32).map(|i| G1.mul(F17::new(i * 5 + 1))).collect();
+        let scalars: Vec<F17> = (0..500u32).map(|i| F17::new(i * i + 11)).collect();
+        assert_eq!(msm(&points, &scalars), msm_naive(&points, &scalars));
+    }
+
+    #[test]
+    fn msm_other_curve_test() {
+        use crate::curve::{AffinePoint, F17Curve};
+
+        // the F17 curve has order 19, scalars only need to be integers
+        let g = AffinePoint::<F17Curve>::generator();
+        let points: Vec<_> = (1..40u32).map(|i| scalar_mult(i, g)).collect();
+        let scalars: Vec<F101> = (0..39u32).map(|i| F101::new(i * 13 + 2)).collect();
+        assert_eq!(msm(&points, &scalars), msm_naive(&points, &scalars));
+        let expected = (0..39u32).fold(AffinePoint::Infinity, |acc, i| {
+            acc.add(scalar_mult((i + 1) * F101::new(i * 13 + 2).value(), g))
+        });
+        assert_eq!(msm(&points, &scalars), expected);
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn msm_bn254_test() {
+        use crate::bn254::{curve::G1Point, field::Fr};
+
+        let g = G1Point::generator();
+        let points: Vec<G1Point> = (0..20u64).map(|i| g.mul(Fr::from_u64(i * 5 + 1))).collect();
+        let scalars: Vec<Fr> = (0..20u64)
+            .map(|i| Fr::from_u64(i * i + 11).pow(&[i + 3]).neg())
+            .collect();
+        let expected = points
+            .iter()
+            .zip(&scalars)
+            .fold(G1Point::Infinity, |acc, (&point, &scalar)| {
+                acc.add(point.mul(scalar))
+            });
+        assert_eq!(msm(&points, &scalars), expected);
+        assert_eq!(msm_naive(&points, &scalars), expected);
+    }
+}
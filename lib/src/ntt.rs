@@ -0,0 +1,276 @@
+// Quotient computation on a coset of a multiplicative subgroup.
+//
+// t(x) = N(x) / Z_H(x) is found by evaluating N on a coset g H' with a radix-2
+// NTT, dividing pointwise by Z_H there and interpolating back with the inverse
+// NTT. Z_H(x) = x^n - 1 is never zero on g H' as long as g is not in H', and it
+// only takes |H'| / n distinct values, so there are just as many inversions.
+//
+// The blinded round 3 numerator has degree 21 while F17 has 16 non-zero elements,
+// so for F17 H' and the coset live in F17^2, whose multiplicative group of order
+// 288 = 2^5 * 9 has subgroups of every power-of-two size up to 32. The numerator
+// and the quotient both have F17 coefficients, only the evaluations leave F17.
+// BN254's Fr has subgroups up to 2^28 and runs the NTT in itself.
+
+use std::fmt;
+
+use crate::{
+    field::{PlonkField, ScalarField, F17},
+    field_extension::F172,
+    polynomial::Polynomial,
+};
+
+// A field with roots of unity of every power-of-two order up to MAX_DOMAIN_SIZE
+pub trait NttField: ScalarField {
+    const MAX_DOMAIN_SIZE: usize;
+    // Outside every power-of-two subgroup, so a valid coset shift for all of them
+    const GENERATOR: Self;
+
+    // A primitive size-th root of unity, None unless size is a power of two up to
+    // MAX_DOMAIN_SIZE
+    fn root_of_unity(size: usize) -> Option<Self>;
+}
+
+// 1 + 2u generates the multiplicative group of F17^2. It is outside every
+// power-of-two subgroup, which makes it a valid coset shift for all of them.
+pub const GENERATOR: F172 = F172 {
+    real: F17(1),
+    imag: F17(2),
+};
+
+// 17^2 - 1
+const GROUP_ORDER: u32 = F17::P * F17::P - 1;
+
+// The largest power-of-two subgroup of F17^2 has 2^5 elements
+pub const MAX_DOMAIN_SIZE: usize = 1 << GROUP_ORDER.trailing_zeros();
+
+impl NttField for F172 {
+    const MAX_DOMAIN_SIZE: usize = MAX_DOMAIN_SIZE;
+    const GENERATOR: F172 = GENERATOR;
+
+    fn root_of_unity(size: usize) -> Option<F172> {
+        if !size.is_power_of_two() || size > MAX_DOMAIN_SIZE {
+            return None;
+        }
+        Some(GENERATOR.pow(GROUP_ORDER / size as u32))
+    }
+}
+
+// In-place evaluation of the polynomial with coefficients `values` at
+// omega^0, omega^1, ..., iterative Cooley-Tukey. omega must have order values.len().
+pub fn ntt<F: NttField>(values: &mut [F], omega: F) {
+    let n = values.len();
+    assert!(n.is_power_of_two(), "NTT size must be a power of two");
+    if n == 1 {
+        return;
+    }
+
+    // bit-reversal permutation, so the butterflies can run in place
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let half = len / 2;
+        let step = omega.pow((n / len) as u64);
+        for chunk in values.chunks_mut(len) {
+            let mut twiddle = F::ONE;
+            for k in 0..half {
+                let even = chunk[k];
+                let odd = chunk[k + half].mul(twiddle);
+                chunk[k] = even.add(odd);
+                chunk[k + half] = even.sub(odd);
+                twiddle = twiddle.mul(step);
+            }
+        }
+        len *= 2;
+    }
+}
+
+// Inverse of ntt: an NTT with omega^-1, scaled by 1 / n
+pub fn intt<F: NttField>(values: &mut [F], omega: F) {
+    ntt(values, omega.inv().expect("root of unity is non-zero"));
+    let n_inv = F::from_u64(values.len() as u64)
+        .inv()
+        .expect("NTT size is not a multiple of the characteristic");
+    for value in values.iter_mut() {
+        *value = value.mul(n_inv);
+    }
+}
+
+// Evaluates at shift * omega^i: scaling coefficient i by shift^i first turns
+// p(shift x) into an ordinary NTT
+pub fn coset_ntt<F: NttField>(values: &mut [F], shift: F, omega: F) {
+    let mut power = F::ONE;
+    for value in values.iter_mut() {
+        *value = value.mul(power);
+        power = power.mul(shift);
+    }
+    ntt(values, omega);
+}
+
+pub fn coset_intt<F: NttField>(values: &mut [F], shift: F, omega: F) {
+    intt(values, omega);
+    let shift_inv = shift.inv().expect("coset shift is non-zero");
+    let mut power = F::ONE;
+    for value in values.iter_mut() {
+        *value = value.mul(power);
+        power = power.mul(shift_inv);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotientError {
+    // The numerator needs more evaluation points than the NTT field has roots of
+    // unity for
+    DomainTooLarge { size: usize, max: usize },
+    // Z_H does not divide the numerator, some constraint fails on H
+    NotDivisible,
+}
+
+impl fmt::Display for QuotientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotientError::DomainTooLarge { size, max } => write!(
+                f,
+                "a coset of size {} is needed, at most {} is available",
+                size, max
+            ),
+            QuotientError::NotDivisible => {
+                write!(f, "the numerator does not vanish on the domain")
+            }
+        }
+    }
+}
+
+impl std::error::Error for QuotientError {}
+
+// numerator / (x^n - 1), computed on a coset of 4n points, or of the next power
+// of two that holds every coefficient of the numerator. The quotient has
+// numerator.coeffs.len() - n coefficients, like the one long_div returns.
+pub fn quotient_on_coset<F: PlonkField>(
+    numerator: &Polynomial<F>,
+    n: usize,
+) -> Result<Polynomial<F>, QuotientError> {
+    assert!(n.is_power_of_two(), "H must be a power-of-two subgroup");
+    let quotient_len = numerator.coeffs.len().saturating_sub(n);
+    let mut size = 4 * n;
+    while size < numerator.coeffs.len() {
+        size *= 2;
+    }
+    let omega = F::Ntt::root_of_unity(size).ok_or(QuotientError::DomainTooLarge {
+        size,
+        max: F::Ntt::MAX_DOMAIN_SIZE,
+    })?;
+    let shift = F::Ntt::GENERATOR;
+
+    let mut values = vec![F::Ntt::ZERO; size];
+    for (value, &coeff) in values.iter_mut().zip(&numerator.coeffs) {
+        *value = F::Ntt::from(coeff);
+    }
+    coset_ntt(&mut values, shift, omega);
+
+    // Z_H(g omega^i) = g^n omega^(n i) - 1 repeats with period size / n
+    let period = size / n;
+    let shift_n = shift.pow(n as u64);
+    let omega_n = omega.pow(n as u64);
+    let mut zh_inverses = Vec::with_capacity(period);
+    let mut power = shift_n;
+    for _ in 0..period {
+        zh_inverses.push(
+            power
+                .sub(F::Ntt::ONE)
+                .inv()
+                .expect("the coset does not meet H"),
+        );
+        power = power.mul(omega_n);
+    }
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = value.mul(zh_inverses[i % period]);
+    }
+    coset_intt(&mut values, shift, omega);
+
+    // A low enough degree means t * Z_H and the numerator agree on more points
+    // than their degree, so the division was exact
+    let (coeffs, excess) = values.split_at(quotient_len);
+    if excess.iter().any(|&value| value != F::Ntt::ZERO) {
+        return Err(QuotientError::NotDivisible);
+    }
+    let coeffs = coeffs
+        .iter()
+        .map(|&value| F::from_ntt(value).ok_or(QuotientError::NotDivisible))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Polynomial { coeffs })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::polynomial::get_Z_H;
+
+    use super::*;
+
+    fn evaluate(coeffs: &[F172], x: F172) -> F172 {
+        coeffs
+            .iter()
+            .rev()
+            .fold(F172::ZERO, |acc, &coeff| acc.mul(x).add(coeff))
+    }
+
+    #[test]
+    fn ntt_test() {
+        assert_eq!(F172::root_of_unity(32).unwrap().pow(16), F172::new(16, 0));
+        assert_eq!(F172::root_of_unity(64), None);
+        assert_eq!(F172::root_of_unity(12), None);
+
+        let coeffs = (0..16).map(|i| F172::new(i * 3 + 1, i)).collect::<Vec<_>>();
+        let omega = F172::root_of_unity(16).unwrap();
+        let shift = GENERATOR;
+
+        let mut values = coeffs.clone();
+        coset_ntt(&mut values, shift, omega);
+        for (i, &value) in values.iter().enumerate() {
+            let x = shift.mul(omega.pow(i as u32));
+            assert_eq!(value, evaluate(&coeffs, x));
+        }
+        coset_intt(&mut values, shift, omega);
+        assert_eq!(values, coeffs);
+
+        let mut values = coeffs.clone();
+        ntt(&mut values, omega);
+        intt(&mut values, omega);
+        assert_eq!(values, coeffs);
+    }
+
+    #[test]
+    fn quotient_on_coset_test() {
+        // (3 + 5x + 7x^2) Z_H(x)
+        let quotient = Polynomial {
+            coeffs: vec![F17(3), F17(5), F17(7)],
+        };
+        let numerator = quotient.mul(&get_Z_H());
+        assert_eq!(
+            quotient_on_coset(&numerator, 4).unwrap().coeffs,
+            quotient.coeffs
+        );
+
+        let shifted = numerator.add(&Polynomial {
+            coeffs: vec![F17::ONE],
+        });
+        assert_eq!(
+            quotient_on_coset(&shifted, 4).unwrap_err(),
+            QuotientError::NotDivisible
+        );
+
+        let too_long = Polynomial {
+            coeffs: vec![F17::ONE; 33],
+        };
+        assert_eq!(
+            quotient_on_coset(&too_long, 4).unwrap_err(),
+            QuotientError::DomainTooLarge { size: 64, max: 32 }
+        );
+    }
+}
@@ -0,0 +1,142 @@
+// Reduced Tate pairing e: G1 x G2 -> F101^2, where G1 and G2 are the order 17
+// subgroups of y^2 = x^3 + 3 over F101 and F101^2 (embedding degree 2).
+//
+// e(P, Q) = f_{r,P}(Q)^((p^2 - 1) / r), with f_{r,P} built by Miller's loop.
+// Vertical lines only depend on x_Q, which lies in F101 for G2 = (36, 31u) and its
+// multiples, and (p - 1) divides the final exponent, so they are dropped.
+
+use crate::{
+    field::{Point, F101, F17},
+    field_extension::{G2Point, F1012},
+};
+
+// (p^2 - 1) / r = 10200 / 17
+const FINAL_EXPONENT: u32 = (F101::P * F101::P - 1) / F17::P;
+
+fn embed(x: F101) -> F1012 {
+    F1012 {
+        real: x,
+        imag: F101::ZERO,
+    }
+}
+
+// The line through t with slope m, evaluated at q: y_q - y_t - m * (x_q - x_t)
+fn line(t: (F101, F101), m: F101, q: (F1012, F1012)) -> F1012 {
+    let (x_t, y_t) = t;
+    let (x_q, y_q) = q;
+    y_q.sub(embed(y_t)).sub(embed(m).mul(x_q.sub(embed(x_t))))
+}
+
+// Slope of the line through t and p (tangent when t = p), None for vertical lines
+fn slope(t: (F101, F101), p: (F101, F101)) -> Option<F101> {
+    let ((x1, y1), (x2, y2)) = (t, p);
+    if x1 == x2 {
+        if y1 != y2 || y1 == F101::ZERO {
+            return None;
+        }
+        Some(
+            x1.mul(x1)
+                .mul(F101::new(3))
+                .mul(y1.mul(F101::new(2)).inv()?),
+        )
+    } else {
+        Some(y2.sub(y1).mul(x2.sub(x1).inv()?))
+    }
+}
+
+fn miller_loop(p: (F101, F101), q: (F1012, F1012)) -> F1012 {
+    let mut f = F1012::ONE;
+    let mut t = Point::Point { x: p.0, y: p.1 };
+    let r = F17::P;
+    let bits = u32::BITS - r.leading_zeros();
+    for i in (0..bits - 1).rev() {
+        if let Point::Point { x, y } = t {
+            if let Some(m) = slope((x, y), (x, y)) {
+                f = f.mul(f).mul(line((x, y), m, q));
+            } else {
+                f = f.mul(f);
+            }
+        }
+        t = t.add(t);
+
+        if (r >> i) & 1 == 1 {
+            if let Point::Point { x, y } = t {
+                if let Some(m) = slope((x, y), p) {
+                    f = f.mul(line((x, y), m, q));
+                }
+            }
+            t = t.add(Point::Point { x: p.0, y: p.1 });
+        }
+    }
+    f
+}
+
+pub fn pairing(p: Point, q: G2Point) -> F1012 {
+    multi_pairing(&[(p, q)])
+}
+
+// prod e(p_i, q_i), sharing one final exponentiation across all the Miller loops
+pub fn multi_pairing(pairs: &[(Point, G2Point)]) -> F1012 {
+    let mut f = F1012::ONE;
+    for &pair in pairs {
+        if let (Point::Point { x, y }, G2Point::Point { x: x_q, y: y_q }) = pair {
+            f = f.mul(miller_loop((x, y), (x_q, y_q)));
+        }
+    }
+    f.pow(FINAL_EXPONENT)
+}
+
+// Checks e(p1, q1) = e(p2, q2), as e(p1, q1) * e(-p2, q2) = 1
+pub fn pairing_check(p1: Point, q1: G2Point, p2: Point, q2: G2Point) -> bool {
+    multi_pairing(&[(p1, q1), (p2.point_neg(), q2)]) == F1012::ONE
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{field::G1, field_extension::G2};
+
+    use super::*;
+
+    #[test]
+    fn pairing_test() {
+        let e = pairing(G1, G2);
+        println!("e(G1, G2) = {:?}", e);
+        assert_ne!(e, F1012::ONE);
+        // e lands in the order 17 subgroup of F101^2
+        assert_eq!(e.pow(F17::P), F1012::ONE);
+    }
+
+    #[test]
+    fn bilinearity_test() {
+        let e = pairing(G1, G2);
+        for a in 1..F17::P {
+            for b in 1..F17::P {
+                let lhs = pairing(G1.mul(F17(a)), G2.mul(F17(b)));
+                assert_eq!(lhs, e.pow(a * b), "a = {}, b = {}", a, b);
+            }
+        }
+        assert_eq!(pairing(Point::Infinity, G2), F1012::ONE);
+        assert!(pairing_check(
+            G1.mul(F17(6)),
+            G2,
+            G1.mul(F17(2)),
+            G2.mul(F17(3))
+        ));
+    }
+
+    #[test]
+    fn multi_pairing_test() {
+        let pairs = [
+            (G1.mul(F17(3)), G2.mul(F17(5))),
+            (G1.mul(F17(7)), G2),
+            (Point::Infinity, G2.mul(F17(2))),
+        ];
+        let expected = pairs
+            .iter()
+            .fold(F1012::ONE, |acc, &(p, q)| acc.mul(pairing(p, q)));
+        assert_eq!(multi_pairing(&pairs), expected);
+        // 3 * 5 + 7 = 22 = 5 mod 17
+        assert_eq!(multi_pairing(&pairs), pairing(G1, G2).pow(5));
+        assert_eq!(multi_pairing(&[]), F1012::ONE);
+    }
+}
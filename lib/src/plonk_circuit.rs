@@ -0,0 +1,424 @@
+// A PLONK circuit as the prover and verifier see it: k wire columns, the declared
+// gates, the copy constraints as sigma values, and the lookup selector and table.
+// Every column is given by its values on H, so n = 4.
+//
+// The column count k is a circuit parameter. The permutation argument labels
+// column j on its own coset k_j H, so k is at most the number of cosets of H in
+// the multiplicative group, 4 over F17. The quotient t(x) is committed in pieces
+// of n + 2 coefficients, as many as its degree needs, which grows with k and with
+// the degree of the gates. Gates are limited to the degree whose numerator term
+// still fits the largest NTT domain, 5 with n = 4 over F17.
+
+use std::fmt;
+
+use crate::{
+    custom_gate::CustomGate,
+    engine::{Engine, Toy},
+    fiat_shamir::FiatShamir,
+    field::{lift, PlonkField, ScalarField, F17},
+    lookup::LookupError,
+    ntt::{NttField, MAX_DOMAIN_SIZE},
+    polynomial::Polynomial,
+    prescribed_permutation::coset_shifts,
+    srs::{commit_with_tables, get_srs, SrsTables, SRS},
+};
+
+// n, the number of rows
+pub const N: usize = F17::H.len();
+
+// One column per coset of H in F17^*
+pub const MAX_WIRES: usize = (F17::P as usize - 1) / N;
+
+// The numerator has fewer coefficients than the largest coset it can be evaluated
+// on, so t(x) has at most MAX_DOMAIN_SIZE - n
+pub const MAX_QUOTIENT_PIECES: usize = (MAX_DOMAIN_SIZE - N).div_ceil(N + 2);
+
+// The highest gate degree d with (n - 1) + d (n + 1) < MAX_DOMAIN_SIZE
+pub const MAX_GATE_DEGREE: usize = (MAX_DOMAIN_SIZE - N) / (N + 1);
+
+// MAX_GATE_DEGREE for the NTT domain of F
+pub fn max_gate_degree<F: PlonkField>() -> usize {
+    (F::Ntt::MAX_DOMAIN_SIZE - N) / (N + 1)
+}
+
+// H in F
+pub fn domain<F: PlonkField>() -> Vec<F> {
+    F::domain(N)
+}
+
+// The generator of H in F
+pub fn omega<F: PlonkField>() -> F {
+    F::root_of_unity(N).expect("every PLONK field has a subgroup of n elements")
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CircuitError {
+    // k must be at least 1 and at most the number of cosets of H
+    WireCount {
+        num_wires: usize,
+    },
+    // one sigma column per wire column
+    SigmaCount {
+        num_wires: usize,
+        sigmas: usize,
+    },
+    // every selector, sigma, q_K and table column has one value per row
+    ColumnLength {
+        len: usize,
+    },
+    // a gate reads a wire column past the last one
+    UnknownColumn {
+        gate: &'static str,
+        column: usize,
+    },
+    // a gate expression above the degree the field's NTT domain allows
+    DegreeTooLarge {
+        gate: &'static str,
+        degree: usize,
+        max_degree: usize,
+    },
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::WireCount { num_wires } => write!(
+                f,
+                "{} wire columns, expected at least 1 and no more than the cosets of H",
+                num_wires
+            ),
+            CircuitError::SigmaCount { num_wires, sigmas } => {
+                write!(f, "{} sigma columns for {} wire columns", sigmas, num_wires)
+            }
+            CircuitError::ColumnLength { len } => {
+                write!(f, "a column has {} values, expected {}", len, N)
+            }
+            CircuitError::UnknownColumn { gate, column } => {
+                write!(f, "gate {} reads wire column {}", gate, column)
+            }
+            CircuitError::DegreeTooLarge {
+                gate,
+                degree,
+                max_degree,
+            } => write!(
+                f,
+                "gate {} has degree {}, at most {} is supported",
+                gate, degree, max_degree
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CircuitError {}
+
+// Why the prover gave up on a witness
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProverError<F = F17> {
+    // a factor w + beta sigma + gamma of the permutation product is zero
+    PermutationDenominator { row: usize },
+    // a factor of the lookup grand product is zero
+    LookupDenominator { row: usize },
+    // a looked-up value of the witness is not in the table
+    LookupValue(LookupError<F>),
+}
+
+impl<F> From<LookupError<F>> for ProverError<F> {
+    fn from(err: LookupError<F>) -> Self {
+        ProverError::LookupValue(err)
+    }
+}
+
+impl<F: fmt::Debug> fmt::Display for ProverError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProverError::PermutationDenominator { row } => write!(
+                f,
+                "the permutation product has a zero denominator at row {}",
+                row
+            ),
+            ProverError::LookupDenominator { row } => {
+                write!(
+                    f,
+                    "the lookup product has a zero denominator at row {}",
+                    row
+                )
+            }
+            ProverError::LookupValue(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl<F: fmt::Debug> std::error::Error for ProverError<F> {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlonkCircuit<F = F17> {
+    // k
+    pub num_wires: usize,
+    pub gates: Vec<CustomGate<F>>,
+    // sigma_j on H, one per wire column
+    pub sigmas: Vec<Vec<F>>,
+    // lookup selector and table on H, looked-up values come from the a wire
+    pub q_k: Vec<F>,
+    pub table: Vec<F>,
+    // k_0 = 1, k_1, ..., k_(k-1)
+    pub coset_shifts: Vec<F>,
+}
+
+// p(x) with the given values on H
+pub fn interpolate_on_h<F: PlonkField>(values: &[F]) -> Polynomial<F> {
+    let points = domain()
+        .into_iter()
+        .zip(values.iter().copied())
+        .collect::<Vec<_>>();
+    Polynomial::interpolate(&points)
+}
+
+impl<F: PlonkField> PlonkCircuit<F> {
+    pub fn new(
+        num_wires: usize,
+        gates: Vec<CustomGate<F>>,
+        sigmas: Vec<Vec<F>>,
+        q_k: Vec<F>,
+        table: Vec<F>,
+    ) -> Result<Self, CircuitError> {
+        if num_wires == 0 {
+            return Err(CircuitError::WireCount { num_wires });
+        }
+        let coset_shifts = coset_shifts(num_wires).ok_or(CircuitError::WireCount { num_wires })?;
+        if sigmas.len() != num_wires {
+            return Err(CircuitError::SigmaCount {
+                num_wires,
+                sigmas: sigmas.len(),
+            });
+        }
+        let columns = gates
+            .iter()
+            .map(|gate| &gate.selector)
+            .chain(&sigmas)
+            .chain([&q_k, &table]);
+        for column in columns {
+            if column.len() != N {
+                return Err(CircuitError::ColumnLength { len: column.len() });
+            }
+        }
+        let max_degree = max_gate_degree::<F>();
+        for gate in &gates {
+            match gate.expression.max_column() {
+                Some(column) if column >= num_wires => {
+                    return Err(CircuitError::UnknownColumn {
+                        gate: gate.name,
+                        column,
+                    })
+                }
+                _ => {}
+            }
+            let degree = gate.expression.degree();
+            if degree > max_degree {
+                return Err(CircuitError::DegreeTooLarge {
+                    gate: gate.name,
+                    degree,
+                    max_degree,
+                });
+            }
+        }
+        Ok(PlonkCircuit {
+            num_wires,
+            gates,
+            sigmas,
+            q_k,
+            table,
+            coset_shifts,
+        })
+    }
+
+    // One sigma polynomial per wire column
+    pub fn sigma_polys(&self) -> Vec<Polynomial<F>> {
+        self.sigmas
+            .iter()
+            .map(|sigma| interpolate_on_h(sigma))
+            .collect()
+    }
+
+    // q_K(x) and t(x)
+    pub fn lookup_polys(&self) -> [Polynomial<F>; 2] {
+        [&self.q_k, &self.table].map(|values| interpolate_on_h(values))
+    }
+
+    // A bound on the degree of the round 3 numerator. After blinding the wires and
+    // f, h1, h2 have degree n + 1, z and z2 degree n + 2, and the fixed columns
+    // degree n - 1.
+    pub fn numerator_degree(&self) -> usize {
+        let permutation = (N + 2) + self.num_wires * (N + 1);
+        let lookup = (N + 2) + 2 * (N + 1);
+        self.gates
+            .iter()
+            .map(|gate| (N - 1) + gate.expression.degree() * (N + 1))
+            .fold(permutation.max(lookup), usize::max)
+    }
+
+    // The number of n + 2 coefficient pieces t(x) is committed in, 3 for the
+    // Pythagorean circuit
+    pub fn quotient_pieces(&self) -> usize {
+        (self.numerator_degree() + 1 - N).div_ceil(N + 2)
+    }
+}
+
+// Everything the prover needs besides the witness: the circuit, the blinding
+// scalars of the wires, two per column, and the SRS to commit with along with
+// its fixed-base tables
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProvingKey<E: Engine = Toy> {
+    pub circuit: PlonkCircuit<E::Fr>,
+    // b_1..b_2k
+    pub wire_blinding: Vec<E::Fr>,
+    srs: SRS<E>,
+    srs_tables: SrsTables<E::G1>,
+}
+
+impl ProvingKey {
+    pub fn new(circuit: PlonkCircuit) -> Self {
+        ProvingKey::with_srs(circuit, get_srs())
+    }
+}
+
+impl<E: Engine> ProvingKey<E> {
+    pub fn with_srs(circuit: PlonkCircuit<E::Fr>, srs: SRS<E>) -> Self {
+        let wire_blinding = gen_wire_blinding(circuit.num_wires, 0);
+        let srs_tables = srs.tables();
+        ProvingKey {
+            circuit,
+            wire_blinding,
+            srs,
+            srs_tables,
+        }
+    }
+
+    pub fn num_wires(&self) -> usize {
+        self.circuit.num_wires
+    }
+
+    pub fn srs(&self) -> &SRS<E> {
+        &self.srs
+    }
+
+    // [p(tau)]_1 through the SRS tables, every polynomial of the prover fits the SRS
+    pub fn commit(&self, poly: &Polynomial<E::Fr>) -> E::G1 {
+        commit_with_tables(&self.srs_tables, poly).expect("the SRS is too small for the circuit")
+    }
+}
+
+// Prover roll the dice: 2k scalars, drawn from a fixed seed so that proofs are
+// reproducible. Each attempt takes the next 2k scalars of the stream, the
+// prover moves on to a later one when beta and gamma hit a zero factor
+pub fn gen_wire_blinding<F: ScalarField>(num_wires: usize, attempt: usize) -> Vec<F> {
+    let mut fs = FiatShamir::new(b"plonk-wire-blinding");
+    (0..2 * num_wires * (attempt + 1))
+        .map(|_| fs.challenge())
+        .skip(2 * num_wires * attempt)
+        .map(lift)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        custom_gate::{Column, Expression},
+        pythagorean_circuit::gen_plonk_circuit,
+    };
+
+    use super::*;
+
+    #[test]
+    fn plonk_circuit_test() {
+        let circuit = gen_plonk_circuit();
+        assert_eq!(circuit.num_wires, 3);
+        assert_eq!(circuit.coset_shifts, vec![F17(1), F17(2), F17(3)]);
+        // z(x) times three wires: 6 + 3 * 5
+        assert_eq!(circuit.numerator_degree(), 21);
+        assert_eq!(circuit.quotient_pieces(), 3);
+
+        let pk = ProvingKey::new(circuit.clone());
+        assert_eq!(pk.num_wires(), 3);
+        assert_eq!(pk.wire_blinding.len(), 6);
+        assert_eq!(gen_wire_blinding::<F17>(4, 0).len(), 8);
+        assert_eq!(gen_wire_blinding::<F17>(4, 0)[..6], pk.wire_blinding[..]);
+        // the second attempt continues the stream of the first
+        let stream = gen_wire_blinding::<F17>(12, 0);
+        assert_eq!(gen_wire_blinding::<F17>(3, 1)[..], stream[6..12]);
+        assert_eq!(gen_wire_blinding::<F17>(3, 2)[..], stream[12..18]);
+
+        let rebuild = |num_wires, gates, sigmas| {
+            PlonkCircuit::new(
+                num_wires,
+                gates,
+                sigmas,
+                circuit.q_k.clone(),
+                circuit.table.clone(),
+            )
+        };
+        assert_eq!(
+            rebuild(0, vec![], vec![]),
+            Err(CircuitError::WireCount { num_wires: 0 })
+        );
+        assert_eq!(
+            rebuild(5, vec![], vec![vec![F17::ONE; 4]; 5]),
+            Err(CircuitError::WireCount { num_wires: 5 })
+        );
+        assert_eq!(
+            rebuild(3, circuit.gates.clone(), circuit.sigmas[..2].to_vec()),
+            Err(CircuitError::SigmaCount {
+                num_wires: 3,
+                sigmas: 2
+            })
+        );
+        let mut short = circuit.sigmas.clone();
+        short[1].pop();
+        assert_eq!(
+            rebuild(3, circuit.gates.clone(), short),
+            Err(CircuitError::ColumnLength { len: 3 })
+        );
+        // the arithmetic gates read c, which a two-column circuit does not have
+        assert_eq!(
+            rebuild(2, circuit.gates.clone(), circuit.sigmas[..2].to_vec()),
+            Err(CircuitError::UnknownColumn {
+                gate: "q_O",
+                column: 2
+            })
+        );
+        let gate = CustomGate::new("d", &[F17::ONE; 4], Expression::wire(Column(3)));
+        assert!(rebuild(4, vec![gate], vec![vec![F17::ONE; 4]; 4]).is_ok());
+
+        // x^5 fills five quotient pieces, x^6 no longer fits the NTT domain
+        assert_eq!(MAX_GATE_DEGREE, 5);
+        let power = |d| {
+            let x = Expression::wire(Column::A);
+            (1..d).fold(x.clone(), |acc, _| acc.mul(x.clone()))
+        };
+        let sigmas = circuit.sigmas.clone();
+        let x5 = rebuild(
+            3,
+            vec![CustomGate::new("x5", &[F17::ONE; 4], power(5))],
+            sigmas.clone(),
+        );
+        assert_eq!(
+            x5.as_ref().map(|circuit| circuit.numerator_degree()),
+            Ok(28)
+        );
+        assert_eq!(
+            x5.map(|circuit| circuit.quotient_pieces()),
+            Ok(MAX_QUOTIENT_PIECES)
+        );
+        assert_eq!(
+            rebuild(
+                3,
+                vec![CustomGate::new("x6", &[F17::ONE; 4], power(6))],
+                sigmas
+            ),
+            Err(CircuitError::DegreeTooLarge {
+                gate: "x6",
+                degree: 6,
+                max_degree: 5
+            })
+        );
+    }
+}
@@ -1,29 +1,35 @@
-use crate::field::F17;
+use crate::field::{ScalarField, F17};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone)]
-pub struct Polynomial {
-    pub coeffs: Vec<F17>,
+pub struct Polynomial<F = F17> {
+    pub coeffs: Vec<F>,
 }
 
-impl Polynomial {
-    pub fn add(&self, other: &Polynomial) -> Polynomial {
+impl<F: ScalarField> Polynomial<F> {
+    pub fn add(&self, other: &Polynomial<F>) -> Polynomial<F> {
         let max_len = usize::max(self.coeffs.len(), other.coeffs.len());
-        let mut result = vec![F17::ZERO; max_len];
+        let mut result = vec![F::ZERO; max_len];
 
-        for i in 0..self.coeffs.len() {
-            result[i] = result[i].add(self.coeffs[i]);
+        for (r, &coeff) in result.iter_mut().zip(&self.coeffs) {
+            *r = r.add(coeff);
         }
 
-        for i in 0..other.coeffs.len() {
-            result[i] = result[i].add(other.coeffs[i]);
+        for (r, &coeff) in result.iter_mut().zip(&other.coeffs) {
+            *r = r.add(coeff);
         }
 
         Polynomial { coeffs: result }
     }
 
+    // Index of the highest non-zero coefficient, None for the zero polynomial
+    pub fn degree(&self) -> Option<usize> {
+        self.coeffs.iter().rposition(|&coeff| coeff != F::ZERO)
+    }
+
     // ax^b
-    pub fn mul_by_monomial(&self, a: F17, b: usize) -> Polynomial {
-        let mut result = vec![F17::ZERO; self.coeffs.len() + b];
+    pub fn mul_by_monomial(&self, a: F, b: usize) -> Polynomial<F> {
+        let mut result = vec![F::ZERO; self.coeffs.len() + b];
         for i in 0..self.coeffs.len() {
             result[i + b] = self.coeffs[i].mul(a);
         }
@@ -32,8 +38,8 @@ impl Polynomial {
 
     // Evaluate the polynomial at a given point `x`
     // Horner’s method
-    pub fn evaluate(&self, x: F17) -> F17 {
-        let mut result = F17::ZERO;
+    pub fn evaluate(&self, x: F) -> F {
+        let mut result = F::ZERO;
         // From the highest degree coeff, Step-down calculation
         for i in (0..self.coeffs.len()).rev() {
             result = result.mul(x).add(self.coeffs[i]);
@@ -42,8 +48,8 @@ impl Polynomial {
     }
 
     // Multiply two polynomials
-    pub fn mul(&self, other: &Polynomial) -> Polynomial {
-        let mut result = vec![F17::ZERO; self.coeffs.len() + other.coeffs.len() - 1];
+    pub fn mul(&self, other: &Polynomial<F>) -> Polynomial<F> {
+        let mut result = vec![F::ZERO; self.coeffs.len() + other.coeffs.len() - 1];
 
         for i in 0..self.coeffs.len() {
             for j in 0..other.coeffs.len() {
@@ -54,27 +60,27 @@ impl Polynomial {
         Polynomial { coeffs: result }
     }
 
-    pub fn evaluate_at_omega_x(&self, omega: F17) -> Polynomial {
-        let mut result = vec![F17::ZERO; self.coeffs.len()];
+    pub fn evaluate_at_omega_x(&self, omega: F) -> Polynomial<F> {
+        let mut result = vec![F::ZERO; self.coeffs.len()];
 
         for (i, &coeff) in self.coeffs.iter().enumerate() {
-            let omega_i = omega.pow(i as u32);
+            let omega_i = omega.pow(i as u64);
             result[i] = coeff.mul(omega_i);
         }
 
         Polynomial { coeffs: result }
     }
 
-    pub fn sub(&self, other: &Polynomial) -> Polynomial {
+    pub fn sub(&self, other: &Polynomial<F>) -> Polynomial<F> {
         let max_len = usize::max(self.coeffs.len(), other.coeffs.len());
-        let mut result = vec![F17::ZERO; max_len];
+        let mut result = vec![F::ZERO; max_len];
 
-        for i in 0..self.coeffs.len() {
-            result[i] = result[i].add(self.coeffs[i]);
+        for (r, &coeff) in result.iter_mut().zip(&self.coeffs) {
+            *r = r.add(coeff);
         }
 
-        for i in 0..other.coeffs.len() {
-            result[i] = result[i].sub(other.coeffs[i]);
+        for (r, &coeff) in result.iter_mut().zip(&other.coeffs) {
+            *r = r.sub(coeff);
         }
 
         Polynomial { coeffs: result }
@@ -108,10 +114,10 @@ impl Polynomial {
     // }
 
     // Perform polynomial long division, returning the quotient and remainder
-    pub fn long_div(&self, divisor: &Polynomial) -> (Polynomial, Polynomial) {
+    pub fn long_div(&self, divisor: &Polynomial<F>) -> (Polynomial<F>, Polynomial<F>) {
         let mut remainder = self.coeffs.clone(); // The remainder starts as the dividend's coefficients
         let divisor_degree = divisor.coeffs.len() - 1; // The degree of the divisor (highest exponent)
-        let mut quotient = vec![F17::ZERO; self.coeffs.len() - divisor.coeffs.len() + 1]; // Initialize quotient
+        let mut quotient = vec![F::ZERO; self.coeffs.len() - divisor.coeffs.len() + 1]; // Initialize quotient
 
         // Continue while the degree of the remainder is greater than or equal to the divisor's degree
         while remainder.len() >= divisor.coeffs.len() {
@@ -134,7 +140,7 @@ impl Polynomial {
             }
 
             // Remove the highest-degree term from the remainder (it has been fully reduced)
-            while remainder.last() == Some(&F17::ZERO) {
+            while remainder.last() == Some(&F::ZERO) {
                 remainder.pop();
             }
         }
@@ -145,18 +151,79 @@ impl Polynomial {
         )
     }
 
-    pub fn split_into_three(&self) -> (Polynomial, Polynomial, Polynomial) {
+    // Z_S(x) = prod (x - s) over the given roots
+    pub fn vanishing(roots: &[F]) -> Polynomial<F> {
+        roots.iter().fold(
+            Polynomial {
+                coeffs: vec![F::ONE],
+            },
+            |acc, &root| {
+                acc.mul(&Polynomial {
+                    coeffs: vec![root.neg(), F::ONE],
+                })
+            },
+        )
+    }
+
+    // Lagrange interpolation through (x_i, y_i), the x_i must be distinct
+    pub fn interpolate(points: &[(F, F)]) -> Polynomial<F> {
+        let mut result = Polynomial { coeffs: vec![] };
+        for (i, &(x_i, y_i)) in points.iter().enumerate() {
+            let mut basis = Polynomial {
+                coeffs: vec![F::ONE],
+            };
+            let mut denominator = F::ONE;
+            for (j, &(x_j, _)) in points.iter().enumerate() {
+                if i != j {
+                    basis = basis.mul(&Polynomial {
+                        coeffs: vec![x_j.neg(), F::ONE],
+                    });
+                    denominator = denominator.mul(x_i.sub(x_j));
+                }
+            }
+            let scale = y_i.mul(
+                denominator
+                    .inv()
+                    .expect("interpolation points must be distinct"),
+            );
+            result = result.add(&basis.mul_by_monomial(scale, 0));
+        }
+        result
+    }
+
+    // `count` pieces of `len` coefficients, low to high, so that
+    // self(x) = sum piece_i(x) x^(i len). Pieces past the degree are zero.
+    pub fn split_into_pieces(&self, len: usize, count: usize) -> Vec<Polynomial<F>> {
+        assert!(
+            self.degree().is_none_or(|degree| degree < len * count),
+            "polynomial does not fit in {} pieces of {} coefficients",
+            count,
+            len
+        );
+        (0..count)
+            .map(|i| Polynomial {
+                coeffs: self
+                    .coeffs
+                    .iter()
+                    .skip(i * len)
+                    .take(len)
+                    .copied()
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn split_into_three(&self) -> (Polynomial<F>, Polynomial<F>, Polynomial<F>) {
         let total_len = self.coeffs.len();
 
         let low_len = total_len / 3;
         let mid_len = total_len / 3;
-        let high_len = total_len - low_len - mid_len;
 
         let low_coeffs = self.coeffs[0..low_len].to_vec();
 
         let mid_coeffs = self.coeffs[low_len..(low_len + mid_len)].to_vec();
 
-        let high_coeffs = self.coeffs[(low_len + mid_len)..total_len].to_vec();
+        let high_coeffs = self.coeffs[(low_len + mid_len)..].to_vec();
 
         (
             Polynomial { coeffs: low_coeffs },
@@ -169,9 +236,9 @@ impl Polynomial {
 }
 
 #[allow(non_snake_case)]
-pub fn get_Z_H() -> Polynomial {
+pub fn get_Z_H<F: ScalarField>() -> Polynomial<F> {
     Polynomial {
-        coeffs: vec![F17::NEG_ONE, F17::ZERO, F17::ZERO, F17::ZERO, F17::ONE], // -1 + x^4
+        coeffs: vec![F::ONE.neg(), F::ZERO, F::ZERO, F::ZERO, F::ONE], // -1 + x^4
     }
 }
 // pub const Z_H: Polynomial = ;
@@ -1,5 +1,6 @@
 use crate::{
-    field::{solve_coefficients, F17},
+    field::{solve_coefficients, PlonkField, F17},
+    plonk_circuit::{domain, N},
     polynomial::Polynomial,
 };
 
@@ -11,47 +12,79 @@ use crate::{
 // a1=b1, a2=b2, a3=b3, a4=c1
 // b1=a1, b2=a2, b3=a3, b4=c2
 // c1=a4, c2=b4, c3=c4, c4=c3
+// sigma_L, sigma_R, sigma_O on H: where the wire at each position is sent
 #[allow(non_snake_case)]
-pub fn gen_W_coeff() -> ([F17; 4], [F17; 4], [F17; 4]) {
+pub fn gen_sigma_values() -> Vec<[F17; 4]> {
     let sigma_L = [F17(2), F17(8), F17(15), F17(3)];
     let sigma_R = [F17(1), F17(4), F17(16), F17(12)];
     let sigma_O = [F17(13), F17(9), F17(5), F17(14)];
-    let sigma_L_points: [(F17, F17); 4] = F17::H
-        .into_iter()
-        .zip(sigma_L.into_iter())
-        .map(|(x, y)| (x, y))
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap();
-    let sigma_R_points: [(F17, F17); 4] = F17::H
-        .into_iter()
-        .zip(sigma_R.into_iter())
-        .map(|(x, y)| (x, y))
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap();
-    let sigma_O_points: [(F17, F17); 4] = F17::H
-        .into_iter()
-        .zip(sigma_O.into_iter())
-        .map(|(x, y)| (x, y))
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap();
-    let sigma_L_coeff = solve_coefficients(&sigma_L_points).unwrap();
-    let sigma_R_coeff = solve_coefficients(&sigma_R_points).unwrap();
-    let sigma_O_coeff = solve_coefficients(&sigma_O_points).unwrap();
-    (sigma_L_coeff, sigma_R_coeff, sigma_O_coeff)
+    vec![sigma_L, sigma_R, sigma_O]
+}
+
+// k_0 = 1, k_1, ..., one coset shift per wire column. Each k_j is the smallest
+// element outside the cosets picked so far, so the k_j H are disjoint. H has index
+// 4 in F17^*, which gives 1, 2, 3, 6 and None for more than four columns.
+pub fn coset_shifts<F: PlonkField>(num_wires: usize) -> Option<Vec<F>> {
+    let h = domain::<F>();
+    let mut shifts: Vec<F> = Vec::with_capacity(num_wires);
+    // 1, 2, 3, ... up to the characteristic, where they wrap to zero
+    let mut candidates = (1..).map(F::from_u64).take_while(|&x| x != F::ZERO);
+    while shifts.len() < num_wires {
+        let shift =
+            candidates.find(|&x| shifts.iter().all(|&k| h.iter().all(|&h| k.mul(h) != x)))?;
+        shifts.push(shift);
+    }
+    Some(shifts)
 }
 
-pub fn gen_sigma_polys() -> [Polynomial; 3] {
-    let (sigma1, sigma2, sigma3) = gen_W_coeff();
+// The label of a wire position, k_column omega^row
+pub fn label<F: PlonkField>(shifts: &[F], column: usize, row: usize) -> F {
+    shifts[column].mul(domain::<F>()[row])
+}
+
+// sigma_j on H from the copy constraints: every cycle of (column, row) positions
+// is rotated by one, and the positions outside the cycles keep their own label
+pub fn sigma_values<F: PlonkField>(shifts: &[F], cycles: &[&[(usize, usize)]]) -> Vec<Vec<F>> {
+    let mut sigmas: Vec<Vec<F>> = (0..shifts.len())
+        .map(|column| (0..N).map(|row| label(shifts, column, row)).collect())
+        .collect();
+    for cycle in cycles {
+        for (i, &(column, row)) in cycle.iter().enumerate() {
+            let (next_column, next_row) = cycle[(i + 1) % cycle.len()];
+            sigmas[column][row] = label(shifts, next_column, next_row);
+        }
+    }
+    sigmas
+}
+
+// One sigma polynomial per wire column, interpolated on H
+#[allow(non_snake_case)]
+pub fn gen_W_coeff() -> Vec<[F17; 4]> {
+    gen_sigma_values()
+        .into_iter()
+        .map(|sigma| {
+            let points: [(F17, F17); 4] = F17::H
+                .into_iter()
+                .zip(sigma)
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+            solve_coefficients(&points).unwrap()
+        })
+        .collect()
+}
 
-    [sigma1, sigma2, sigma3].map(|coeffs| Polynomial {
-        coeffs: coeffs.to_vec(),
-    })
+pub fn gen_sigma_polys() -> Vec<Polynomial> {
+    gen_W_coeff()
+        .into_iter()
+        .map(|coeffs| Polynomial {
+            coeffs: coeffs.to_vec(),
+        })
+        .collect()
 }
 
 #[cfg(test)]
+#[allow(non_snake_case)]
 mod tests {
     use super::*;
 
@@ -60,4 +93,44 @@ mod tests {
         let result = gen_W_coeff();
         println!("{:?}", result);
     }
+
+    #[test]
+    fn label_test() {
+        // a4 = c1 and c3 = c4
+        let shifts = coset_shifts::<F17>(3).unwrap();
+        let sigmas = gen_sigma_values();
+        assert_eq!(sigmas[0][3], label(&shifts, 2, 0));
+        assert_eq!(sigmas[2][0], label(&shifts, 0, 3));
+        assert_eq!(sigmas[2][2], label(&shifts, 2, 3));
+        assert_eq!(sigmas[2][3], label(&shifts, 2, 2));
+
+        // the same values from the copy cycles
+        let cycles: [&[(usize, usize)]; 6] = [
+            &[(0, 0), (1, 0)],
+            &[(0, 1), (1, 1)],
+            &[(0, 2), (1, 2)],
+            &[(0, 3), (2, 0)],
+            &[(1, 3), (2, 1)],
+            &[(2, 2), (2, 3)],
+        ];
+        let from_cycles = sigma_values(&shifts, &cycles);
+        for (sigma, expected) in from_cycles.iter().zip(&sigmas) {
+            assert_eq!(sigma[..], expected[..]);
+        }
+    }
+
+    #[test]
+    fn coset_shifts_test() {
+        assert_eq!(coset_shifts(4), Some(vec![F17(1), F17(2), F17(3), F17(6)]));
+        assert_eq!(coset_shifts(2), Some(vec![F17(1), F17(2)]));
+        assert_eq!(coset_shifts::<F17>(5), None);
+
+        // the cosets are disjoint and cover F17^*
+        let mut labels: Vec<u32> = (0..4)
+            .flat_map(|column| (0..4).map(move |row| (column, row)))
+            .map(|(column, row)| label(&coset_shifts::<F17>(4).unwrap(), column, row).0)
+            .collect();
+        labels.sort();
+        assert_eq!(labels, (1..F17::P).collect::<Vec<_>>());
+    }
 }
@@ -11,8 +11,12 @@
 // Gate 3: 1*a3 + 1*b3 + (-1)*c3 + 0*a3b3 + 0 = 0
 
 use crate::{
+    custom_gate::arithmetic_gates,
     field::{solve_coefficients, F17},
+    plonk_circuit::{PlonkCircuit, ProvingKey},
     polynomial::Polynomial,
+    prescribed_permutation::gen_sigma_values,
+    witness::{Circuit, GateOp},
 };
 
 // Selectors, the verifier would have |S|
@@ -23,6 +27,26 @@ pub const Q_M: [F17; 4] = [F17::ONE, F17::ONE, F17::ONE, F17::ZERO];
 pub const Q_C: [F17; 4] = [F17::ZERO, F17::ZERO, F17::ZERO, F17::ZERO];
 pub const N: u32 = 4;
 
+// Lookup selector and table. The Pythagorean gates need no lookups, so q_K is off
+// on every row and the lookup argument only checks the padding f_i = t_0.
+pub const Q_K: [F17; 4] = [F17::ZERO, F17::ZERO, F17::ZERO, F17::ZERO];
+// The two-bit values
+pub const TABLE: [F17; 4] = [F17(0), F17(1), F17(2), F17(3)];
+
+// The same four gates as a circuit over the inputs d, e, f.
+// Gate 3 writes d^2 + e^2 into the output of gate 2, which is the copy constraint c3 = c4.
+pub fn gen_circuit() -> Circuit {
+    let mut circuit = Circuit::new(3);
+    let d = circuit.input(0);
+    let e = circuit.input(1);
+    let f = circuit.input(2);
+    let d_square = circuit.mul(d, d);
+    let e_square = circuit.mul(e, e);
+    let f_square = circuit.mul(f, f);
+    circuit.gate(GateOp::Add, d_square, e_square, f_square);
+    circuit
+}
+
 pub fn gen_s_polys_point_value() -> [[(F17, F17); 4]; 5] {
     [Q_L, Q_R, Q_O, Q_M, Q_C].map(|q| {
         F17::H
@@ -53,6 +77,26 @@ pub fn gen_selector_polys() -> [Polynomial; 5] {
     })
 }
 
+// The circuit the prover and verifier work with: the wires a, b, c, the five
+// arithmetic gates, the copy constraints of gen_sigma_values and the lookup columns
+pub fn gen_plonk_circuit() -> PlonkCircuit {
+    PlonkCircuit::new(
+        3,
+        arithmetic_gates(&Q_L, &Q_R, &Q_O, &Q_M, &Q_C),
+        gen_sigma_values()
+            .iter()
+            .map(|sigma| sigma.to_vec())
+            .collect(),
+        Q_K.to_vec(),
+        TABLE.to_vec(),
+    )
+    .expect("the Pythagorean circuit is well formed")
+}
+
+pub fn gen_proving_key() -> ProvingKey {
+    ProvingKey::new(gen_plonk_circuit())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::field::solve_coefficients;
@@ -1,14 +1,34 @@
-use crate::field::{solve_coefficients, F17};
+use crate::{
+    field::{solve_coefficients, F17},
+    pythagorean_circuit::gen_circuit,
+    witness::{generate_witness, WitnessError},
+};
 
-pub fn gen_transcript() -> ([F17; 4], [F17; 4], [F17; 4]) {
-    let a: [F17; 4] = [F17::new(3), F17::new(4), F17::new(5), F17::new(9)];
-    let b: [F17; 4] = [F17::new(3), F17::new(4), F17::new(5), F17::new(16)];
-    let c: [F17; 4] = [F17::new(9), F17::new(16), F17::new(25), F17::new(25)];
-    return (a, b, c);
+// The a, b, c wire values of the four gates
+pub type Transcript = ([F17; 4], [F17; 4], [F17; 4]);
+
+// Run the pythagorean circuit on d, e, f. Fails unless d^2 + e^2 = f^2 in F17.
+pub fn gen_transcript_for(d: F17, e: F17, f: F17) -> Result<Transcript, WitnessError> {
+    let witness = generate_witness(&gen_circuit(), &[d, e, f])?;
+    Ok((
+        witness.a.try_into().unwrap(),
+        witness.b.try_into().unwrap(),
+        witness.c.try_into().unwrap(),
+    ))
+}
+
+pub fn gen_transcript() -> Transcript {
+    gen_transcript_for(F17::new(3), F17::new(4), F17::new(5)).unwrap()
 }
 
-pub fn gen_t_polys_point_value() -> [[(F17, F17); 4]; 3] {
-    let (a, b, c) = gen_transcript();
+// The wire columns in order, a, b, c
+pub fn wire_columns(transcript: Transcript) -> Vec<[F17; 4]> {
+    let (a, b, c) = transcript;
+    vec![a, b, c]
+}
+
+pub fn gen_t_polys_point_value(transcript: Transcript) -> [[(F17, F17); 4]; 3] {
+    let (a, b, c) = transcript;
     [a, b, c].map(|q| {
         F17::H
             .iter()
@@ -20,8 +40,8 @@ pub fn gen_t_polys_point_value() -> [[(F17, F17); 4]; 3] {
     })
 }
 
-pub fn gen_t_polys_coeff() -> [[F17; 4]; 3] {
-    let polys_pv = gen_t_polys_point_value();
+pub fn gen_t_polys_coeff(transcript: Transcript) -> [[F17; 4]; 3] {
+    let polys_pv = gen_t_polys_point_value(transcript);
     polys_pv
         .into_iter()
         .map(|poly_pv| solve_coefficients(&poly_pv).unwrap())
@@ -36,9 +56,32 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn gen_transcript_test() {
+        let (a, b, c) = gen_transcript();
+        assert_eq!(a, [F17(3), F17(4), F17(5), F17(9)]);
+        assert_eq!(b, [F17(3), F17(4), F17(5), F17(16)]);
+        // 25 = 8 mod 17
+        assert_eq!(c, [F17(9), F17(16), F17(8), F17(8)]);
+    }
+
+    #[test]
+    fn gen_transcript_for_test() {
+        // 5^2 + 12^2 = 13^2
+        let (a, b, c) = gen_transcript_for(F17::new(5), F17::new(12), F17::new(13)).unwrap();
+        assert_eq!(a, [F17(5), F17(12), F17(13), F17(8)]);
+        assert_eq!(b, [F17(5), F17(12), F17(13), F17(8)]);
+        assert_eq!(c, [F17(8), F17(8), F17(16), F17(16)]);
+
+        assert!(matches!(
+            gen_transcript_for(F17::new(1), F17::new(2), F17::new(3)),
+            Err(WitnessError::Unsatisfied { gate: 3, .. })
+        ));
+    }
+
     #[test]
     fn gen_t_polys_pv_test() {
-        let polys_pv = gen_t_polys_point_value();
+        let polys_pv = gen_t_polys_point_value(gen_transcript());
         for poly_pv in polys_pv {
             println!("poly in point-value form: {:?}", poly_pv);
             println!("poly in coeff form: {:?}", solve_coefficients(&poly_pv));
@@ -47,7 +90,7 @@ mod tests {
 
     #[test]
     fn gen_t_polys_coeff_test() {
-        let polys_coeff = gen_t_polys_coeff();
+        let polys_coeff = gen_t_polys_coeff(gen_transcript());
         for poly_coeff in polys_coeff {
             println!("poly in coeff form: {:?}", poly_coeff);
         }
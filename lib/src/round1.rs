@@ -1,14 +1,12 @@
 use crate::{
-    field::{Point, F17},
+    engine::Engine,
+    field::{lift, ScalarField},
+    lookup::{lookup_values, sort_by_table, LookupError, B_RANDS as LOOKUP_B_RANDS},
+    plonk_circuit::{interpolate_on_h, ProvingKey},
     polynomial::{get_Z_H, Polynomial},
-    pythagorean_transcript::gen_t_polys_coeff,
-    srs::commit_poly,
 };
 
-// generate random b1..b6 in F17
-pub const B_RANDS: [F17; 6] = [F17(7), F17(4), F17(11), F17(12), F17(16), F17(2)];
-
-pub fn compute_poly_coeff_round1(b_x: F17, b: F17, f: Polynomial) -> Polynomial {
+pub fn compute_poly_coeff_round1<F: ScalarField>(b_x: F, b: F, f: Polynomial<F>) -> Polynomial<F> {
     let zh_poly = get_Z_H();
     // compute (b1 * x + b2) * Z_H(x)
     let after_bx = zh_poly.mul_by_monomial(b_x, 1); // b_x * x * Z_H(x)
@@ -19,32 +17,61 @@ pub fn compute_poly_coeff_round1(b_x: F17, b: F17, f: Polynomial) -> Polynomial
     final_zh.add(&f)
 }
 
-pub fn gen_round1_result() -> ([Polynomial; 3], [Point; 3]) {
-    let fs = gen_t_polys_coeff();
+// One blinded polynomial and commitment per wire column, with the blinding
+// scalars b_2i+1, b_2i+2 of the proving key
+pub fn gen_round1_result<E: Engine>(
+    pk: &ProvingKey<E>,
+    wires: &[[E::Fr; 4]],
+) -> (Vec<Polynomial<E::Fr>>, Vec<E::G1>) {
+    assert_eq!(wires.len(), pk.num_wires(), "one witness column per wire");
     let mut round1_polys = Vec::new();
     let mut round1_committed_points = Vec::new();
-    for i in 0..3 {
-        let f = fs[i];
-        let f_poly = Polynomial { coeffs: f.to_vec() };
-        let poly_for_commit = compute_poly_coeff_round1(B_RANDS[2 * i], B_RANDS[2 * i + 1], f_poly);
+    for (i, wire) in wires.iter().enumerate() {
+        let f_poly = interpolate_on_h(wire);
+        let poly_for_commit =
+            compute_poly_coeff_round1(pk.wire_blinding[2 * i], pk.wire_blinding[2 * i + 1], f_poly);
         println!("{:?}", poly_for_commit);
         round1_polys.push(poly_for_commit.clone());
         // println!("Commited Point: {:?}", commit_poly(poly_for_commit));
-        round1_committed_points.push(commit_poly(poly_for_commit));
+        round1_committed_points.push(pk.commit(&poly_for_commit));
+    }
+    (round1_polys, round1_committed_points)
+}
+
+// f, h1, h2 of the lookup argument, blinded like the wires
+#[allow(clippy::type_complexity)]
+pub fn gen_round1_lookup_result<E: Engine>(
+    pk: &ProvingKey<E>,
+    wires: &[[E::Fr; 4]],
+) -> Result<([Polynomial<E::Fr>; 3], [E::G1; 3]), LookupError<E::Fr>> {
+    let circuit = &pk.circuit;
+    let f = lookup_values(&wires[0], &circuit.q_k, &circuit.table);
+    let (h1, h2) = sort_by_table(&f, &circuit.table)?;
+    let mut round1_polys = Vec::new();
+    let mut round1_committed_points = Vec::new();
+    for (i, values) in [f, h1, h2].into_iter().enumerate() {
+        let f_poly = interpolate_on_h(&values);
+        let poly_for_commit = compute_poly_coeff_round1(
+            lift(LOOKUP_B_RANDS[2 * i]),
+            lift(LOOKUP_B_RANDS[2 * i + 1]),
+            f_poly,
+        );
+        round1_polys.push(poly_for_commit.clone());
+        round1_committed_points.push(pk.commit(&poly_for_commit));
     }
-    (
+    Ok((
         round1_polys.try_into().unwrap(),
         round1_committed_points.try_into().unwrap(),
-    )
+    ))
 }
 
 #[cfg(test)]
 mod tests {
 
     use crate::{
-        field::{point_add, scalar_mult, Point, F101},
-        pythagorean_transcript::gen_t_polys_coeff,
-        srs::{commit_poly, get_srs},
+        pythagorean_circuit::gen_proving_key,
+        pythagorean_transcript::{gen_t_polys_coeff, gen_transcript, wire_columns},
+        srs::commit_poly,
     };
 
     use super::*;
@@ -52,15 +79,22 @@ mod tests {
     // we commited the entire transcipt by the following 3 committed point, a-box, b-box, c-box
     #[test]
     fn compute_poly_coeff_test() {
-        let fs = gen_t_polys_coeff();
+        let pk = gen_proving_key();
+        let fs = gen_t_polys_coeff(gen_transcript());
+        let (_, boxes) = gen_round1_result(&pk, &wire_columns(gen_transcript()));
         for i in 0..3 {
             let f = fs[i];
             let f_poly = Polynomial { coeffs: f.to_vec() };
-            let poly_for_commit =
-                compute_poly_coeff_round1(B_RANDS[2 * i], B_RANDS[2 * i + 1], f_poly);
+            let poly_for_commit = compute_poly_coeff_round1(
+                pk.wire_blinding[2 * i],
+                pk.wire_blinding[2 * i + 1],
+                f_poly,
+            );
             println!("{:?}", poly_for_commit);
 
-            println!("Commited Point: {:?}", commit_poly(poly_for_commit));
+            let committed = commit_poly(poly_for_commit);
+            println!("Commited Point: {:?}", committed);
+            assert_eq!(committed, boxes[i]);
         }
     }
 }
@@ -1,85 +1,94 @@
 use crate::{
-    field::{solve_coefficients, Point, F17},
+    engine::Engine,
+    fiat_shamir::FiatShamir,
+    field::{batch_inverse, lift, ScalarField, ZeroInverseError, F17},
+    lookup::{
+        compute_lookup_product, lookup_values, sort_by_table, B_RANDS as LOOKUP_B_RANDS, DELTA,
+        EPSILON,
+    },
+    plonk_circuit::{domain, interpolate_on_h, ProverError, ProvingKey},
     polynomial::{get_Z_H, Polynomial},
-    prescribed_permutation::gen_W_coeff,
-    pythagorean_transcript::gen_transcript,
-    srs::commit_poly,
+    round1::gen_round1_result,
 };
 
-// generate random b1..b9 in F17 (b1..b6 is from round1)
+// generate random b_2k+1..b_2k+3 in F17 for z(x), the wires take b1..b_2k
 // Prover roll the dice and generate the random number
-pub const B_RANDS: [F17; 9] = [
-    F17(7),
-    F17(4),
-    F17(11),
-    F17(12),
-    F17(16),
-    F17(2),
-    F17(14),
-    F17(11),
-    F17(7),
-];
+pub const B_RANDS: [F17; 3] = [F17(14), F17(11), F17(7)];
 
 // Challenges
-// Verifier roll the dice and generate the random number
-pub const BETA: F17 = F17(12);
-pub const GAMMA: F17 = F17(13);
-
-pub fn compute_acc(
-    acc_prev: F17,
-    i: usize,
-    a: [F17; 4],
-    b: [F17; 4],
-    c: [F17; 4],
-    s1: [F17; 4],
-    s2: [F17; 4],
-    s3: [F17; 4], // S_sigma
-    omega: [F17; 4],
-) -> F17 {
-    let ai = a[i - 1];
-    let bi = b[i - 1];
-    let ci = c[i - 1];
-
-    println!("ai,bi,ci: {:?}, {:?}, {:?}", ai, bi, ci);
-    // ω^(i-1)
-    let omega_i_neg_one = omega[i - 1];
-
-    let s1_eval = Polynomial {
-        coeffs: s1.to_vec(),
+// beta and gamma are drawn from the wire commitments, so the wires are fixed
+// before the prover learns them. The verifier derives them from the proof.
+pub fn permutation_challenges<E: Engine>(wire_boxes: &[E::G1]) -> (E::Fr, E::Fr) {
+    let mut fs = FiatShamir::new(b"plonk-permutation");
+    for wire_box in wire_boxes {
+        fs.absorb(wire_box);
     }
-    .evaluate(omega_i_neg_one);
-    let s2_eval = Polynomial {
-        coeffs: s2.to_vec(),
-    }
-    .evaluate(omega_i_neg_one);
-    let s3_eval = Polynomial {
-        coeffs: s3.to_vec(),
-    }
-    .evaluate(omega_i_neg_one);
-
-    let k1 = F17(2);
-    let k2 = F17(3);
-
-    let numerator1 = ai.add(BETA.mul(omega_i_neg_one)).add(GAMMA);
-    let numerator2 = bi.add(BETA.mul(k1).mul(omega_i_neg_one)).add(GAMMA);
-    let numerator3 = ci.add(BETA.mul(k2).mul(omega_i_neg_one)).add(GAMMA);
+    (lift(fs.challenge()), lift(fs.challenge()))
+}
 
-    let denominator1 = ai.add(BETA.mul(s1_eval)).add(GAMMA);
-    let denominator2 = bi.add(BETA.mul(s2_eval)).add(GAMMA);
-    let denominator3 = ci.add(BETA.mul(s3_eval)).add(GAMMA);
+// z(omega^i) for i = 0..n, the running product of the permutation argument:
+//   z(1) = 1
+//   z(omega^(i+1)) = z(omega^i) * prod_j (w_j,i + beta k_j omega^i + gamma)
+//                                      / (w_j,i + beta sigma_j,i + gamma)
+// where column j holds the values w_j on the domain, sigma_j are its permutation
+// labels on the domain, and k_j = 1, k1, k2, ... are the coset shifts of the columns.
+// All n denominators are inverted together, and a zero one is returned as an error.
+pub fn compute_permutation_product<F: ScalarField>(
+    witness_columns: &[&[F]],
+    sigma_columns: &[&[F]],
+    shifts: &[F],
+    domain: &[F],
+    beta: F,
+    gamma: F,
+) -> Result<Vec<F>, ZeroInverseError> {
+    assert!(
+        witness_columns.len() == sigma_columns.len() && witness_columns.len() == shifts.len(),
+        "every witness column needs a sigma column and a coset shift"
+    );
+    let n = domain.len();
+    assert!(
+        witness_columns
+            .iter()
+            .chain(sigma_columns)
+            .all(|column| column.len() == n),
+        "columns must have one value per domain element"
+    );
 
-    let numerator = numerator1.mul(numerator2).mul(numerator3);
-    let denominator = denominator1.mul(denominator2).mul(denominator3);
+    let mut numerators = vec![F::ONE; n];
+    let mut denominators = vec![F::ONE; n];
+    for ((column, sigma), &shift) in witness_columns.iter().zip(sigma_columns).zip(shifts) {
+        for i in 0..n {
+            let identity = beta.mul(shift).mul(domain[i]);
+            numerators[i] = numerators[i].mul(column[i].add(identity).add(gamma));
+            denominators[i] = denominators[i].mul(column[i].add(beta.mul(sigma[i])).add(gamma));
+        }
+    }
+    batch_inverse(&mut denominators)?;
 
-    // acc_i = acc_(i-1) * (numerator / denominator)
-    acc_prev.mul(numerator.mul(denominator.inv().unwrap()))
+    let mut accs = Vec::with_capacity(n);
+    let mut acc: F = initial_acc();
+    for (numerator, denominator_inv) in numerators.into_iter().zip(denominators) {
+        accs.push(acc);
+        acc = acc.mul(numerator).mul(denominator_inv);
+    }
+    assert_eq!(
+        acc,
+        F::ONE,
+        "permutation product does not wrap back to 1, the copy constraints do not hold"
+    );
+    Ok(accs)
 }
 
-pub fn initial_acc() -> F17 {
-    F17(1)
+pub fn initial_acc<F: ScalarField>() -> F {
+    F::ONE
 }
 
-pub fn compute_poly_coeff_round2(b_xx: F17, b_x: F17, b: F17, f: Polynomial) -> Polynomial {
+pub fn compute_poly_coeff_round2<F: ScalarField>(
+    b_xx: F,
+    b_x: F,
+    b: F,
+    f: Polynomial<F>,
+) -> Polynomial<F> {
     let zh_poly = get_Z_H();
     // compute (b1 * x + b2) * Z_H(x)
     let after_bxx = zh_poly.mul_by_monomial(b_xx, 2); // b_xx * x^2 * Z_H(x)
@@ -91,74 +100,119 @@ pub fn compute_poly_coeff_round2(b_xx: F17, b_x: F17, b: F17, f: Polynomial) ->
     final_zh.add(&f)
 }
 
-pub fn gen_round2_result() -> (Polynomial, Point) {
-    let acc0 = initial_acc();
-    let (a, b, c) = gen_transcript();
-    let (s1, s2, s3) = gen_W_coeff();
-    let acc1 = compute_acc(acc0, 1, a, b, c, s1, s2, s3, F17::H);
-    let acc2 = compute_acc(acc1, 2, a, b, c, s1, s2, s3, F17::H);
-    let acc3 = compute_acc(acc2, 3, a, b, c, s1, s2, s3, F17::H);
+#[allow(clippy::type_complexity)]
+pub fn gen_round2_result<E: Engine>(
+    pk: &ProvingKey<E>,
+    wires: &[[E::Fr; 4]],
+) -> Result<(Polynomial<E::Fr>, E::G1), ProverError<E::Fr>> {
+    let circuit = &pk.circuit;
+    let (_, wire_boxes) = gen_round1_result(pk, wires);
+    let (beta, gamma) = permutation_challenges::<E>(&wire_boxes);
+    let accs = compute_permutation_product(
+        &wires.iter().map(|w| &w[..]).collect::<Vec<_>>(),
+        &circuit.sigmas.iter().map(|s| &s[..]).collect::<Vec<_>>(),
+        &circuit.coset_shifts,
+        &domain(),
+        beta,
+        gamma,
+    )
+    .map_err(|err| ProverError::PermutationDenominator { row: err.index })?;
 
-    let acc_points = F17::H
-        .into_iter()
-        .zip([acc0, acc1, acc2, acc3].into_iter())
-        .map(|(x, y)| (x, y))
-        .collect::<Vec<_>>();
-
-    let acc_coeff = solve_coefficients(&acc_points.try_into().unwrap());
-    println!("acc coeff: {:?}", acc_coeff.unwrap());
+    let acc_poly = interpolate_on_h(&accs);
+    println!("acc coeff: {:?}", acc_poly.coeffs);
 
     let round2_poly = compute_poly_coeff_round2(
-        B_RANDS[6],
-        B_RANDS[7],
-        B_RANDS[8],
-        Polynomial {
-            coeffs: acc_coeff.unwrap().to_vec(),
-        },
+        lift(B_RANDS[0]),
+        lift(B_RANDS[1]),
+        lift(B_RANDS[2]),
+        acc_poly,
     );
 
     println!("round2 poly: {:?}", round2_poly);
 
-    let commited_point = commit_poly(round2_poly.clone());
+    let commited_point = pk.commit(&round2_poly);
     println!("round2 committed point: {:?}", commited_point);
-    (round2_poly, commited_point)
+    Ok((round2_poly, commited_point))
+}
+
+// z2(x), the lookup grand product, blinded like z(x)
+#[allow(clippy::type_complexity)]
+pub fn gen_round2_lookup_result<E: Engine>(
+    pk: &ProvingKey<E>,
+    wires: &[[E::Fr; 4]],
+) -> Result<(Polynomial<E::Fr>, E::G1), ProverError<E::Fr>> {
+    let circuit = &pk.circuit;
+    let f = lookup_values(&wires[0], &circuit.q_k, &circuit.table);
+    let (h1, h2) = sort_by_table(&f, &circuit.table)?;
+    let accs = compute_lookup_product(&f, &h1, &h2, &circuit.table, lift(DELTA), lift(EPSILON))
+        .map_err(|err| ProverError::LookupDenominator { row: err.index })?;
+
+    let z2_poly = compute_poly_coeff_round2(
+        lift(LOOKUP_B_RANDS[6]),
+        lift(LOOKUP_B_RANDS[7]),
+        lift(LOOKUP_B_RANDS[8]),
+        interpolate_on_h(&accs),
+    );
+
+    let commited_point = pk.commit(&z2_poly);
+    Ok((z2_poly, commited_point))
+}
+
+// The rounds below the prover use the blinding of the key as is, their tests
+// take the first blinding for which z(x) has no zero factor, as
+// gen_round5_result does
+#[cfg(test)]
+pub(crate) fn reblinded_for<E: Engine>(
+    mut pk: ProvingKey<E>,
+    witness: &[[E::Fr; 4]],
+) -> ProvingKey<E> {
+    let mut attempt = 0;
+    while gen_round2_result(&pk, witness).is_err() {
+        attempt += 1;
+        pk.wire_blinding = crate::plonk_circuit::gen_wire_blinding(pk.num_wires(), attempt);
+    }
+    pk
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        field::solve_coefficients, prescribed_permutation::gen_W_coeff,
-        pythagorean_transcript::gen_transcript, srs::commit_poly,
+        field::solve_coefficients,
+        prescribed_permutation::{coset_shifts, gen_sigma_values},
+        pythagorean_transcript::{gen_transcript, gen_transcript_for},
+        srs::commit_poly,
     };
 
     use super::*;
 
+    const BETA: F17 = F17(12);
+    const GAMMA: F17 = F17(13);
+
     #[test]
-    fn compute_acc_test() {
-        let acc0 = initial_acc();
-        println!("acc0: {:?}", acc0);
+    fn compute_permutation_product_test() {
         let (a, b, c) = gen_transcript();
-        let (s1, s2, s3) = gen_W_coeff();
-        let acc1 = compute_acc(acc0, 1, a, b, c, s1, s2, s3, F17::H);
-        println!("acc1: {:?}", acc1);
-        let acc2 = compute_acc(acc1, 2, a, b, c, s1, s2, s3, F17::H);
-        println!("acc2: {:?}", acc2);
-        let acc3 = compute_acc(acc2, 3, a, b, c, s1, s2, s3, F17::H);
-        println!("acc3: {:?}", acc3);
-
-        let acc_points = F17::H
-            .into_iter()
-            .zip([acc0, acc1, acc2, acc3].into_iter())
-            .map(|(x, y)| (x, y))
-            .collect::<Vec<_>>();
+        let [s1, s2, s3]: [[F17; 4]; 3] = gen_sigma_values().try_into().unwrap();
+        let shifts = coset_shifts(3).unwrap();
+        let accs = compute_permutation_product(
+            &[&a, &b, &c],
+            &[&s1, &s2, &s3],
+            &shifts,
+            &F17::H,
+            BETA,
+            GAMMA,
+        )
+        .unwrap();
+        assert_eq!(accs, vec![F17(1), F17(3), F17(9), F17(4)]);
+
+        let acc_points = F17::H.into_iter().zip(accs).collect::<Vec<_>>();
 
         let acc_coeff = solve_coefficients(&acc_points.try_into().unwrap());
         println!("acc coeff: {:?}", acc_coeff.unwrap());
 
         let round2_poly = compute_poly_coeff_round2(
-            B_RANDS[6],
-            B_RANDS[7],
-            B_RANDS[8],
+            B_RANDS[0],
+            B_RANDS[1],
+            B_RANDS[2],
             Polynomial {
                 coeffs: acc_coeff.unwrap().to_vec(),
             },
@@ -168,5 +222,86 @@ mod tests {
 
         let commited_point = commit_poly(round2_poly);
         println!("round2 committed point: {:?}", commited_point);
+
+        let (a, b, c) = gen_transcript_for(F17(5), F17(12), F17(13)).unwrap();
+        let accs = compute_permutation_product(
+            &[&a, &b, &c],
+            &[&s1, &s2, &s3],
+            &shifts,
+            &F17::H,
+            BETA,
+            GAMMA,
+        );
+        assert_eq!(accs, Ok(vec![F17(1), F17(13), F17(2), F17(5)]));
+
+        // (0, 1, 16): b_2 + beta sigma_2(omega^2) + gamma = 16 + 12 * 16 + 13 = 0
+        let (a, b, c) = gen_transcript_for(F17(0), F17(1), F17(16)).unwrap();
+        let accs = compute_permutation_product(
+            &[&a, &b, &c],
+            &[&s1, &s2, &s3],
+            &shifts,
+            &F17::H,
+            BETA,
+            GAMMA,
+        );
+        assert_eq!(accs, Err(ZeroInverseError { index: 2 }));
+    }
+
+    #[test]
+    fn compute_permutation_product_four_columns_test() {
+        use crate::prescribed_permutation::{label, sigma_values};
+
+        // a fourth column d copying a, so d_i joins the cycle of a_i
+        let (a, b, c) = gen_transcript();
+        let d = a;
+        let shifts = coset_shifts(4).unwrap();
+        let cycles: [&[(usize, usize)]; 6] = [
+            &[(0, 0), (1, 0), (3, 0)],
+            &[(0, 1), (1, 1), (3, 1)],
+            &[(0, 2), (1, 2), (3, 2)],
+            &[(0, 3), (2, 0), (3, 3)],
+            &[(1, 3), (2, 1)],
+            // c2 = c3
+            &[(2, 2), (2, 3)],
+        ];
+        let sigmas = sigma_values(&shifts, &cycles);
+
+        let sigma_columns = sigmas.iter().map(|s| &s[..]).collect::<Vec<_>>();
+        let accs = compute_permutation_product(
+            &[&a, &b, &c, &d],
+            &sigma_columns,
+            &shifts,
+            &F17::H,
+            BETA,
+            GAMMA,
+        )
+        .unwrap();
+        assert_eq!(accs.len(), 4);
+        assert_eq!(accs[0], F17::ONE);
+
+        // a keeps its sigma values, b0..b2 and c0 now point into d
+        let [s1, s2, s3]: [[F17; 4]; 3] = gen_sigma_values().try_into().unwrap();
+        assert_eq!(sigmas[0], s1);
+        assert_eq!(sigmas[1][3], s2[3]);
+        assert_eq!(sigmas[1][0], label(&shifts, 3, 0));
+        assert_eq!(sigmas[2][1..], s3[1..]);
+        assert_eq!(sigmas[2][0], label(&shifts, 3, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "copy constraints do not hold")]
+    fn compute_permutation_product_broken_copy_test() {
+        let (mut a, b, c) = gen_transcript();
+        // a4 = c1 no longer holds
+        a[3] = a[3].add(F17::ONE);
+        let [s1, s2, s3]: [[F17; 4]; 3] = gen_sigma_values().try_into().unwrap();
+        let _ = compute_permutation_product(
+            &[&a, &b, &c],
+            &[&s1, &s2, &s3],
+            &coset_shifts(3).unwrap(),
+            &F17::H,
+            BETA,
+            GAMMA,
+        );
     }
 }
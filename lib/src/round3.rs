@@ -1,11 +1,13 @@
 use crate::{
-    field::{Point, F17},
-    polynomial::{get_Z_H, Polynomial},
-    prescribed_permutation::gen_sigma_polys,
-    pythagorean_circuit::{gen_s_polys_coeff, gen_selector_polys},
-    round1::gen_round1_result,
-    round2::{gen_round2_result, BETA, GAMMA},
-    srs::commit_poly,
+    custom_gate::gate_constraint_poly,
+    engine::Engine,
+    field::{lift, PlonkField, ScalarField, F17},
+    lookup::{DELTA, EPSILON},
+    ntt::quotient_on_coset,
+    plonk_circuit::{interpolate_on_h, omega, ProverError, ProvingKey, N},
+    polynomial::Polynomial,
+    round1::{gen_round1_lookup_result, gen_round1_result},
+    round2::{gen_round2_lookup_result, gen_round2_result, permutation_challenges},
 };
 
 pub const ALPHA: F17 = F17(15);
@@ -13,133 +15,221 @@ pub const ALPHA: F17 = F17(15);
 // Langrange base, calculate by interpolating (1,0,0,0) on F17::H
 pub const L1: [F17; 4] = [F17(13), F17(13), F17(13), F17(13)];
 
-pub fn compute_poly_coeff_round3() -> (Polynomial, (Polynomial, Polynomial, Polynomial)) {
-    let [q_l, q_r, q_o, q_m, q_c] = gen_selector_polys();
-    let ([a, b, c], [a_box, b_box, c_box]) = gen_round1_result();
-    let [sigma1, sigma2, sigma3] = gen_sigma_polys();
-    let zh = get_Z_H();
-    let a_b_qM = a.mul(&b).mul(&q_m);
-    println!("a(x) * b(x) * qM(x): {:?}", a.mul(&b).mul(&q_m));
-
-    let a_qL = a.mul(&q_l);
-    println!("a(x) * qL(x): {:?}", a.mul(&q_l));
-
-    let b_qR = b.mul(&q_r);
-    println!("b(x) * qR(x): {:?}", b.mul(&q_r));
+// L1(x) in any field, every coefficient is 1 / n
+pub fn l1_poly<F: PlonkField>() -> Polynomial<F> {
+    let mut values = vec![F::ZERO; N];
+    values[0] = F::ONE;
+    interpolate_on_h(&values)
+}
 
-    let c_qO = c.mul(&q_o);
-    println!("c(x) * qO(x): {:?}", c.mul(&q_o));
+// t(x) Z_H(x), the gate, permutation and first-row constraints folded with alpha
+pub fn compute_quotient_numerator<E: Engine>(
+    pk: &ProvingKey<E>,
+    witness: &[[E::Fr; 4]],
+) -> Result<Polynomial<E::Fr>, ProverError<E::Fr>> {
+    let circuit = &pk.circuit;
+    let alpha_scalar = lift(ALPHA);
+    let (wires, wire_boxes) = gen_round1_result(pk, witness);
+    let (beta, gamma) = permutation_challenges::<E>(&wire_boxes);
+    let sigmas = circuit.sigma_polys();
+    let gates = gate_constraint_poly(&circuit.gates, &wires);
 
     let public_inputs_poly = Polynomial {
-        coeffs: vec![F17::ZERO],
+        coeffs: vec![E::Fr::ZERO],
     };
 
     let beta_x = Polynomial {
-        coeffs: [F17::ZERO, BETA].to_vec(),
+        coeffs: [E::Fr::ZERO, beta].to_vec(),
     };
 
     let gamma_constant = Polynomial {
-        coeffs: [GAMMA].to_vec(),
+        coeffs: [gamma].to_vec(),
     };
 
-    let alpha_a_beta_x_gamma = a
-        .add(&beta_x)
-        .add(&gamma_constant)
-        .mul_by_monomial(ALPHA, 0);
-
-    println!("alpha_a_beta_x_gamma: {:?}", alpha_a_beta_x_gamma);
-
-    let k1 = F17(2);
-    let k2 = F17(3);
-    let b_beta_k1_x_gamma = b.add(&beta_x.mul_by_monomial(k1, 0)).add(&gamma_constant);
-    println!("b_beta_k1_x_gamma: {:?}", b_beta_k1_x_gamma);
-
-    let c_beta_k2_x_gamma = c.add(&beta_x.mul_by_monomial(k2, 0)).add(&gamma_constant);
-    println!("c_beta_k2_x_gamma: {:?}", c_beta_k2_x_gamma);
-
-    let (z_x, z_box) = gen_round2_result();
+    let (z_x, _) = gen_round2_result(pk, witness)?;
     println!("z_x: {:?}", z_x);
 
-    let z_omega_x = z_x.evaluate_at_omega_x(F17::H[1]);
+    let z_omega_x = z_x.evaluate_at_omega_x(omega());
     println!("z_omega_x: {:?}", z_omega_x);
 
-    let alpha_a_beta_sigma1_gamma = a
-        .add(&sigma1.mul_by_monomial(BETA, 0))
-        .add(&gamma_constant)
-        .mul_by_monomial(ALPHA, 0);
-
-    println!("alpha_a_beta_sigma1_gamma: {:?}", alpha_a_beta_sigma1_gamma);
-
-    let b_beta_k1_sigma2_gamma = b.add(&sigma2.mul_by_monomial(BETA, 0)).add(&gamma_constant);
-    println!("b_beta_k1_sigma2_gamma: {:?}", b_beta_k1_sigma2_gamma);
-
-    let c_beta_k2_sigma3_gamma = c.add(&sigma3.mul_by_monomial(BETA, 0)).add(&gamma_constant);
-    println!("c_beta_k2_sigma3_gamma: {:?}", c_beta_k2_sigma3_gamma);
+    // prod_j (w_j(x) + beta k_j x + gamma) z(x) and
+    // prod_j (w_j(x) + beta sigma_j(x) + gamma) z(omega x), both times alpha
+    let alpha = Polynomial {
+        coeffs: vec![alpha_scalar],
+    };
+    let (identity_product, sigma_product) =
+        wires.iter().zip(&sigmas).zip(&circuit.coset_shifts).fold(
+            (z_x.mul(&alpha), z_omega_x.mul(&alpha)),
+            |(identity_acc, sigma_acc), ((wire, sigma), shift)| {
+                let identity = wire
+                    .add(&beta_x.mul_by_monomial(*shift, 0))
+                    .add(&gamma_constant);
+                let sigma = wire
+                    .add(&sigma.mul_by_monomial(beta, 0))
+                    .add(&gamma_constant);
+                (identity_acc.mul(&identity), sigma_acc.mul(&sigma))
+            },
+        );
 
     let z_x_neg_one = z_x.add(&Polynomial {
-        coeffs: vec![F17::NEG_ONE],
+        coeffs: vec![E::Fr::ONE.neg()],
     });
 
-    let alpha_2_z_x_negone_L1 = z_x_neg_one
-        .mul(&Polynomial {
-            coeffs: L1.to_vec(),
-        })
-        .mul_by_monomial(ALPHA.mul(ALPHA), 0);
+    let alpha_2_z_x_negone_l1 = z_x_neg_one
+        .mul(&l1_poly())
+        .mul_by_monomial(alpha_scalar.mul(alpha_scalar), 0);
 
-    println!("alpha_2_z_x_negone_L1: {:?}", alpha_2_z_x_negone_L1);
+    println!("alpha_2_z_x_negone_l1: {:?}", alpha_2_z_x_negone_l1);
 
-    let term1 = a_b_qM
-        .add(&a_qL)
-        .add(&b_qR)
-        .add(&c_qO)
-        .add(&public_inputs_poly)
-        .add(&q_c);
+    let term1 = gates.add(&public_inputs_poly);
     println!("term1: {:?}", term1);
-    let term2 = alpha_a_beta_x_gamma
-        .mul(&b_beta_k1_x_gamma)
-        .mul(&c_beta_k2_x_gamma)
-        .mul(&z_x);
+    let term2 = identity_product;
     println!("term2: {:?}", term2);
-    let term3 = alpha_a_beta_sigma1_gamma
-        .mul(&b_beta_k1_sigma2_gamma)
-        .mul(&c_beta_k2_sigma3_gamma)
-        .mul(&z_omega_x);
+    let term3 = sigma_product;
     println!("term3: {:?}", term3);
-    let term4 = alpha_2_z_x_negone_L1;
+    let term4 = alpha_2_z_x_negone_l1;
     println!("term4: {:?}", term4);
 
-    let t_zh = term1.add(&term2).sub(&term3).add(&term4);
+    let term5 = lookup_quotient_terms(pk, witness)?;
+
+    let t_zh = term1.add(&term2).sub(&term3).add(&term4).add(&term5);
     println!("t_zh: {:?}", t_zh);
+    Ok(t_zh)
+}
+
+// The lookup constraints, folded with alpha^3 to alpha^5:
+//   q_K(x) (a(x) - f(x))
+//   z2(x) (1 + delta)(epsilon + f(x))(epsilon(1 + delta) + t(x) + delta t(omega x))
+//     - z2(omega x)(epsilon(1 + delta) + h1(x) + delta h2(x))
+//                  (epsilon(1 + delta) + h2(x) + delta h1(omega x))
+//   L1(x) (z2(x) - 1)
+fn lookup_quotient_terms<E: Engine>(
+    pk: &ProvingKey<E>,
+    witness: &[[E::Fr; 4]],
+) -> Result<Polynomial<E::Fr>, ProverError<E::Fr>> {
+    let [q_k, table] = pk.circuit.lookup_polys();
+    let (wires, _) = gen_round1_result(pk, witness);
+    let a = &wires[0];
+    let ([f, h1, h2], _) = gen_round1_lookup_result(pk, witness)?;
+    let (z2_x, _) = gen_round2_lookup_result(pk, witness)?;
+    let omega = omega();
+    let (alpha, delta, epsilon_scalar) = (
+        lift::<E::Fr>(ALPHA),
+        lift::<E::Fr>(DELTA),
+        lift::<E::Fr>(EPSILON),
+    );
+
+    let one_delta = E::Fr::ONE.add(delta);
+    let epsilon_one_delta = Polynomial {
+        coeffs: vec![epsilon_scalar.mul(one_delta)],
+    };
+    let epsilon = Polynomial {
+        coeffs: vec![epsilon_scalar],
+    };
+
+    let gate = q_k.mul(&a.sub(&f)).mul_by_monomial(alpha.pow(3), 0);
+
+    let numerator = z2_x
+        .mul(&f.add(&epsilon).mul_by_monomial(one_delta, 0))
+        .mul(
+            &epsilon_one_delta
+                .add(&table)
+                .add(&table.evaluate_at_omega_x(omega).mul_by_monomial(delta, 0)),
+        );
+    let denominator = z2_x
+        .evaluate_at_omega_x(omega)
+        .mul(
+            &epsilon_one_delta
+                .add(&h1)
+                .add(&h2.mul_by_monomial(delta, 0)),
+        )
+        .mul(
+            &epsilon_one_delta
+                .add(&h2)
+                .add(&h1.evaluate_at_omega_x(omega).mul_by_monomial(delta, 0)),
+        );
+    let product = numerator.sub(&denominator).mul_by_monomial(alpha.pow(4), 0);
+
+    let first_row = z2_x
+        .add(&Polynomial {
+            coeffs: vec![E::Fr::ONE.neg()],
+        })
+        .mul(&l1_poly())
+        .mul_by_monomial(alpha.pow(5), 0);
 
-    let (t, t_remainder) = t_zh.long_div(&zh);
-    println!("t: {:?}, t_remainder: {:?}", t, t_remainder);
+    Ok(gate.add(&product).add(&first_row))
+}
 
-    (t.clone(), t.split_into_three())
+// t(x) and its pieces t_0, t_1, ... of n + 2 coefficients each
+#[allow(clippy::type_complexity)]
+pub fn compute_poly_coeff_round3<E: Engine>(
+    pk: &ProvingKey<E>,
+    witness: &[[E::Fr; 4]],
+) -> Result<(Polynomial<E::Fr>, Vec<Polynomial<E::Fr>>), ProverError<E::Fr>> {
+    let t_zh = compute_quotient_numerator(pk, witness)?;
+    let t = quotient_on_coset(&t_zh, N)
+        .expect("the constraints hold on H, so Z_H divides the numerator");
+
+    let pieces = t.split_into_pieces(N + 2, pk.circuit.quotient_pieces());
+    Ok((t, pieces))
 }
 
-pub fn gen_round3_result() -> (
-    Polynomial,
-    Polynomial,
-    Polynomial,
-    Polynomial,
-    Point,
-    Point,
-    Point,
-) {
-    let (t, (t_low, t_mid, t_high)) = compute_poly_coeff_round3();
-    let t_low_box = commit_poly(t_low.clone());
-    let t_mid_box = commit_poly(t_mid.clone());
-    let t_high_box = commit_poly(t_high.clone());
-    (t, t_low, t_mid, t_high, t_low_box, t_mid_box, t_high_box)
+#[allow(clippy::type_complexity)]
+pub fn gen_round3_result<E: Engine>(
+    pk: &ProvingKey<E>,
+    witness: &[[E::Fr; 4]],
+) -> Result<(Polynomial<E::Fr>, Vec<Polynomial<E::Fr>>, Vec<E::G1>), ProverError<E::Fr>> {
+    let (t, pieces) = compute_poly_coeff_round3(pk, witness)?;
+    let t_boxes = pieces.iter().map(|piece| pk.commit(piece)).collect();
+    Ok((t, pieces, t_boxes))
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::{
+        polynomial::get_Z_H,
+        pythagorean_circuit::gen_proving_key,
+        pythagorean_transcript::{gen_transcript, gen_transcript_for, wire_columns},
+        round2::reblinded_for,
+    };
+
     use super::*;
 
     #[test]
     fn compute_poly_coeff_round3_test() {
-        compute_poly_coeff_round3();
+        let witness = wire_columns(gen_transcript());
+        let pk = reblinded_for(gen_proving_key(), &witness);
+        let (t, pieces) = compute_poly_coeff_round3(&pk, &witness).unwrap();
+        assert_eq!(pieces.len(), pk.circuit.quotient_pieces());
+        // t = t_0 + x^(n+2) t_1 + x^(2n+4) t_2 + ...
+        let n = F17::H.len();
+        let mut coeffs: Vec<F17> = pieces
+            .iter()
+            .flat_map(|piece| {
+                assert!(piece.coeffs.len() <= n + 2);
+                let mut padded = piece.coeffs.clone();
+                padded.resize(n + 2, F17::ZERO);
+                padded
+            })
+            .collect();
+        coeffs.truncate(t.coeffs.len());
+        assert_eq!(coeffs, t.coeffs);
+    }
+
+    #[test]
+    fn quotient_matches_long_division_test() {
+        for transcript in [
+            gen_transcript(),
+            gen_transcript_for(F17(5), F17(12), F17(13)).unwrap(),
+        ] {
+            let witness = wire_columns(transcript);
+            let pk = reblinded_for(gen_proving_key(), &witness);
+            let t_zh = compute_quotient_numerator(&pk, &witness).unwrap();
+            let (expected, remainder) = t_zh.long_div(&get_Z_H());
+            assert_eq!(remainder.degree(), None);
+            let (t, _) = compute_poly_coeff_round3(&pk, &witness).unwrap();
+            assert_eq!(t.coeffs, expected.coeffs);
+        }
     }
 }
@@ -1,138 +1,181 @@
 use crate::{
-    field::F17,
-    polynomial::{get_Z_H, Polynomial},
-    prescribed_permutation::gen_sigma_polys,
-    pythagorean_circuit::gen_selector_polys,
-    round1::gen_round1_result,
-    round2::{gen_round2_result, BETA, GAMMA},
-    round3::{gen_round3_result, ALPHA, L1},
+    custom_gate::gate_scalars,
+    engine::Engine,
+    field::{lift, ScalarField, F17},
+    lookup::{DELTA, EPSILON},
+    plonk_circuit::{omega, ProverError, ProvingKey},
+    polynomial::Polynomial,
+    round1::{gen_round1_lookup_result, gen_round1_result},
+    round2::{gen_round2_lookup_result, gen_round2_result, permutation_challenges},
+    round3::{gen_round3_result, l1_poly, ALPHA},
 };
 
 pub const ZETA: F17 = F17(5);
 
-pub struct Round4Output {
-    pub a_bar: F17,
-    pub b_bar: F17,
-    pub c_bar: F17,
-    pub sigma1_bar: F17,
-    pub sigma2_bar: F17,
-    pub t_bar: F17,
-    pub z_omega_bar: F17,
-    pub r_bar: F17,
-    pub r: Polynomial,
+pub struct Round4Output<F = F17> {
+    // every wire at zeta and zeta omega, and every sigma but the last at zeta
+    pub wire_bars: Vec<F>,
+    pub wire_omega_bars: Vec<F>,
+    pub sigma_bars: Vec<F>,
+    pub t_bar: F,
+    pub z_omega_bar: F,
+    pub f_bar: F,
+    pub h1_bar: F,
+    pub h2_bar: F,
+    pub table_bar: F,
+    pub h1_omega_bar: F,
+    pub table_omega_bar: F,
+    pub z2_omega_bar: F,
+    pub r_bar: F,
+    pub r: Polynomial<F>,
 }
 
-pub fn gen_round4_result() -> Round4Output {
-    let [q_l, q_r, q_o, q_m, q_c] = gen_selector_polys();
-    let ([a, b, c], [a_box, b_box, c_box]) = gen_round1_result();
-    let [sigma1, sigma2, sigma3] = gen_sigma_polys();
-    let (t, t_low, t_mid, t_high, t_low_box, t_mid_box, t_high_box) = gen_round3_result();
-    let (z_x, z_box) = gen_round2_result();
+pub fn gen_round4_result<E: Engine>(
+    pk: &ProvingKey<E>,
+    witness: &[[E::Fr; 4]],
+) -> Result<Round4Output<E::Fr>, ProverError<E::Fr>> {
+    let circuit = &pk.circuit;
+    let [alpha, delta, epsilon, zeta] = [ALPHA, DELTA, EPSILON, ZETA].map(lift::<E::Fr>);
+    let gates = &circuit.gates;
+    let (wires, wire_boxes) = gen_round1_result(pk, witness);
+    let (beta, gamma) = permutation_challenges::<E>(&wire_boxes);
+    let mut sigmas = circuit.sigma_polys();
+    // the last sigma stays in the linearization, the others are opened
+    let sigma_last = sigmas.pop().expect("at least one wire column");
+    let [q_k, table] = circuit.lookup_polys();
+    let ([f, h1, h2], _) = gen_round1_lookup_result(pk, witness)?;
+    let (z2_x, _) = gen_round2_lookup_result(pk, witness)?;
+    let (t, ..) = gen_round3_result(pk, witness)?;
+    let (z_x, _) = gen_round2_result(pk, witness)?;
     println!("z_x: {:?}", z_x);
 
-    let z_omega_x = z_x.evaluate_at_omega_x(F17::H[1]);
+    let z_omega_x = z_x.evaluate_at_omega_x(omega());
     println!("z_omega_x: {:?}", z_omega_x);
 
-    let zh = get_Z_H();
-    let a_bar = a.evaluate(ZETA);
-    let b_bar = b.evaluate(ZETA);
-    let c_bar = c.evaluate(ZETA);
-    println!("a_bar: {:?}", a_bar);
-    println!("b_bar: {:?}", b_bar);
-    println!("c_bar: {:?}", c_bar);
-    let sigma1_bar = sigma1.evaluate(ZETA);
-    let sigma2_bar = sigma2.evaluate(ZETA);
-    // let sigma3_bar = sigma3.evaluate(ZETA);
-    println!("sigma1_bar: {:?}", sigma1_bar);
-    println!("sigma2_bar: {:?}", sigma2_bar);
-    // println!("sigma3_bar: {:?}", sigma3_bar);
-
-    let t_bar = t.evaluate(ZETA);
+    let wire_bars = wires
+        .iter()
+        .map(|wire| wire.evaluate(zeta))
+        .collect::<Vec<_>>();
+    // the wires at the next row, for gates that use omega^1
+    let zeta_omega = zeta.mul(omega());
+    let wire_omega_bars = wires
+        .iter()
+        .map(|wire| wire.evaluate(zeta_omega))
+        .collect::<Vec<_>>();
+    let sigma_bars = sigmas
+        .iter()
+        .map(|sigma| sigma.evaluate(zeta))
+        .collect::<Vec<_>>();
+
+    let t_bar = t.evaluate(zeta);
     println!("t_bar: {:?}", t_bar);
 
-    let z_omega_bar = z_omega_x.evaluate(ZETA);
+    let z_omega_bar = z_omega_x.evaluate(zeta);
     println!("z_omega_bar: {:?}", z_omega_bar);
 
-    // term 1
-    let ab_bar_q_m = q_m.mul_by_monomial(a_bar, 0).mul_by_monomial(b_bar, 0);
-    println!("ab_bar_q_m: {:?}", ab_bar_q_m);
-
-    let a_bar_q_l = q_l.mul_by_monomial(a_bar, 0);
-    let b_bar_q_r = q_r.mul_by_monomial(b_bar, 0);
-    let c_bar_q_o = q_o.mul_by_monomial(c_bar, 0);
-    println!("a_bar_q_l: {:?}", a_bar_q_l);
-    println!("b_bar_q_r: {:?}", b_bar_q_r);
-    println!("c_bar_q_o: {:?}", c_bar_q_o);
-    println!("q_c: {:?}", q_c);
-
-    let a_bar_beta_zeta_gamma = a_bar.add(BETA.mul(ZETA)).add(GAMMA);
-    let b_bar_beta_k1_zeta_gamma = b_bar.add(BETA.mul(F17::K1).mul(ZETA)).add(GAMMA);
-    let c_bar_beta_k2_zeta_gamma = c_bar.add(BETA.mul(F17::K2).mul(ZETA)).add(GAMMA);
-
-    let term2 = z_x.mul_by_monomial(
-        a_bar_beta_zeta_gamma
-            .mul(b_bar_beta_k1_zeta_gamma)
-            .mul(c_bar_beta_k2_zeta_gamma)
-            .mul(ALPHA),
-        0,
+    let f_bar = f.evaluate(zeta);
+    let h1_bar = h1.evaluate(zeta);
+    let h2_bar = h2.evaluate(zeta);
+    let table_bar = table.evaluate(zeta);
+    let h1_omega_bar = h1.evaluate(zeta_omega);
+    let table_omega_bar = table.evaluate(zeta_omega);
+    let z2_omega_bar = z2_x.evaluate(zeta_omega);
+
+    // term 1: every selector times its gate expression at the openings
+    let expressions = gates
+        .iter()
+        .map(|gate| &gate.expression)
+        .collect::<Vec<_>>();
+    let scalars = gate_scalars(&expressions, &wire_bars, &wire_omega_bars);
+    let term1 = gates.iter().zip(scalars).fold(
+        Polynomial {
+            coeffs: vec![E::Fr::ZERO],
+        },
+        |acc, (gate, scalar)| acc.add(&gate.selector_poly().mul_by_monomial(scalar, 0)),
     );
+
+    // prod_j (w_j_bar + beta k_j zeta + gamma)
+    let identity_product = wire_bars
+        .iter()
+        .zip(&circuit.coset_shifts)
+        .fold(E::Fr::ONE, |acc, (&wire_bar, &shift)| {
+            acc.mul(wire_bar.add(beta.mul(shift).mul(zeta)).add(gamma))
+        });
+    let term2 = z_x.mul_by_monomial(identity_product.mul(alpha), 0);
     println!("term2: {:?}", term2);
 
-    let a_bar_beta_sigma1_bar_gamma = a_bar.add(BETA.mul(sigma1_bar)).add(GAMMA);
-    let b_bar_beta_sigma2_bar_gamma = b_bar.add(BETA.mul(sigma2_bar)).add(GAMMA);
-    let term3 = sigma3.mul_by_monomial(
-        a_bar_beta_sigma1_bar_gamma
-            .mul(b_bar_beta_sigma2_bar_gamma)
-            .mul(BETA)
-            .mul(z_omega_bar)
-            .mul(ALPHA),
-        0,
-    );
+    // prod_j (w_j_bar + beta sigma_j_bar + gamma) over every column but the last
+    let sigma_product = wire_bars
+        .iter()
+        .zip(&sigma_bars)
+        .fold(E::Fr::ONE, |acc, (&wire_bar, &sigma_bar)| {
+            acc.mul(wire_bar.add(beta.mul(sigma_bar)).add(gamma))
+        });
+    let term3 = sigma_last.mul_by_monomial(sigma_product.mul(beta).mul(z_omega_bar).mul(alpha), 0);
     println!("term3: {:?}", term3);
 
-    let term4 = z_x.mul_by_monomial(
-        Polynomial {
-            coeffs: L1.to_vec(),
-        }
-        .evaluate(ZETA)
-        .mul(ALPHA)
-        .mul(ALPHA),
+    let term4 = z_x.mul_by_monomial(l1_poly::<E::Fr>().evaluate(zeta).mul(alpha).mul(alpha), 0);
+    println!("term4: {:?}", term4);
+
+    // the lookup terms, with the z2(omega x) part left to the verifier
+    let term5 = q_k.mul_by_monomial(wire_bars[0].sub(f_bar).mul(alpha.pow(3)), 0);
+    let one_delta = E::Fr::ONE.add(delta);
+    let epsilon_one_delta = epsilon.mul(one_delta);
+    let term6 = z2_x.mul_by_monomial(
+        one_delta
+            .mul(epsilon.add(f_bar))
+            .mul(
+                epsilon_one_delta
+                    .add(table_bar)
+                    .add(delta.mul(table_omega_bar)),
+            )
+            .mul(alpha.pow(4))
+            .add(l1_poly::<E::Fr>().evaluate(zeta).mul(alpha.pow(5))),
         0,
     );
-    println!("term4: {:?}", term4);
 
-    let r = ab_bar_q_m
-        .add(&a_bar_q_l)
-        .add(&b_bar_q_r)
-        .add(&c_bar_q_o)
-        .add(&q_c)
+    let r = term1
         .add(&term2)
-        .add(&term3)
-        .add(&term4);
+        .sub(&term3)
+        .add(&term4)
+        .add(&term5)
+        .add(&term6);
     println!("r: {:?}", r);
 
-    let r_bar = r.evaluate(ZETA);
+    let r_bar = r.evaluate(zeta);
     println!("r_bar: {:?}", r_bar);
-    Round4Output {
-        a_bar,
-        b_bar,
-        c_bar,
-        sigma1_bar,
-        sigma2_bar,
+    Ok(Round4Output {
+        wire_bars,
+        wire_omega_bars,
+        sigma_bars,
         t_bar,
         z_omega_bar,
+        f_bar,
+        h1_bar,
+        h2_bar,
+        table_bar,
+        h1_omega_bar,
+        table_omega_bar,
+        z2_omega_bar,
         r_bar,
         r,
-    }
+    })
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::{
+        pythagorean_circuit::gen_proving_key,
+        pythagorean_transcript::{gen_transcript, wire_columns},
+        round2::reblinded_for,
+    };
+
     use super::*;
 
     #[test]
     fn gen_round4_result_test() {
-        gen_round4_result();
+        let witness = wire_columns(gen_transcript());
+        gen_round4_result(&reblinded_for(gen_proving_key(), &witness), &witness).unwrap();
     }
 }
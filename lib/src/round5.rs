@@ -1,161 +1,220 @@
 use crate::{
-    field::{Point, F17},
+    engine::{Engine, Toy},
+    field::{lift, ScalarField, F17},
+    kzg,
+    plonk_circuit::{gen_wire_blinding, omega, ProverError, ProvingKey, N},
     polynomial::Polynomial,
-    prescribed_permutation::gen_sigma_polys,
-    pythagorean_circuit::gen_selector_polys,
-    round1::gen_round1_result,
-    round2::gen_round2_result,
+    round1::{gen_round1_lookup_result, gen_round1_result},
+    round2::{gen_round2_lookup_result, gen_round2_result},
     round3::gen_round3_result,
     round4::{gen_round4_result, Round4Output, ZETA},
-    srs::commit_poly,
 };
 
 pub const V: F17 = F17(12);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Plonk_Proof {
-    pub a_box: Point,
-    pub b_box: Point,
-    pub c_box: Point,
-    pub z_box: Point,
-    pub t_low_box: Point,
-    pub t_mid_box: Point,
-    pub t_high_box: Point,
-    pub w_zeta_box: Point,
-    pub w_zeta_omega_box: Point,
-    pub a_bar: F17,
-    pub b_bar: F17,
-    pub c_bar: F17,
-    pub sigma1_bar: F17,
-    pub sigma2_bar: F17,
-    pub r_bar: F17,
-    pub z_omega_bar: F17,
+// Blindings the prover tries before giving up on a zero permutation factor.
+// Each one draws fresh beta and gamma, and in F17 a draw fails with
+// probability well under a half.
+pub const MAX_BLINDING_ATTEMPTS: usize = 32;
+
+#[allow(non_camel_case_types)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(remote = "Self"),
+    serde(bound(
+        serialize = "E::Fr: serde::Serialize, E::G1: serde::Serialize",
+        deserialize = "E::Fr: serde::Deserialize<'de>, E::G1: serde::Deserialize<'de>"
+    ))
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plonk_Proof<E: Engine = Toy> {
+    // one commitment per wire column, a, b, c, ...
+    pub wire_boxes: Vec<E::G1>,
+    pub z_box: E::G1,
+    pub f_box: E::G1,
+    pub h1_box: E::G1,
+    pub h2_box: E::G1,
+    pub z2_box: E::G1,
+    // the pieces of t(x), t_lo, t_mid, t_hi, ... of n + 2 coefficients each
+    pub t_boxes: Vec<E::G1>,
+    pub w_zeta_box: E::G1,
+    pub w_zeta_omega_box: E::G1,
+    pub wire_bars: Vec<E::Fr>,
+    pub wire_omega_bars: Vec<E::Fr>,
+    // every sigma but the last, which the verifier folds into [D]
+    pub sigma_bars: Vec<E::Fr>,
+    pub r_bar: E::Fr,
+    pub z_omega_bar: E::Fr,
+    pub f_bar: E::Fr,
+    pub h1_bar: E::Fr,
+    pub h2_bar: E::Fr,
+    pub table_bar: E::Fr,
+    pub h1_omega_bar: E::Fr,
+    pub table_omega_bar: E::Fr,
+    pub z2_omega_bar: E::Fr,
+}
+
+// A zero factor w + beta sigma + gamma only depends on beta and gamma, which
+// come from the wire commitments, so the prover re-blinds the wires and draws
+// again. The lookup product uses fixed challenges and is not retried.
+pub fn gen_round5_result<E: Engine>(
+    pk: &ProvingKey<E>,
+    witness: &[[E::Fr; 4]],
+) -> Result<Plonk_Proof<E>, ProverError<E::Fr>> {
+    let mut result = prove(pk, witness);
+    for attempt in 1..MAX_BLINDING_ATTEMPTS {
+        if !matches!(result, Err(ProverError::PermutationDenominator { .. })) {
+            break;
+        }
+        let mut reblinded = pk.clone();
+        reblinded.wire_blinding = gen_wire_blinding(pk.num_wires(), attempt);
+        result = prove(&reblinded, witness);
+    }
+    result
 }
 
-pub fn gen_round5_result() -> Plonk_Proof {
-    let [q_l, q_r, q_o, q_m, q_c] = gen_selector_polys();
-    let ([a, b, c], [a_box, b_box, c_box]) = gen_round1_result();
-    let [sigma1, sigma2, sigma3] = gen_sigma_polys();
-    let (z_x, z_box) = gen_round2_result();
-    let (t, t_low, t_mid, t_high, t_low_box, t_mid_box, t_high_box) = gen_round3_result();
+fn prove<E: Engine>(
+    pk: &ProvingKey<E>,
+    witness: &[[E::Fr; 4]],
+) -> Result<Plonk_Proof<E>, ProverError<E::Fr>> {
+    let (zeta, v) = (lift::<E::Fr>(ZETA), lift::<E::Fr>(V));
+    let (wires, wire_boxes) = gen_round1_result(pk, witness);
+    let sigmas = pk.circuit.sigma_polys();
+    let (z_x, z_box) = gen_round2_result(pk, witness)?;
+    let ([f, h1, h2], [f_box, h1_box, h2_box]) = gen_round1_lookup_result(pk, witness)?;
+    let (z2_x, z2_box) = gen_round2_lookup_result(pk, witness)?;
+    let [_, table] = pk.circuit.lookup_polys();
+    let (_, t_pieces, t_boxes) = gen_round3_result(pk, witness)?;
     // let round4_output = gen_round4_result();
     let Round4Output {
-        a_bar,
-        b_bar,
-        c_bar,
-        sigma1_bar,
-        sigma2_bar,
+        wire_bars,
+        wire_omega_bars,
+        sigma_bars,
         t_bar,
         z_omega_bar,
+        f_bar,
+        h1_bar,
+        h2_bar,
+        table_bar,
+        h1_omega_bar,
+        table_omega_bar,
+        z2_omega_bar,
         r_bar,
         r,
-    } = gen_round4_result();
+    } = gen_round4_result(pk, witness)?;
     // number of gates
-    let n = 4u32;
-    println!("t_low:{:?}", t_low);
-    println!("t_mid_zeta:{:?}", t_mid.mul_by_monomial(ZETA.pow(n + 2), 0));
-    // println!("t_high:{:?}", t_high);
-    println!(
-        "t_high_zeta:{:?}",
-        t_high.mul_by_monomial(ZETA.pow(2 * n + 4), 0)
+    let n = N as u64;
+    // t_lo + zeta^(n+2) t_mid + zeta^(2n+4) t_hi + ... - t_bar
+    let term1 = t_pieces.iter().zip(0..).fold(
+        Polynomial {
+            coeffs: vec![t_bar.neg()],
+        },
+        |acc, (piece, i)| acc.add(&piece.mul_by_monomial(zeta.pow(i * (n + 2)), 0)),
     );
-    let term1 = t_low
-        .add(&t_mid.mul_by_monomial(ZETA.pow(n + 2), 0))
-        .add(&t_high.mul_by_monomial(ZETA.pow(2 * n + 4), 0))
-        .sub(&Polynomial {
-            coeffs: vec![t_bar],
-        });
     let term2 = r
         .sub(&Polynomial {
             coeffs: vec![r_bar],
         })
-        .mul_by_monomial(V, 0);
-    let term3 = a
-        .sub(&Polynomial {
-            coeffs: vec![a_bar],
-        })
-        .mul_by_monomial(V.pow(2), 0);
-    let term4 = b
-        .sub(&Polynomial {
-            coeffs: vec![b_bar],
-        })
-        .mul_by_monomial(V.pow(3), 0);
-    let term5 = c
-        .sub(&Polynomial {
-            coeffs: vec![c_bar],
-        })
-        .mul_by_monomial(V.pow(4), 0);
-    let term6 = sigma1
-        .sub(&Polynomial {
-            coeffs: vec![sigma1_bar],
-        })
-        .mul_by_monomial(V.pow(5), 0);
-    let term7 = sigma2
-        .sub(&Polynomial {
-            coeffs: vec![sigma2_bar],
-        })
-        .mul_by_monomial(V.pow(6), 0);
-    let w_zeta_x_neg_zeta = term1
-        .add(&term2)
-        .add(&term3)
-        .add(&term4)
-        .add(&term5)
-        .add(&term6)
-        .add(&term7);
+        .mul_by_monomial(v, 0);
+    // the wires at v^2.., then the opened sigmas, then the lookup columns, each
+    // minus its opening at zeta
+    let openings = wires
+        .iter()
+        .zip(wire_bars.iter().copied())
+        .chain(sigmas.iter().zip(sigma_bars.iter().copied()))
+        .chain([
+            (&f, f_bar),
+            (&h1, h1_bar),
+            (&h2, h2_bar),
+            (&table, table_bar),
+        ])
+        .zip(2..)
+        .fold(
+            Polynomial { coeffs: vec![] },
+            |acc, ((poly, bar), power)| {
+                acc.add(
+                    &poly
+                        .sub(&Polynomial { coeffs: vec![bar] })
+                        .mul_by_monomial(v.pow(power), 0),
+                )
+            },
+        );
+    let w_zeta_x_neg_zeta = term1.add(&term2).add(&openings);
     println!("w_zeta_x_neg_zeta: {:?}", w_zeta_x_neg_zeta);
-    let (w_zeta, _remainder) = w_zeta_x_neg_zeta.long_div(&Polynomial {
-        coeffs: vec![ZETA.neg(), F17::ONE],
+    // every term vanishes at zeta, so the opening at zeta is the batched witness
+    let w_zeta_box = pk.commit(&kzg::witness_poly(&w_zeta_x_neg_zeta, zeta));
+    // z, z2, h1, t and the wires are all opened at zeta omega, batched with powers of v
+    let shifted = z_x
+        .add(&z2_x.mul_by_monomial(v, 0))
+        .add(&h1.mul_by_monomial(v.pow(2), 0))
+        .add(&table.mul_by_monomial(v.pow(3), 0));
+    let shifted = wires.iter().zip(4..).fold(shifted, |acc, (wire, power)| {
+        acc.add(&wire.mul_by_monomial(v.pow(power), 0))
     });
-    println!("w_zeta: {:?}", w_zeta);
-
-    // println!("z_x: {:?}", z_x);
-    // let w_zeta_omega_nomi = z_x.sub(&Polynomial {
-    //     coeffs: vec![z_omega_bar.neg()],
-    // });
-    // println!("w_zeta_omega_nomi: {:?}", w_zeta_omega_nomi);
-    // println!("z_omega_bar_neg: {:?}", z_omega_bar.neg());
-
-    let (w_zeta_omega, _remainder) = z_x
-        .sub(&Polynomial {
-            coeffs: vec![z_omega_bar],
-        })
-        .long_div(&Polynomial {
-            coeffs: vec![ZETA.mul(F17::H[1]).neg(), F17::ONE],
-        });
-    println!("w_zeta_omega: {:?}", w_zeta_omega);
-
-    let w_zeta_box = commit_poly(w_zeta);
-    let w_zeta_omega_box = commit_poly(w_zeta_omega);
+    let w_zeta_omega_box = pk.commit(&kzg::witness_poly(&shifted, zeta.mul(omega())));
     println!("w_zeta_box: {:?}", w_zeta_box);
     println!("w_zeta_omega_box: {:?}", w_zeta_omega_box);
-    Plonk_Proof {
-        a_box,
-        b_box,
-        c_box,
+    Ok(Plonk_Proof {
+        wire_boxes,
         z_box,
-        t_low_box,
-        t_mid_box,
-        t_high_box,
+        f_box,
+        h1_box,
+        h2_box,
+        z2_box,
+        t_boxes,
         w_zeta_box,
         w_zeta_omega_box,
-        a_bar,
-        b_bar,
-        c_bar,
-        sigma1_bar,
-        sigma2_bar,
+        wire_bars,
+        wire_omega_bars,
+        sigma_bars,
         r_bar,
         z_omega_bar,
-    }
+        f_bar,
+        h1_bar,
+        h2_bar,
+        table_bar,
+        h1_omega_bar,
+        table_omega_bar,
+        z2_omega_bar,
+    })
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::{
+        pythagorean_circuit::gen_proving_key,
+        pythagorean_transcript::{gen_transcript, gen_transcript_for, wire_columns},
+    };
+
     use super::*;
 
     #[test]
     fn gen_round5_result_test() {
-        let proof = gen_round5_result();
+        let proof = gen_round5_result(&gen_proving_key(), &wire_columns(gen_transcript())).unwrap();
         println!("plonk proof: {:?}", proof);
+        assert_eq!(proof.t_boxes.len(), 3);
+    }
+
+    #[test]
+    fn gen_round5_result_reblinding_test() {
+        // a triple whose first blinding draws beta and gamma with a zero factor
+        let pk = gen_proving_key();
+        let witness = (0..17)
+            .flat_map(|d| (0..17).map(move |e| (d, e)))
+            .flat_map(|(d, e)| (0..17).map(move |f| (d, e, f)))
+            .filter_map(|(d, e, f)| gen_transcript_for(F17(d), F17(e), F17(f)).ok())
+            .map(wire_columns)
+            .find(|witness| {
+                matches!(
+                    prove(&pk, witness),
+                    Err(ProverError::PermutationDenominator { .. })
+                )
+            })
+            .unwrap();
+        // the proof commits to the wires under a later blinding
+        let proof = gen_round5_result(&pk, &witness).unwrap();
+        assert_ne!(proof.wire_boxes, gen_round1_result(&pk, &witness).1);
     }
 }
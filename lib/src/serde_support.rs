@@ -0,0 +1,354 @@
+// Validation for the serde (JSON) representation, enabled by the `serde` feature.
+//
+// Field elements are plain numbers and must be canonical (< p).
+// Points are "infinity" or {"point": {"x": .., "y": ..}} and must lie on the curve.
+// Structs keep their field names (wire_boxes, z_omega_bar, ...).
+//
+// Proofs, verifying keys and SRSs carry "version": SCHEMA_VERSION next to their
+// fields, and any other version is refused.
+//   1: the three-wire proof of the paper, a_box, b_box, c_box, t_low_box, ...,
+//      sigma1_bar, sigma2_bar, and the SRS points as f101_points.
+//   2: any number of wires and quotient pieces, so per-column fields became
+//      wire_boxes, wire_bars, wire_omega_bars, sigma_bars and t_boxes, the
+//      lookup commitments and openings were added, and the SRS points are
+//      g1_points. Version 1 documents do not carry the version and fail to
+//      load with a missing field "version".
+
+use serde::{
+    de::{DeserializeOwned, Error as _},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    curve::{AffinePoint, CurveParams},
+    engine::Engine,
+    field::{F101, F17},
+    field_extension::{G2Point, F1012},
+    round5::Plonk_Proof,
+    srs::SRS,
+    verifier::VerifyingKey,
+};
+
+pub const SCHEMA_VERSION: u32 = 2;
+
+impl TryFrom<u32> for F17 {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value < F17::P {
+            Ok(F17(value))
+        } else {
+            Err(format!("{} is not a canonical element of F17", value))
+        }
+    }
+}
+
+impl TryFrom<u32> for F101 {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value < F101::P {
+            Ok(F101(value))
+        } else {
+            Err(format!("{} is not a canonical element of F101", value))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(
+    rename_all = "snake_case",
+    bound(deserialize = "C::Base: Deserialize<'de>")
+)]
+pub enum PointRepr<C: CurveParams> {
+    Infinity,
+    Point { x: C::Base, y: C::Base },
+}
+
+impl<C: CurveParams> TryFrom<PointRepr<C>> for AffinePoint<C> {
+    type Error = String;
+
+    fn try_from(repr: PointRepr<C>) -> Result<Self, Self::Error> {
+        let point = match repr {
+            PointRepr::Infinity => AffinePoint::Infinity,
+            PointRepr::Point { x, y } => AffinePoint::Point { x, y },
+        };
+        if point.is_on_curve() {
+            Ok(point)
+        } else {
+            Err(format!("{:?} is not on the curve", point))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum G2PointRepr {
+    Infinity,
+    Point { x: F1012, y: F1012 },
+}
+
+impl TryFrom<G2PointRepr> for G2Point {
+    type Error = String;
+
+    fn try_from(repr: G2PointRepr) -> Result<Self, Self::Error> {
+        let point = match repr {
+            G2PointRepr::Infinity => G2Point::Infinity,
+            G2PointRepr::Point { x, y } => G2Point::Point { x, y },
+        };
+        if point.is_on_curve() {
+            Ok(point)
+        } else {
+            Err(format!("{:?} is not on the curve", point))
+        }
+    }
+}
+
+// The derived representation of a versioned type, generated with
+// #[serde(remote = "Self")] so that the impls below can add the version
+pub trait Versioned: Sized {
+    fn serialize_fields<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+    fn deserialize_fields<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+struct Fields<'a, T>(&'a T);
+
+impl<T: Versioned> Serialize for Fields<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_fields(serializer)
+    }
+}
+
+struct OwnedFields<T>(T);
+
+impl<'de, T: Versioned> Deserialize<'de> for OwnedFields<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_fields(deserializer).map(OwnedFields)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(bound = "T: Versioned")]
+struct Tagged<'a, T> {
+    version: u32,
+    #[serde(flatten)]
+    fields: Fields<'a, T>,
+}
+
+// The version is checked as it is read, before the fields of an unknown
+// schema can fail with a less helpful error
+#[derive(Deserialize)]
+#[serde(bound = "T: Versioned")]
+struct OwnedTagged<T> {
+    #[serde(rename = "version", deserialize_with = "supported_version")]
+    _version: (),
+    #[serde(flatten)]
+    fields: OwnedFields<T>,
+}
+
+fn supported_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version == SCHEMA_VERSION {
+        Ok(())
+    } else {
+        Err(D::Error::custom(format!(
+            "schema version {} is not supported, expected {}",
+            version, SCHEMA_VERSION
+        )))
+    }
+}
+
+macro_rules! versioned {
+    ($ty:ident, $($bounded:ty),+) => {
+        impl<E: Engine> Versioned for $ty<E>
+        where
+            $($bounded: Serialize + DeserializeOwned),+
+        {
+            fn serialize_fields<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                $ty::serialize(self, serializer)
+            }
+
+            fn deserialize_fields<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                $ty::deserialize(deserializer)
+            }
+        }
+
+        impl<E: Engine> Serialize for $ty<E>
+        where
+            Self: Versioned,
+        {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                Tagged {
+                    version: SCHEMA_VERSION,
+                    fields: Fields(self),
+                }
+                .serialize(serializer)
+            }
+        }
+
+        impl<'de, E: Engine> Deserialize<'de> for $ty<E>
+        where
+            Self: Versioned,
+        {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                OwnedTagged::deserialize(deserializer).map(|tagged| tagged.fields.0)
+            }
+        }
+    };
+}
+
+versioned!(Plonk_Proof, E::Fr, E::G1);
+versioned!(VerifyingKey, E::Fr, E::G1, E::G2);
+versioned!(SRS, E::G1, E::G2);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        curve::F17Curve,
+        field::{Point, G1},
+        field_extension::G2,
+        polynomial::Polynomial,
+        pythagorean_circuit::{gen_plonk_circuit, gen_proving_key},
+        pythagorean_transcript::{gen_transcript, wire_columns},
+        round5::{gen_round5_result, Plonk_Proof},
+        serialize::Decode,
+        srs::{get_srs, SRS},
+        verifier::{gen_verifying_key, VerifyingKey},
+    };
+
+    use super::*;
+
+    #[test]
+    fn schema_test() {
+        assert_eq!(serde_json::to_string(&F17(5)).unwrap(), "5");
+        assert_eq!(
+            serde_json::to_string(&G1).unwrap(),
+            r#"{"point":{"x":1,"y":2}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Point::Infinity).unwrap(),
+            r#""infinity""#
+        );
+        assert_eq!(
+            serde_json::to_string(&G2).unwrap(),
+            r#"{"point":{"x":{"real":36,"imag":0},"y":{"real":0,"imag":31}}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Polynomial {
+                coeffs: vec![F17(1), F17(16)]
+            })
+            .unwrap(),
+            r#"{"coeffs":[1,16]}"#
+        );
+
+        let proof = serde_json::to_value(
+            gen_round5_result(&gen_proving_key(), &wire_columns(gen_transcript())).unwrap(),
+        )
+        .unwrap();
+        for field in [
+            "wire_boxes",
+            "w_zeta_omega_box",
+            "sigma_bars",
+            "z_omega_bar",
+        ] {
+            assert!(proof.get(field).is_some(), "missing {}", field);
+        }
+        assert_eq!(proof["version"], SCHEMA_VERSION);
+        let srs = serde_json::to_value(get_srs()).unwrap();
+        assert_eq!(srs["version"], SCHEMA_VERSION);
+        assert!(srs.get("g1_points").is_some());
+    }
+
+    #[test]
+    fn schema_version_test() {
+        let mut proof = serde_json::to_value(
+            gen_round5_result(&gen_proving_key(), &wire_columns(gen_transcript())).unwrap(),
+        )
+        .unwrap();
+        assert!(serde_json::from_value::<Plonk_Proof>(proof.clone()).is_ok());
+
+        proof["version"] = 3.into();
+        let err = serde_json::from_value::<Plonk_Proof>(proof.clone()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("schema version 3 is not supported"));
+
+        // a version 1 document has no version
+        proof.as_object_mut().unwrap().remove("version");
+        let err = serde_json::from_value::<Plonk_Proof>(proof).unwrap_err();
+        assert!(err.to_string().contains("missing field `version`"));
+        let srs = r#"{"f101_points":[{"point":{"x":1,"y":2}}],"g2_points":[]}"#;
+        let err = serde_json::from_str::<SRS>(srs).unwrap_err();
+        assert!(err.to_string().contains("missing field `version`"));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let proof = gen_round5_result(&gen_proving_key(), &wire_columns(gen_transcript())).unwrap();
+        let json = serde_json::to_string_pretty(&proof).unwrap();
+        assert_eq!(serde_json::from_str::<Plonk_Proof>(&json).unwrap(), proof);
+
+        let vk = gen_verifying_key(&gen_plonk_circuit());
+        let json = serde_json::to_string(&vk).unwrap();
+        assert_eq!(serde_json::from_str::<VerifyingKey>(&json).unwrap(), vk);
+
+        let srs = get_srs();
+        let json = serde_json::to_string(&srs).unwrap();
+        let decoded: SRS = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.g1_points, srs.g1_points);
+        assert_eq!(decoded.g2_points, srs.g2_points);
+    }
+
+    #[test]
+    fn proof_json_test() {
+        // the one-wire proof of serialize::tests::proof_encoding_test
+        let bytes = [
+            10, 1, 1, 2, 1, 1, 0, 3, 1, 2, 1, 1, 0, 3, 1, 2, 1, 1, 0, 3, 1, 1, 2, 3, 4, 5, 6, 7, 8,
+            9, 10, 16,
+        ];
+        let proof = Plonk_Proof::from_bytes(&bytes).unwrap();
+        let g1 = r#"{"point":{"x":1,"y":2}}"#;
+        let neg = r#"{"point":{"x":1,"y":99}}"#;
+        let json = format!(
+            concat!(
+                r#"{{"version":2,"wire_boxes":[{g1}],"z_box":"infinity","f_box":{neg},"#,
+                r#""h1_box":{g1},"h2_box":"infinity","z2_box":{neg},"t_boxes":[{g1}],"#,
+                r#""w_zeta_box":"infinity","w_zeta_omega_box":{neg},"wire_bars":[1],"#,
+                r#""wire_omega_bars":[2],"sigma_bars":[],"r_bar":3,"z_omega_bar":4,"#,
+                r#""f_bar":5,"h1_bar":6,"h2_bar":7,"table_bar":8,"h1_omega_bar":9,"#,
+                r#""table_omega_bar":10,"z2_omega_bar":16}}"#
+            ),
+            g1 = g1,
+            neg = neg
+        );
+        assert_eq!(serde_json::to_string(&proof).unwrap(), json);
+        assert_eq!(serde_json::from_str::<Plonk_Proof>(&json).unwrap(), proof);
+    }
+
+    #[test]
+    fn validation_test() {
+        assert!(serde_json::from_str::<F17>("17").is_err());
+        assert!(serde_json::from_str::<F101>("101").is_err());
+        assert!(serde_json::from_str::<Point>(r#"{"point":{"x":1,"y":3}}"#).is_err());
+        assert!(serde_json::from_str::<G2Point>(
+            r#"{"point":{"x":{"real":36,"imag":0},"y":{"real":0,"imag":30}}}"#
+        )
+        .is_err());
+        assert_eq!(
+            serde_json::from_str::<Point>(r#""infinity""#).unwrap(),
+            Point::Infinity
+        );
+
+        // the curve equation comes from the curve parameters
+        let g = AffinePoint::<F17Curve>::generator();
+        assert_eq!(
+            serde_json::from_str::<AffinePoint<F17Curve>>(r#"{"point":{"x":5,"y":1}}"#).unwrap(),
+            g
+        );
+        assert!(
+            serde_json::from_str::<AffinePoint<F17Curve>>(r#"{"point":{"x":1,"y":2}}"#).is_err()
+        );
+    }
+}
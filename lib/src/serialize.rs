@@ -0,0 +1,784 @@
+// Canonical binary encoding of field elements, curve points and proofs.
+//
+// F17, F101: one byte holding the canonical representative (< p).
+// F1012:     real part, then imaginary part.
+// Point:     flag byte, then x and y. The flag is POINT_FLAG_AFFINE or
+//            POINT_FLAG_INFINITY, the point at infinity has x = y = 0.
+//            G2Point uses the same layout with F1012 coordinates.
+// Compressed points: flag byte, then x only. POINT_FLAG_EVEN_Y / POINT_FLAG_ODD_Y
+//            select the root of x^3 + 3, the point at infinity has x = 0.
+// BN254:     Fq and Fr as the 32 little-endian bytes of the canonical value, Fq2
+//            as c0 then c1, points in the Point layout. Encode only, for the
+//            Fiat-Shamir transcript.
+// Proof:     version byte, the number of wire columns k (1 to 4), the number of
+//            quotient pieces m (1 to 5), the k + m + 7 commitments, then the
+//            3k - 1 + 9 evaluations, in the order of the Plonk_Proof fields.
+//            Version 10 stores compressed commitments, version 9 (uncompressed) is
+//            still accepted on decode. Versions 1 to 8 have a fixed set of three
+//            wires or three quotient pieces, or lack the lookup or next-row
+//            openings, and are rejected.
+//
+// Decoding is strict: every value has exactly one accepted encoding, so
+// non-canonical field elements, unknown flags, off-curve points and
+// trailing bytes are all rejected.
+
+use std::fmt;
+
+use crate::{
+    big_field::{BigPrimeField, BigPrimeParams},
+    curve::{CurveParams, F101Curve},
+    engine::Engine,
+    field::{Point, F101, F17},
+    field_extension::{G2Point, F1012},
+    plonk_circuit::{MAX_QUOTIENT_PIECES, MAX_WIRES},
+    round5::Plonk_Proof,
+};
+
+#[cfg(feature = "bn254")]
+use crate::bn254::{
+    curve::{BnCurve, CurveField, CurvePoint},
+    field_extension::Fq2,
+};
+
+pub const PROOF_VERSION_UNCOMPRESSED: u8 = 9;
+pub const PROOF_VERSION: u8 = 10;
+
+pub const POINT_FLAG_AFFINE: u8 = 0;
+pub const POINT_FLAG_INFINITY: u8 = 1;
+pub const POINT_FLAG_EVEN_Y: u8 = 2;
+pub const POINT_FLAG_ODD_Y: u8 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    TrailingBytes { remaining: usize },
+    UnsupportedVersion(u8),
+    InvalidWireCount(u8),
+    InvalidQuotientPieceCount(u8),
+    NonCanonicalField { value: u8, modulus: u32 },
+    InvalidPointFlag(u8),
+    NonCanonicalInfinity,
+    NonCanonicalSign,
+    NotOnCurve,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::TrailingBytes { remaining } => {
+                write!(f, "{} trailing bytes after the encoded value", remaining)
+            }
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported encoding version {}", version)
+            }
+            DecodeError::InvalidWireCount(count) => {
+                write!(f, "{} wire columns, expected 1 to {}", count, MAX_WIRES)
+            }
+            DecodeError::InvalidQuotientPieceCount(count) => {
+                write!(
+                    f,
+                    "{} quotient pieces, expected 1 to {}",
+                    count, MAX_QUOTIENT_PIECES
+                )
+            }
+            DecodeError::NonCanonicalField { value, modulus } => {
+                write!(f, "{} is not a canonical element of F{}", value, modulus)
+            }
+            DecodeError::InvalidPointFlag(flag) => write!(f, "invalid point flag {:#04x}", flag),
+            DecodeError::NonCanonicalInfinity => {
+                write!(f, "point at infinity with non-zero coordinates")
+            }
+            DecodeError::NonCanonicalSign => write!(f, "odd sign flag for y = 0"),
+            DecodeError::NotOnCurve => write!(f, "point is not on the curve"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        let (&first, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(first)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        let mut bytes = [0u8; 4];
+        for byte in bytes.iter_mut() {
+            *byte = self.read_u8()?;
+        }
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn finish(self) -> Result<(), DecodeError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(DecodeError::TrailingBytes {
+                remaining: self.bytes.len(),
+            })
+        }
+    }
+}
+
+pub trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+}
+
+pub trait Decode: Sized {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError>;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes);
+        let value = Self::decode(&mut reader)?;
+        reader.finish()?;
+        Ok(value)
+    }
+}
+
+fn read_canonical(reader: &mut Reader<'_>, modulus: u32) -> Result<u32, DecodeError> {
+    let value = reader.read_u8()?;
+    if u32::from(value) >= modulus {
+        return Err(DecodeError::NonCanonicalField { value, modulus });
+    }
+    Ok(u32::from(value))
+}
+
+// The tuple fields are public, so a value such as F101(300) can reach encode.
+// Reduce it first, or the byte would be the truncated 300 mod 256 = 44.
+impl Encode for F17 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(F17::new(self.value()).value() as u8);
+    }
+}
+
+impl Decode for F17 {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        read_canonical(reader, F17::P).map(F17)
+    }
+}
+
+impl Encode for F101 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(F101::new(self.value()).value() as u8);
+    }
+}
+
+impl Decode for F101 {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        read_canonical(reader, F101::P).map(F101)
+    }
+}
+
+impl Encode for Point {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Point::Infinity => {
+                out.push(POINT_FLAG_INFINITY);
+                F101::ZERO.encode(out);
+                F101::ZERO.encode(out);
+            }
+            Point::Point { x, y } => {
+                out.push(POINT_FLAG_AFFINE);
+                x.encode(out);
+                y.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for Point {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let flag = reader.read_u8()?;
+        let x = F101::decode(reader)?;
+        let y = F101::decode(reader)?;
+        match flag {
+            POINT_FLAG_INFINITY if x == F101::ZERO && y == F101::ZERO => Ok(Point::Infinity),
+            POINT_FLAG_INFINITY => Err(DecodeError::NonCanonicalInfinity),
+            POINT_FLAG_AFFINE => {
+                let point = Point::Point { x, y };
+                if point.is_on_curve() {
+                    Ok(point)
+                } else {
+                    Err(DecodeError::NotOnCurve)
+                }
+            }
+            _ => Err(DecodeError::InvalidPointFlag(flag)),
+        }
+    }
+}
+
+impl Encode for F1012 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.real.encode(out);
+        self.imag.encode(out);
+    }
+}
+
+impl Decode for F1012 {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(F1012 {
+            real: F101::decode(reader)?,
+            imag: F101::decode(reader)?,
+        })
+    }
+}
+
+impl Encode for G2Point {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            G2Point::Infinity => {
+                out.push(POINT_FLAG_INFINITY);
+                F1012::ZERO.encode(out);
+                F1012::ZERO.encode(out);
+            }
+            G2Point::Point { x, y } => {
+                out.push(POINT_FLAG_AFFINE);
+                x.encode(out);
+                y.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for G2Point {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let flag = reader.read_u8()?;
+        let x = F1012::decode(reader)?;
+        let y = F1012::decode(reader)?;
+        match flag {
+            POINT_FLAG_INFINITY if x == F1012::ZERO && y == F1012::ZERO => Ok(G2Point::Infinity),
+            POINT_FLAG_INFINITY => Err(DecodeError::NonCanonicalInfinity),
+            POINT_FLAG_AFFINE => {
+                let point = G2Point::Point { x, y };
+                if point.is_on_curve() {
+                    Ok(point)
+                } else {
+                    Err(DecodeError::NotOnCurve)
+                }
+            }
+            _ => Err(DecodeError::InvalidPointFlag(flag)),
+        }
+    }
+}
+
+impl<P: BigPrimeParams<N>, const N: usize> Encode for BigPrimeField<P, N> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        for limb in self.to_raw() {
+            out.extend(limb.to_le_bytes());
+        }
+    }
+}
+
+#[cfg(feature = "bn254")]
+impl Encode for Fq2 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.c0.encode(out);
+        self.c1.encode(out);
+    }
+}
+
+#[cfg(feature = "bn254")]
+impl<C: BnCurve> Encode for CurvePoint<C>
+where
+    C::Base: Encode,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            CurvePoint::Infinity => {
+                out.push(POINT_FLAG_INFINITY);
+                C::Base::ZERO.encode(out);
+                C::Base::ZERO.encode(out);
+            }
+            CurvePoint::Point { x, y } => {
+                out.push(POINT_FLAG_AFFINE);
+                x.encode(out);
+                y.encode(out);
+            }
+        }
+    }
+}
+
+// Wrapper selecting the compressed encoding of a point
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compressed<T>(pub T);
+
+fn sign_flag(odd: bool) -> u8 {
+    if odd {
+        POINT_FLAG_ODD_Y
+    } else {
+        POINT_FLAG_EVEN_Y
+    }
+}
+
+impl Encode for Compressed<Point> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self.0 {
+            Point::Infinity => {
+                out.push(POINT_FLAG_INFINITY);
+                F101::ZERO.encode(out);
+            }
+            Point::Point { x, y } => {
+                out.push(sign_flag(y.is_odd()));
+                x.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for Compressed<Point> {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let flag = reader.read_u8()?;
+        let x = F101::decode(reader)?;
+        let odd = match flag {
+            POINT_FLAG_INFINITY if x == F101::ZERO => return Ok(Compressed(Point::Infinity)),
+            POINT_FLAG_INFINITY => return Err(DecodeError::NonCanonicalInfinity),
+            POINT_FLAG_EVEN_Y => false,
+            POINT_FLAG_ODD_Y => true,
+            _ => return Err(DecodeError::InvalidPointFlag(flag)),
+        };
+        let y = x
+            .mul(x)
+            .mul(x)
+            .add(F101Curve::B)
+            .sqrt()
+            .ok_or(DecodeError::NotOnCurve)?;
+        let y = if y.is_odd() == odd { y } else { y.neg() };
+        if y.is_odd() != odd {
+            // y = 0 has no odd representative
+            return Err(DecodeError::NonCanonicalSign);
+        }
+        Ok(Compressed(Point::Point { x, y }))
+    }
+}
+
+impl Encode for Compressed<G2Point> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self.0 {
+            G2Point::Infinity => {
+                out.push(POINT_FLAG_INFINITY);
+                F1012::ZERO.encode(out);
+            }
+            G2Point::Point { x, y } => {
+                out.push(sign_flag(y.is_odd()));
+                x.encode(out);
+            }
+        }
+    }
+}
+
+impl Decode for Compressed<G2Point> {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let flag = reader.read_u8()?;
+        let x = F1012::decode(reader)?;
+        let odd = match flag {
+            POINT_FLAG_INFINITY if x == F1012::ZERO => return Ok(Compressed(G2Point::Infinity)),
+            POINT_FLAG_INFINITY => return Err(DecodeError::NonCanonicalInfinity),
+            POINT_FLAG_EVEN_Y => false,
+            POINT_FLAG_ODD_Y => true,
+            _ => return Err(DecodeError::InvalidPointFlag(flag)),
+        };
+        let y = x
+            .mul(x)
+            .mul(x)
+            .add(F1012::new(3, 0))
+            .sqrt()
+            .ok_or(DecodeError::NotOnCurve)?;
+        let y = if y.is_odd() == odd { y } else { y.neg() };
+        if y.is_odd() != odd {
+            return Err(DecodeError::NonCanonicalSign);
+        }
+        Ok(Compressed(G2Point::Point { x, y }))
+    }
+}
+
+impl<E: Engine> Plonk_Proof<E> {
+    // Every commitment and every evaluation in the order of the byte layout
+    pub(crate) fn commitments(&self) -> Vec<E::G1> {
+        let mut points = self.wire_boxes.clone();
+        points.extend([
+            self.z_box,
+            self.f_box,
+            self.h1_box,
+            self.h2_box,
+            self.z2_box,
+        ]);
+        points.extend(&self.t_boxes);
+        points.extend([self.w_zeta_box, self.w_zeta_omega_box]);
+        points
+    }
+
+    pub(crate) fn evaluations(&self) -> Vec<E::Fr> {
+        let mut scalars = self.wire_bars.clone();
+        scalars.extend(&self.wire_omega_bars);
+        scalars.extend(&self.sigma_bars);
+        scalars.extend([
+            self.r_bar,
+            self.z_omega_bar,
+            self.f_bar,
+            self.h1_bar,
+            self.h2_bar,
+            self.table_bar,
+            self.h1_omega_bar,
+            self.table_omega_bar,
+            self.z2_omega_bar,
+        ]);
+        scalars
+    }
+}
+
+impl Plonk_Proof {
+    // The version 9 layout, with every commitment stored as a full (x, y) pair
+    pub fn to_bytes_uncompressed(&self) -> Vec<u8> {
+        let mut out = vec![
+            PROOF_VERSION_UNCOMPRESSED,
+            self.wire_boxes.len() as u8,
+            self.t_boxes.len() as u8,
+        ];
+        for point in self.commitments() {
+            point.encode(&mut out);
+        }
+        for scalar in self.evaluations() {
+            scalar.encode(&mut out);
+        }
+        out
+    }
+}
+
+impl Encode for Plonk_Proof {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(PROOF_VERSION);
+        out.push(self.wire_boxes.len() as u8);
+        out.push(self.t_boxes.len() as u8);
+        for point in self.commitments() {
+            Compressed(point).encode(out);
+        }
+        for scalar in self.evaluations() {
+            scalar.encode(out);
+        }
+    }
+}
+
+impl Decode for Plonk_Proof {
+    fn decode(reader: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let version = reader.read_u8()?;
+        let read_point: fn(&mut Reader<'_>) -> Result<Point, DecodeError> = match version {
+            PROOF_VERSION_UNCOMPRESSED => Point::decode,
+            PROOF_VERSION => |reader| Compressed::<Point>::decode(reader).map(|c| c.0),
+            _ => return Err(DecodeError::UnsupportedVersion(version)),
+        };
+        let count = reader.read_u8()?;
+        if count == 0 || count as usize > MAX_WIRES {
+            return Err(DecodeError::InvalidWireCount(count));
+        }
+        let k = count as usize;
+        let pieces = reader.read_u8()?;
+        if pieces == 0 || pieces as usize > MAX_QUOTIENT_PIECES {
+            return Err(DecodeError::InvalidQuotientPieceCount(pieces));
+        }
+        let wire_boxes = (0..k)
+            .map(|_| read_point(reader))
+            .collect::<Result<_, _>>()?;
+        let z_box = read_point(reader)?;
+        let f_box = read_point(reader)?;
+        let h1_box = read_point(reader)?;
+        let h2_box = read_point(reader)?;
+        let z2_box = read_point(reader)?;
+        let t_boxes = (0..pieces)
+            .map(|_| read_point(reader))
+            .collect::<Result<_, _>>()?;
+        let w_zeta_box = read_point(reader)?;
+        let w_zeta_omega_box = read_point(reader)?;
+        let mut read_scalars = |len| {
+            (0..len)
+                .map(|_| F17::decode(reader))
+                .collect::<Result<_, _>>()
+        };
+        let wire_bars = read_scalars(k)?;
+        let wire_omega_bars = read_scalars(k)?;
+        let sigma_bars = read_scalars(k - 1)?;
+        Ok(Plonk_Proof {
+            wire_boxes,
+            z_box,
+            f_box,
+            h1_box,
+            h2_box,
+            z2_box,
+            t_boxes,
+            w_zeta_box,
+            w_zeta_omega_box,
+            wire_bars,
+            wire_omega_bars,
+            sigma_bars,
+            r_bar: F17::decode(reader)?,
+            z_omega_bar: F17::decode(reader)?,
+            f_bar: F17::decode(reader)?,
+            h1_bar: F17::decode(reader)?,
+            h2_bar: F17::decode(reader)?,
+            table_bar: F17::decode(reader)?,
+            h1_omega_bar: F17::decode(reader)?,
+            table_omega_bar: F17::decode(reader)?,
+            z2_omega_bar: F17::decode(reader)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        field::G1,
+        field_extension::G2,
+        pythagorean_circuit::gen_proving_key,
+        pythagorean_transcript::{gen_transcript, wire_columns},
+        round5::gen_round5_result,
+    };
+
+    use super::*;
+
+    #[test]
+    fn point_round_trip_test() {
+        for k in 0..17 {
+            let point = G1.mul(F17(k));
+            assert_eq!(Point::from_bytes(&point.to_bytes()), Ok(point));
+        }
+        assert_eq!(Point::Infinity.to_bytes(), vec![POINT_FLAG_INFINITY, 0, 0]);
+    }
+
+    #[test]
+    fn non_canonical_scalar_test() {
+        // 300 = 98 mod 101 but 44 mod 256, and 20 = 3 mod 17
+        assert_eq!(F101(300).to_bytes(), vec![98]);
+        assert_eq!(F101::from_bytes(&F101(300).to_bytes()), Ok(F101::new(300)));
+        assert_eq!(F17::from_bytes(&F17(20).to_bytes()), Ok(F17(3)));
+        let point = Point::Point {
+            x: F101(1 + 101),
+            y: F101(2 + 202),
+        };
+        assert_eq!(
+            Point::from_bytes(&point.to_bytes()),
+            Ok(Point::Point {
+                x: F101(1),
+                y: F101(2)
+            })
+        );
+    }
+
+    #[test]
+    fn proof_round_trip_test() {
+        let proof = gen_round5_result(&gen_proving_key(), &wire_columns(gen_transcript())).unwrap();
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), 3 + 13 * 2 + 17);
+        assert_eq!(bytes[0], PROOF_VERSION);
+        assert_eq!(bytes[1], 3);
+        assert_eq!(bytes[2], 3);
+        assert_eq!(Plonk_Proof::from_bytes(&bytes), Ok(proof.clone()));
+
+        let uncompressed = proof.to_bytes_uncompressed();
+        assert_eq!(uncompressed.len(), 3 + 13 * 3 + 17);
+        assert_eq!(Plonk_Proof::from_bytes(&uncompressed), Ok(proof));
+    }
+
+    #[test]
+    fn proof_encoding_test() {
+        // one wire and one quotient piece, with G1 = (1, 2), its negation (1, 99)
+        // and the point at infinity as commitments
+        let neg = G1.point_neg();
+        let proof = Plonk_Proof {
+            wire_boxes: vec![G1],
+            z_box: Point::Infinity,
+            f_box: neg,
+            h1_box: G1,
+            h2_box: Point::Infinity,
+            z2_box: neg,
+            t_boxes: vec![G1],
+            w_zeta_box: Point::Infinity,
+            w_zeta_omega_box: neg,
+            wire_bars: vec![F17(1)],
+            wire_omega_bars: vec![F17(2)],
+            sigma_bars: vec![],
+            r_bar: F17(3),
+            z_omega_bar: F17(4),
+            f_bar: F17(5),
+            h1_bar: F17(6),
+            h2_bar: F17(7),
+            table_bar: F17(8),
+            h1_omega_bar: F17(9),
+            table_omega_bar: F17(10),
+            z2_omega_bar: F17(16),
+        };
+        let bytes = vec![
+            10, 1, 1, // version, k, m
+            2, 1, 1, 0, 3, 1, // [a], [z], [f]
+            2, 1, 1, 0, 3, 1, // [h1], [h2], [z2]
+            2, 1, 1, 0, 3, 1, // [t_0], [W_zeta], [W_zeta_omega]
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 16,
+        ];
+        assert_eq!(proof.to_bytes(), bytes);
+        assert_eq!(Plonk_Proof::from_bytes(&bytes), Ok(proof.clone()));
+
+        let uncompressed = vec![
+            9, 1, 1, // version, k, m
+            0, 1, 2, 1, 0, 0, 0, 1, 99, // [a], [z], [f]
+            0, 1, 2, 1, 0, 0, 0, 1, 99, // [h1], [h2], [z2]
+            0, 1, 2, 1, 0, 0, 0, 1, 99, // [t_0], [W_zeta], [W_zeta_omega]
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 16,
+        ];
+        assert_eq!(proof.to_bytes_uncompressed(), uncompressed);
+        assert_eq!(Plonk_Proof::from_bytes(&uncompressed), Ok(proof));
+    }
+
+    #[test]
+    fn compressed_point_test() {
+        for k in 0..17 {
+            let point = G1.mul(F17(k));
+            let bytes = Compressed(point).to_bytes();
+            assert_eq!(bytes.len(), 2);
+            assert_eq!(
+                Compressed::<Point>::from_bytes(&bytes),
+                Ok(Compressed(point))
+            );
+
+            let point = G2.mul(F17(k));
+            let bytes = Compressed(point).to_bytes();
+            assert_eq!(bytes.len(), 3);
+            assert_eq!(G2Point::from_bytes(&point.to_bytes()), Ok(point));
+            assert_eq!(
+                Compressed::<G2Point>::from_bytes(&bytes),
+                Ok(Compressed(point))
+            );
+        }
+
+        // x^3 + 3 = 11 is not a square for x = 2
+        assert!(!F101(11).is_square());
+        assert_eq!(
+            Compressed::<Point>::from_bytes(&[POINT_FLAG_EVEN_Y, 2]),
+            Err(DecodeError::NotOnCurve)
+        );
+        assert_eq!(
+            Compressed::<Point>::from_bytes(&[POINT_FLAG_AFFINE, 1]),
+            Err(DecodeError::InvalidPointFlag(POINT_FLAG_AFFINE))
+        );
+        assert_eq!(
+            Compressed::<Point>::from_bytes(&[POINT_FLAG_INFINITY, 1]),
+            Err(DecodeError::NonCanonicalInfinity)
+        );
+        // (48, 0) is on the curve, and y = 0 only has the even encoding
+        assert_eq!(
+            Compressed::<Point>::from_bytes(&[POINT_FLAG_EVEN_Y, 48]),
+            Ok(Compressed(Point::Point {
+                x: F101(48),
+                y: F101(0)
+            }))
+        );
+        assert_eq!(
+            Compressed::<Point>::from_bytes(&[POINT_FLAG_ODD_Y, 48]),
+            Err(DecodeError::NonCanonicalSign)
+        );
+    }
+
+    #[test]
+    fn strict_decoding_test() {
+        assert_eq!(F17::from_bytes(&[16]), Ok(F17(16)));
+        assert_eq!(
+            F17::from_bytes(&[17]),
+            Err(DecodeError::NonCanonicalField {
+                value: 17,
+                modulus: 17
+            })
+        );
+        assert_eq!(
+            F101::from_bytes(&[101]),
+            Err(DecodeError::NonCanonicalField {
+                value: 101,
+                modulus: 101
+            })
+        );
+        assert_eq!(F17::from_bytes(&[]), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(
+            F17::from_bytes(&[1, 2]),
+            Err(DecodeError::TrailingBytes { remaining: 1 })
+        );
+
+        // (1, 3) is not on y^2 = x^3 + 3
+        assert_eq!(
+            Point::from_bytes(&[POINT_FLAG_AFFINE, 1, 3]),
+            Err(DecodeError::NotOnCurve)
+        );
+        assert_eq!(
+            Point::from_bytes(&[POINT_FLAG_INFINITY, 1, 2]),
+            Err(DecodeError::NonCanonicalInfinity)
+        );
+        assert_eq!(
+            Point::from_bytes(&[2, 1, 2]),
+            Err(DecodeError::InvalidPointFlag(2))
+        );
+
+        let bytes = gen_round5_result(&gen_proving_key(), &wire_columns(gen_transcript()))
+            .unwrap()
+            .to_bytes();
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = PROOF_VERSION + 1;
+        assert_eq!(
+            Plonk_Proof::from_bytes(&wrong_version),
+            Err(DecodeError::UnsupportedVersion(PROOF_VERSION + 1))
+        );
+        // proofs with the fixed a, b, c layout
+        wrong_version[0] = 6;
+        assert_eq!(
+            Plonk_Proof::from_bytes(&wrong_version),
+            Err(DecodeError::UnsupportedVersion(6))
+        );
+        for count in [0, 5] {
+            let mut wrong_count = bytes.clone();
+            wrong_count[1] = count;
+            assert_eq!(
+                Plonk_Proof::from_bytes(&wrong_count),
+                Err(DecodeError::InvalidWireCount(count))
+            );
+        }
+        // proofs with exactly three quotient pieces
+        wrong_version[0] = 8;
+        assert_eq!(
+            Plonk_Proof::from_bytes(&wrong_version),
+            Err(DecodeError::UnsupportedVersion(8))
+        );
+        for count in [0, 6] {
+            let mut wrong_count = bytes.clone();
+            wrong_count[2] = count;
+            assert_eq!(
+                Plonk_Proof::from_bytes(&wrong_count),
+                Err(DecodeError::InvalidQuotientPieceCount(count))
+            );
+        }
+        // a three-wire proof read as a two-wire one
+        let mut fewer = bytes.clone();
+        fewer[1] = 2;
+        assert!(Plonk_Proof::from_bytes(&fewer).is_err());
+        assert_eq!(
+            Plonk_Proof::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        let mut non_canonical = bytes.clone();
+        *non_canonical.last_mut().unwrap() += 17;
+        assert!(matches!(
+            Plonk_Proof::from_bytes(&non_canonical),
+            Err(DecodeError::NonCanonicalField { modulus: 17, .. })
+        ));
+    }
+}
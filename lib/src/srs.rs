@@ -1,58 +1,237 @@
+use std::{fmt, sync::OnceLock};
+
 use crate::{
-    field::{point_add, scalar_mult, Point, F101},
-    field_extension::F1012,
+    engine::{Engine, Group, Toy},
+    field::{Point, ScalarField, F17},
+    fixed_base::{FixedBaseTable, TablePoint},
+    msm::{msm, MsmScalar},
     polynomial::Polynomial,
 };
 
-#[derive(Clone, Debug)]
-pub struct SRS {
-    pub f101_points: Vec<Point>,
-    pub f101_2_elements: Vec<F1012>,
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(remote = "Self"),
+    serde(bound(
+        serialize = "E::G1: serde::Serialize, E::G2: serde::Serialize",
+        deserialize = "E::G1: serde::Deserialize<'de>, E::G2: serde::Deserialize<'de>"
+    ))
+)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SRS<E: Engine = Toy> {
+    pub g1_points: Vec<E::G1>,
+    pub g2_points: Vec<E::G2>,
 }
 
-pub fn get_srs() -> SRS {
-    let g1 = Point::Point {
-        x: F101::new(1),
-        y: F101::new(2),
-    };
-    let mut srs = Vec::new();
+impl<E: Engine> SRS<E> {
+    // tau^0*G1, tau^1*G1, ..., tau^max_degree*G1, and G2, tau*G2 for pairing
+    pub fn new(max_degree: usize, tau: E::Fr) -> SRS<E> {
+        let g2 = E::G2::generator();
+        let g1_points = (0..=max_degree)
+            .map(|i| E::g1_table().mul(tau.pow(i as u64)))
+            .collect();
+        SRS {
+            g1_points,
+            g2_points: vec![g2, g2.mul(tau)],
+        }
+    }
 
-    // compute 1*G1, 2*G1, 2^2*G1, 2^3*G1,..., 2^6*G1
-    // tau^0*G1, tau^1*G1...
-    for i in 0..=6 {
-        let k = 2u32.pow(i);
-        let point = scalar_mult(k, g1);
-        // println!("2^{} * G1 = {:?}", i, point);
-        srs.push(point);
+    // The largest degree this SRS can commit to, None when it has no G1 points.
+    // The fields are public and deserializable, so an empty SRS can exist.
+    pub fn max_degree(&self) -> Option<usize> {
+        self.g1_points.len().checked_sub(1)
     }
-    // two F101_2 element here for pairing, G2, tau*G2 (2*G2)
-    let g2 = F1012::new(36, 31);
-    let g2_2 = F1012::new(90, 82);
 
-    SRS {
-        f101_points: srs,
-        f101_2_elements: [g2, g2_2].to_vec(),
+    pub fn tables(&self) -> SrsTables<E::G1> {
+        SrsTables {
+            tables: self
+                .g1_points
+                .iter()
+                .map(|&point| FixedBaseTable::for_scalars::<E::Fr>(point))
+                .collect(),
+        }
     }
 }
 
-pub fn commit_poly(poly_for_commit: Polynomial) -> Point {
-    let srs = get_srs();
-    let mut committed_point = Point::Infinity;
-    for j in 0..poly_for_commit.coeffs.len() {
-        let coeff = poly_for_commit.coeffs[j].0;
-        let p = scalar_mult(coeff, srs.f101_points[j]);
-        committed_point = point_add(committed_point, p);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitError {
+    DegreeTooLarge { degree: usize, max_degree: usize },
+    EmptySrs,
+}
+
+impl fmt::Display for CommitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitError::DegreeTooLarge { degree, max_degree } => write!(
+                f,
+                "polynomial of degree {} exceeds the SRS maximum degree {}",
+                degree, max_degree
+            ),
+            CommitError::EmptySrs => write!(f, "the SRS has no G1 points"),
+        }
+    }
+}
+
+impl std::error::Error for CommitError {}
+
+pub fn get_srs() -> SRS {
+    SRS::new(6, F17(2))
+}
+
+// Fixed-base tables for the G1 points of an SRS, so that repeated commitments
+// against the same SRS skip the doublings
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SrsTables<P: TablePoint = Point> {
+    tables: Vec<FixedBaseTable<P>>,
+}
+
+// The degree a polynomial is committed at, None for the zero polynomial.
+// Trailing zero coefficients do not count toward the degree.
+fn commit_degree<F: ScalarField>(
+    poly: &Polynomial<F>,
+    max_degree: Option<usize>,
+) -> Result<Option<usize>, CommitError> {
+    match (poly.degree(), max_degree) {
+        (None, _) => Ok(None),
+        (Some(_), None) => Err(CommitError::EmptySrs),
+        (Some(degree), Some(max_degree)) if degree > max_degree => {
+            Err(CommitError::DegreeTooLarge { degree, max_degree })
+        }
+        (degree, _) => Ok(degree),
     }
-    committed_point
+}
+
+// [p(tau)]_1 = sum of c_j * tau^j*G1
+pub fn commit<E: Engine>(srs: &SRS<E>, poly: &Polynomial<E::Fr>) -> Result<E::G1, CommitError> {
+    Ok(match commit_degree(poly, srs.max_degree())? {
+        Some(degree) => msm(&srs.g1_points[..=degree], &poly.coeffs[..=degree]),
+        None => <E::G1 as Group<E::Fr>>::IDENTITY,
+    })
+}
+
+pub fn commit_with_tables<P: TablePoint, F: ScalarField + MsmScalar>(
+    tables: &SrsTables<P>,
+    poly: &Polynomial<F>,
+) -> Result<P, CommitError> {
+    let degree = match commit_degree(poly, tables.tables.len().checked_sub(1))? {
+        Some(degree) => degree,
+        None => return Ok(P::to_affine(P::IDENTITY)),
+    };
+    let sum = tables.tables[..=degree]
+        .iter()
+        .zip(&poly.coeffs)
+        .fold(P::IDENTITY, |acc, (table, &coeff)| {
+            P::add_affine(acc, table.mul(coeff))
+        });
+    Ok(P::to_affine(sum))
+}
+
+// Commits against get_srs(), whose tables are built once
+pub fn commit_poly(poly_for_commit: Polynomial) -> Point {
+    static TABLES: OnceLock<SrsTables> = OnceLock::new();
+    let tables = TABLES.get_or_init(|| get_srs().tables());
+    commit_with_tables(tables, &poly_for_commit).unwrap()
 }
 
 #[cfg(test)]
 pub mod tests {
-    use super::get_srs;
+    use crate::{
+        field::{scalar_mult, G1},
+        field_extension::G2,
+    };
+
+    use super::*;
 
     #[test]
     pub fn srs_display() {
         let srs = get_srs();
         println!("{:?}", srs);
     }
+
+    #[test]
+    fn srs_new_test() {
+        // compute 1*G1, 2*G1, 2^2*G1, 2^3*G1,..., 2^6*G1
+        let expected: Vec<Point> = (0..=6).map(|i| scalar_mult(2u32.pow(i), G1)).collect();
+        let srs = SRS::<Toy>::new(6, F17(2));
+        assert_eq!(srs.g1_points, expected);
+        assert_eq!(srs.max_degree(), Some(6));
+        assert_eq!(SRS::<Toy>::new(10, F17(5)).max_degree(), Some(10));
+    }
+
+    #[test]
+    fn commit_test() {
+        let srs = SRS::<Toy>::new(3, F17(2));
+        let poly = Polynomial {
+            coeffs: vec![F17(1), F17(2), F17(3), F17(4)],
+        };
+        // p(2) = 1 + 4 + 12 + 32 = 49 = 15 mod 17
+        assert_eq!(commit(&srs, &poly), Ok(G1.mul(F17(15))));
+
+        // zero padding is ignored
+        let padded = Polynomial {
+            coeffs: vec![F17(1), F17(2), F17(3), F17(4), F17::ZERO, F17::ZERO],
+        };
+        assert_eq!(commit(&srs, &padded), commit(&srs, &poly));
+        assert_eq!(
+            commit(&srs, &Polynomial { coeffs: vec![] }),
+            Ok(Point::Infinity)
+        );
+
+        let too_long = Polynomial {
+            coeffs: vec![F17(1), F17(2), F17(3), F17(4), F17(5)],
+        };
+        assert_eq!(
+            commit(&srs, &too_long),
+            Err(CommitError::DegreeTooLarge {
+                degree: 4,
+                max_degree: 3
+            })
+        );
+    }
+
+    #[test]
+    fn empty_srs_test() {
+        let srs = SRS::<Toy> {
+            g1_points: vec![],
+            g2_points: vec![G2],
+        };
+        assert_eq!(srs.max_degree(), None);
+        assert_eq!(
+            commit(&srs, &Polynomial { coeffs: vec![] }),
+            Ok(Point::Infinity)
+        );
+        let constant = Polynomial {
+            coeffs: vec![F17(1)],
+        };
+        assert_eq!(commit(&srs, &constant), Err(CommitError::EmptySrs));
+        assert_eq!(
+            commit_with_tables(&srs.tables(), &constant),
+            Err(CommitError::EmptySrs)
+        );
+    }
+
+    #[test]
+    fn commit_with_tables_test() {
+        let srs = SRS::<Toy>::new(6, F17(3));
+        let tables = srs.tables();
+        for seed in 0..F17::P {
+            let poly = Polynomial {
+                coeffs: (0..7).map(|i| F17::new(seed * i + i * i)).collect(),
+            };
+            assert_eq!(commit_with_tables(&tables, &poly), commit(&srs, &poly));
+        }
+        let too_long = Polynomial {
+            coeffs: vec![F17::ONE; 8],
+        };
+        assert_eq!(
+            commit_with_tables(&tables, &too_long),
+            commit(&srs, &too_long)
+        );
+        assert_eq!(
+            commit_poly(Polynomial {
+                coeffs: vec![F17(1), F17(1)]
+            }),
+            G1.mul(F17(3))
+        );
+    }
 }
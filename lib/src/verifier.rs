@@ -1,215 +1,991 @@
 // The verifier has |W| (permutation commitment) and |S| (selector commitment) in advance.
-// |W| is sigma1,2,3, |S| is q_L,q_R,q_O,q_M,q_C
+// |W| is one sigma per wire column, |S| is one selector per declared gate (q_L,q_R,
+// q_O,q_M,q_C and the circuit's own), and the lookup adds q_K and the table t
+
+use std::fmt;
 
 use crate::{
-    field::{point_add, scalar_mult, Point, F101, F17},
-    prescribed_permutation::gen_sigma_polys,
-    pythagorean_circuit::{gen_selector_polys, N},
-    round2::{BETA, GAMMA},
+    custom_gate::{gate_scalars, Expression},
+    engine::{Engine, Group, Toy},
+    fiat_shamir::FiatShamir,
+    field::{batch_inverse, lift, ScalarField, F17},
+    lookup::{DELTA, EPSILON},
+    msm::msm,
+    plonk_circuit::{omega, PlonkCircuit, N},
+    round2::permutation_challenges,
     round3::ALPHA,
     round4::ZETA,
     round5::{Plonk_Proof, V},
-    srs::commit_poly,
+    srs::{commit, get_srs, SRS},
 };
 
-fn verify_point_on_ec_curve(p: Point) -> bool {
-    let result = match p {
-        Point::Infinity => true,
-        Point::Point { x, y } => {
-            let lhs = y.mul(y);
-            let rhs = x.mul(x).mul(x).add(F101(3));
-            lhs == rhs
+// A declared gate as the verifier sees it: the expression and [selector]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "E::Fr: serde::Serialize, E::G1: serde::Serialize",
+        deserialize = "E::Fr: serde::Deserialize<'de>, E::G1: serde::Deserialize<'de>"
+    ))
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedGate<E: Engine = Toy> {
+    pub expression: Expression<E::Fr>,
+    pub selector_box: E::G1,
+}
+
+// Preprocessed verifier input: selector and permutation commitments, plus [1]_2 and [x]_2
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(remote = "Self"),
+    serde(bound(
+        serialize = "E::Fr: serde::Serialize, E::G1: serde::Serialize, E::G2: serde::Serialize",
+        deserialize = "E::Fr: serde::Deserialize<'de>, E::G1: serde::Deserialize<'de>, E::G2: serde::Deserialize<'de>"
+    ))
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyingKey<E: Engine = Toy> {
+    pub n: u32,
+    // k_1, ..., k_(k-1), the first column is labelled on H itself
+    pub coset_shifts: Vec<E::Fr>,
+    pub gates: Vec<CommittedGate<E>>,
+    pub q_k_box: E::G1,
+    // one per wire column, so the proof must have as many wires
+    pub sigma_boxes: Vec<E::G1>,
+    pub table_box: E::G1,
+    // the number of pieces t(x) is committed in
+    pub quotient_pieces: usize,
+    pub g2: E::G2,
+    pub x_g2: E::G2,
+}
+
+impl<E: Engine> VerifyingKey<E> {
+    // The circuit's fixed columns committed with the SRS the prover uses
+    pub fn new(circuit: &PlonkCircuit<E::Fr>, srs: &SRS<E>) -> Self {
+        let commit = |poly| commit(srs, &poly).expect("the SRS is too small for the circuit");
+        let gates = circuit
+            .gates
+            .iter()
+            .map(|gate| CommittedGate {
+                selector_box: commit(gate.selector_poly()),
+                expression: gate.expression.clone(),
+            })
+            .collect();
+        let sigma_boxes = circuit
+            .sigma_polys()
+            .into_iter()
+            .map(commit)
+            .collect::<Vec<_>>();
+        let [q_k, table] = circuit.lookup_polys();
+        VerifyingKey {
+            n: N as u32,
+            coset_shifts: circuit.coset_shifts[1..].to_vec(),
+            gates,
+            q_k_box: commit(q_k),
+            sigma_boxes,
+            table_box: commit(table),
+            quotient_pieces: circuit.quotient_pieces(),
+            g2: srs.g2_points[0],
+            x_g2: srs.g2_points[1],
         }
-    };
-    result
+    }
 }
 
-#[allow(non_snake_case)]
-fn verify_ele_in_F17(ele: F17) -> bool {
-    let num = ele.0;
-    return num < 17u32;
+pub fn gen_verifying_key(circuit: &PlonkCircuit) -> VerifyingKey {
+    VerifyingKey::new(circuit, &get_srs())
 }
 
-pub fn verifier_process(proof: Plonk_Proof) {
-    let [q_l, q_r, q_o, q_m, q_c] = gen_selector_polys();
-    let q_l_box = commit_poly(q_l);
-    let q_r_box = commit_poly(q_r);
-    let q_o_box = commit_poly(q_o);
-    let q_m_box = commit_poly(q_m);
-    let q_c_box = commit_poly(q_c);
-
-    println!(
-        "{:?}, {:?}, {:?}, {:?}, {:?}",
-        q_l_box, q_r_box, q_o_box, q_m_box, q_c_box
-    );
+// Public inputs w_1..w_l, they occupy the first l rows of the a wire
+pub type PublicInputs<F = F17> = Vec<F>;
 
-    let [sigma1, sigma2, sigma3] = gen_sigma_polys();
-    let sigma1_box = commit_poly(sigma1);
-    let sigma2_box = commit_poly(sigma2);
-    let sigma3_box = commit_poly(sigma3);
-    println!(
-        "sigma commitments: {:?}, {:?},  {:?}",
-        sigma1_box, sigma2_box, sigma3_box
-    );
+pub fn verifier_process<E: Engine>(vk: &VerifyingKey<E>, proof: &Plonk_Proof<E>) -> bool {
+    verifier_process_with_public_inputs(vk, proof, &[])
+}
+
+pub fn verifier_process_with_public_inputs<E: Engine>(
+    vk: &VerifyingKey<E>,
+    proof: &Plonk_Proof<E>,
+    public_inputs: &[E::Fr],
+) -> bool {
+    match pairing_inputs(vk, proof, public_inputs) {
+        // Step 11: final pairing, e(lhs, [x]_2) e(-rhs, [1]_2) = 1
+        Some((lhs, rhs)) => E::pairing_product_is_one(&[(lhs, vk.x_g2), (rhs.neg(), vk.g2)]),
+        None => false,
+    }
+}
+
+// Steps 1 to 10, returning both sides of the final pairing equation
+// e(lhs, [x]_2) = e(rhs, [1]_2), or None when the proof is malformed
+fn pairing_inputs<E: Engine>(
+    vk: &VerifyingKey<E>,
+    proof: &Plonk_Proof<E>,
+    public_inputs: &[E::Fr],
+) -> Option<(E::G1, E::G1)> {
+    let VerifyingKey {
+        n,
+        ref coset_shifts,
+        ref gates,
+        q_k_box,
+        ref sigma_boxes,
+        table_box,
+        quotient_pieces,
+        ..
+    } = *vk;
 
-    let random_u = F17(4);
+    let [alpha, delta, epsilon, zeta, v] = [ALPHA, DELTA, EPSILON, ZETA, V].map(lift::<E::Fr>);
+    let (beta, gamma) = permutation_challenges::<E>(&proof.wire_boxes);
+    let random_u = lift::<E::Fr>(F17(4));
 
     let Plonk_Proof {
-        a_box,
-        b_box,
-        c_box,
+        ref wire_boxes,
+        z_box,
+        f_box,
+        h1_box,
+        h2_box,
+        z2_box,
+        ref t_boxes,
+        w_zeta_box,
+        w_zeta_omega_box,
+        ref wire_bars,
+        ref wire_omega_bars,
+        ref sigma_bars,
+        r_bar,
+        z_omega_bar,
+        f_bar,
+        h1_bar,
+        h2_bar,
+        table_bar,
+        h1_omega_bar,
+        table_omega_bar,
+        z2_omega_bar,
+    } = *proof;
+
+    // The proof must have one wire per sigma commitment, open all but the last
+    // sigma, and split t(x) into as many pieces as the circuit needs
+    let k = sigma_boxes.len();
+    if k == 0
+        || t_boxes.len() != quotient_pieces
+        || coset_shifts.len() + 1 != k
+        || wire_boxes.len() != k
+        || wire_bars.len() != k
+        || wire_omega_bars.len() != k
+        || sigma_bars.len() != k - 1
+    {
+        return None;
+    }
+    let shifts = std::iter::once(E::Fr::ONE)
+        .chain(coset_shifts.iter().copied())
+        .collect::<Vec<_>>();
+    let (sigma_last_box, sigma_opened_boxes) = sigma_boxes.split_last()?;
+
+    // Step 1: check all the commitments are valid ellptic curve elements of G1
+    let commitments = [
         z_box,
-        t_low_box,
-        t_mid_box,
-        t_high_box,
+        f_box,
+        h1_box,
+        h2_box,
+        z2_box,
         w_zeta_box,
         w_zeta_omega_box,
-        a_bar,
-        b_bar,
-        c_bar,
-        sigma1_bar,
-        sigma2_bar,
+    ];
+    if !commitments
+        .into_iter()
+        .chain(wire_boxes.iter().copied())
+        .chain(t_boxes.iter().copied())
+        .all(Group::is_valid)
+    {
+        return None;
+    }
+
+    // Step 2: check all the evaluations are valid field elements
+    let evaluations = [
         r_bar,
         z_omega_bar,
-    } = proof;
-
-    // Step 1: check all the commitments are valid ellptic curve elements
-    assert!(verify_point_on_ec_curve(a_box));
-    assert!(verify_point_on_ec_curve(b_box));
-    assert!(verify_point_on_ec_curve(c_box));
-    assert!(verify_point_on_ec_curve(z_box));
-    assert!(verify_point_on_ec_curve(t_low_box));
-    assert!(verify_point_on_ec_curve(t_mid_box));
-    assert!(verify_point_on_ec_curve(t_high_box));
-    assert!(verify_point_on_ec_curve(w_zeta_box));
-    assert!(verify_point_on_ec_curve(w_zeta_omega_box));
-
-    // Step 2: check all the commitments are valid ellptic curve elements
-    assert!(verify_ele_in_F17(a_bar));
-    assert!(verify_ele_in_F17(b_bar));
-    assert!(verify_ele_in_F17(c_bar));
-    assert!(verify_ele_in_F17(sigma1_bar));
-    assert!(verify_ele_in_F17(sigma2_bar));
-    assert!(verify_ele_in_F17(r_bar));
-    assert!(verify_ele_in_F17(z_omega_bar));
-
-    // Step 3: check w_{i /in public input set} is valid F17 elements
-    // skip, we have no public inputs for now
+        f_bar,
+        h1_bar,
+        h2_bar,
+        table_bar,
+        h1_omega_bar,
+        table_omega_bar,
+        z2_omega_bar,
+    ];
+    if !evaluations
+        .into_iter()
+        .chain(wire_bars.iter().copied())
+        .chain(wire_omega_bars.iter().copied())
+        .chain(sigma_bars.iter().copied())
+        .all(ScalarField::is_canonical)
+    {
+        return None;
+    }
+
+    // Step 3: check w_{i /in public input set} is valid field elements
+    if public_inputs.len() > n as usize
+        || !public_inputs.iter().copied().all(ScalarField::is_canonical)
+    {
+        return None;
+    }
 
     // Step 4: compute Zeta^n - 1, n is the # of gates, 4
     // log(n) computation here
     // let n = 4u32;
-    let z_h_eval = ZETA.pow(N).sub(F17::ONE);
+    let z_h_eval = zeta.pow(n.into()).sub(E::Fr::ONE);
     println!("z_h_eval:{:?}", z_h_eval);
 
+    // The denominators of steps 5 to 7, inverted together: n (zeta - 1), Z_H(zeta),
+    // then n (zeta - omega^i) for each public input. Any of them being zero means
+    // zeta landed on H, where the checks below say nothing.
+    let n_scalar = E::Fr::from_u64(n.into());
+    let omegas: Vec<E::Fr> = (0..public_inputs.len())
+        .map(|i| omega::<E::Fr>().pow(i as u64))
+        .collect();
+    let mut inverses = vec![n_scalar.mul(zeta.sub(E::Fr::ONE)), z_h_eval];
+    inverses.extend(
+        omegas
+            .iter()
+            .map(|&omega_i| n_scalar.mul(zeta.sub(omega_i))),
+    );
+    batch_inverse(&mut inverses).ok()?;
+    let z_h_eval_inv = inverses[1];
+
     // Step 5:
-    let l_1_eval = z_h_eval.mul(F17(N).mul(ZETA.sub(F17::ONE)).inv().unwrap());
+    let l_1_eval = z_h_eval.mul(inverses[0]);
     println!("l_1_eval:{:?}", l_1_eval);
 
-    // Step 6:
-    // skip for now, we have no public inputs
+    // Step 6: PI(zeta) = sum -w_i * L_i(zeta), L_i(zeta) = omega^i (zeta^n - 1) / (n (zeta - omega^i))
+    let pi_eval = public_inputs.iter().zip(&omegas).zip(&inverses[2..]).fold(
+        E::Fr::ZERO,
+        |acc, ((&w, &omega_i), &denominator_inv)| {
+            let l_i_eval = omega_i.mul(z_h_eval).mul(denominator_inv);
+            acc.sub(w.mul(l_i_eval))
+        },
+    );
 
     // Step 7: compute quotient polynomial evaluation
-    let a_bar_beta_sigma1_gamma = a_bar.add(BETA.mul(sigma1_bar)).add(GAMMA);
-    let b_bar_beta_sigma2_gamma = b_bar.add(BETA.mul(sigma2_bar)).add(GAMMA);
-    let c_bar_gamma_z_omega_alpha = c_bar.add(GAMMA).mul(z_omega_bar).mul(ALPHA);
+    let one_delta = E::Fr::ONE.add(delta);
+    let epsilon_one_delta = epsilon.mul(one_delta);
+    let lookup_z2_omega = epsilon_one_delta
+        .add(h1_bar)
+        .add(delta.mul(h2_bar))
+        .mul(epsilon_one_delta.add(h2_bar).add(delta.mul(h1_omega_bar)))
+        .mul(z2_omega_bar)
+        .mul(alpha.pow(4));
+    // prod_j (w_j_bar + beta sigma_j_bar + gamma) over every column but the last
+    let sigma_product = wire_bars
+        .iter()
+        .zip(sigma_bars)
+        .fold(E::Fr::ONE, |acc, (&wire_bar, &sigma_bar)| {
+            acc.mul(wire_bar.add(beta.mul(sigma_bar)).add(gamma))
+        });
+    let last_bar_gamma_z_omega_alpha = wire_bars[k - 1].add(gamma).mul(z_omega_bar).mul(alpha);
     let t_bar = r_bar
-        .sub(
-            a_bar_beta_sigma1_gamma
-                .mul(b_bar_beta_sigma2_gamma)
-                .mul(c_bar_gamma_z_omega_alpha),
-        )
-        .sub(l_1_eval.mul(ALPHA).mul(ALPHA))
-        .mul(z_h_eval.inv().unwrap());
+        .add(pi_eval)
+        .sub(sigma_product.mul(last_bar_gamma_z_omega_alpha))
+        .sub(l_1_eval.mul(alpha).mul(alpha))
+        .sub(lookup_z2_omega)
+        .sub(l_1_eval.mul(alpha.pow(5)))
+        .mul(z_h_eval_inv);
     println!("t_bar:{:?}", t_bar);
 
     // Step 8: the first part of batch polynomial commitment
-    let mut term1 = scalar_mult(a_bar.mul(b_bar).mul(V).0, q_m_box);
-    term1 = point_add(term1, scalar_mult(a_bar.mul(V).0, q_l_box));
-    term1 = point_add(term1, scalar_mult(b_bar.mul(V).0, q_r_box));
-    term1 = point_add(term1, scalar_mult(c_bar.mul(V).0, q_o_box));
-    term1 = point_add(term1, scalar_mult(V.0, q_c_box));
-
-    let term2 = scalar_mult(
-        a_bar
-            .add(BETA.mul(ZETA))
-            .add(GAMMA)
-            .mul(b_bar.add(BETA.mul(ZETA).mul(F17::K1)).add(GAMMA))
-            .mul(c_bar.add(BETA.mul(ZETA).mul(F17::K2)).add(GAMMA))
-            .mul(ALPHA)
-            .mul(V)
-            .add(l_1_eval.mul(ALPHA).mul(ALPHA).mul(V))
-            .add(random_u)
-            .0,
-        z_box,
-    );
-
-    let term3 = scalar_mult(
-        a_bar
-            .add(BETA.mul(sigma1_bar))
-            .add(GAMMA)
-            .mul(b_bar.add(BETA.mul(sigma2_bar)).add(GAMMA))
-            .mul(ALPHA)
-            .mul(V)
-            .mul(BETA)
-            .mul(z_omega_bar)
-            .0,
-        sigma3_box,
-    )
-    .point_neg();
-
-    let d_box = term1.add(term2).add(term3);
+    let z_scalar = wire_bars
+        .iter()
+        .zip(&shifts)
+        .fold(E::Fr::ONE, |acc, (&wire_bar, &shift)| {
+            acc.mul(wire_bar.add(beta.mul(zeta).mul(shift)).add(gamma))
+        })
+        .mul(alpha)
+        .mul(v)
+        .add(l_1_eval.mul(alpha).mul(alpha).mul(v))
+        .add(random_u);
+    let sigma_last_scalar = sigma_product
+        .mul(alpha)
+        .mul(v)
+        .mul(beta)
+        .mul(z_omega_bar)
+        .neg();
+    let q_k_scalar = wire_bars[0].sub(f_bar).mul(alpha.pow(3)).mul(v);
+    let z2_scalar = one_delta
+        .mul(epsilon.add(f_bar))
+        .mul(
+            epsilon_one_delta
+                .add(table_bar)
+                .add(delta.mul(table_omega_bar)),
+        )
+        .mul(alpha.pow(4))
+        .add(l_1_eval.mul(alpha.pow(5)))
+        .mul(v)
+        .add(random_u.mul(v));
+    // every gate selector, scaled by its expression at the openings
+    let expressions = gates
+        .iter()
+        .map(|gate| &gate.expression)
+        .collect::<Vec<_>>();
+    let gate_scalars = gate_scalars(&expressions, wire_bars, wire_omega_bars);
+    let mut d_points = gates
+        .iter()
+        .map(|gate| gate.selector_box)
+        .collect::<Vec<_>>();
+    let mut d_scalars = gate_scalars
+        .into_iter()
+        .map(|scalar| scalar.mul(v))
+        .collect::<Vec<_>>();
+    d_points.extend([z_box, *sigma_last_box, q_k_box, z2_box]);
+    d_scalars.extend([z_scalar, sigma_last_scalar, q_k_scalar, z2_scalar]);
+    let d_box = msm(&d_points, &d_scalars);
     println!("d_box:{:?}", d_box);
 
-    // q_m_box. a_bar
-    //     .mul(b_bar)
-    //     .mul()
-    //     .mul(V)
-    //     .add(a_bar.mul(V).mul(q_l_box))
-    //     .add(b_bar.mul(V).mul(q_r_box))
-    //     .add(c_bar.mul(V).mul(q_o_box))
-    //     .add(V.mul(q_c_box));
-
-    // Step 9: compute full batched polynomial commitment
-    let f_box = t_low_box
-        .add(t_mid_box.mul(ZETA.pow(N + 2)))
-        .add(t_high_box.mul(ZETA.pow(2 * N + 4)))
-        .add(d_box)
-        .add(a_box.mul(V.pow(2)))
-        .add(b_box.mul(V.pow(3)))
-        .add(c_box.mul(V.pow(4)))
-        .add(sigma1_box.mul(V.pow(5)))
-        .add(sigma2_box.mul(V.pow(6)));
+    // Step 9: compute full batched polynomial commitment. The wires are opened at
+    // zeta with v^2.. and at zeta omega with u v^4.., the opened sigmas follow the
+    // wires, then f, h1, h2 and t.
+    let mut f_points = t_boxes.clone();
+    let mut f_scalars = (0..quotient_pieces as u64)
+        .map(|i| zeta.pow(i * (u64::from(n) + 2)))
+        .collect::<Vec<_>>();
+    for (j, &wire_box) in wire_boxes.iter().enumerate() {
+        let j = j as u64;
+        f_points.push(wire_box);
+        f_scalars.push(v.pow(j + 2).add(random_u.mul(v.pow(j + 4))));
+    }
+    let lookup_power = 2 * k as u64 + 1;
+    for (j, &sigma_box) in sigma_opened_boxes.iter().enumerate() {
+        f_points.push(sigma_box);
+        f_scalars.push(v.pow(k as u64 + 2 + j as u64));
+    }
+    f_points.extend([f_box, h1_box, h2_box, table_box]);
+    f_scalars.extend([
+        v.pow(lookup_power),
+        v.pow(lookup_power + 1).add(random_u.mul(v.pow(2))),
+        v.pow(lookup_power + 2),
+        v.pow(lookup_power + 3).add(random_u.mul(v.pow(3))),
+    ]);
+    let f_box = msm(&f_points, &f_scalars).add(d_box);
     println!("f_box:{:?}", f_box);
 
     // Step 10: compute group encoded batch evaluation
-    let point_primitive = Point::Point {
-        x: F101(1),
-        y: F101(2),
-    };
-    let e_coeff = t_bar
-        .add(V.mul(r_bar))
-        .add(V.pow(2).add(a_bar))
-        .add(V.pow(3).add(b_bar))
-        .add(V.pow(4).add(c_bar))
-        .add(V.pow(5).add(sigma1_bar))
-        .add(V.pow(6).add(sigma2_bar))
-        .add(random_u.mul(z_omega_bar));
-    let e_box = point_primitive.mul(e_coeff);
+    let at_zeta = wire_bars
+        .iter()
+        .chain(sigma_bars)
+        .chain(&[f_bar, h1_bar, h2_bar, table_bar])
+        .zip(2..)
+        .fold(t_bar.add(v.mul(r_bar)), |acc, (&bar, power)| {
+            acc.add(v.pow(power).mul(bar))
+        });
+    let at_zeta_omega = wire_omega_bars.iter().zip(4..).fold(
+        z_omega_bar
+            .add(v.mul(z2_omega_bar))
+            .add(v.pow(2).mul(h1_omega_bar))
+            .add(v.pow(3).mul(table_omega_bar)),
+        |acc, (&bar, power)| acc.add(v.pow(power).mul(bar)),
+    );
+    let e_coeff = at_zeta.add(random_u.mul(at_zeta_omega));
+    let e_box = E::g1_table().mul(e_coeff);
     println!("e_box:{:?}", e_box);
 
-    // Step 11: final pairing
+    // e([W_zeta] + u[W_zeta_omega], [x]_2) = e(zeta[W_zeta] + u*zeta*omega[W_zeta_omega] + [F] - [E], [1]_2)
+    let lhs = w_zeta_box.add(w_zeta_omega_box.mul(random_u));
+    let rhs = msm(
+        &[w_zeta_box, w_zeta_omega_box, f_box, e_box],
+        &[
+            zeta,
+            random_u.mul(zeta).mul(omega()),
+            E::Fr::ONE,
+            E::Fr::ONE.neg(),
+        ],
+    );
+    Some((lhs, rhs))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchVerifyError {
+    // indices of the proofs that fail on their own
+    pub invalid: Vec<usize>,
+}
+
+impl fmt::Display for BatchVerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "proofs {:?} failed verification", self.invalid)
+    }
+}
+
+impl std::error::Error for BatchVerifyError {}
+
+// Verifies every proof with one multi-pairing: each proof contributes
+// e(r_j * lhs_j, [x]_2) = e(r_j * rhs_j, [1]_2) with a random non-zero r_j, and the
+// sums are checked together. A failing batch is re-checked proof by proof.
+pub fn verify_batch<E: Engine>(
+    vk: &VerifyingKey<E>,
+    proofs: &[(Plonk_Proof<E>, PublicInputs<E::Fr>)],
+) -> Result<(), BatchVerifyError> {
+    let prepared: Vec<Option<(E::G1, E::G1)>> = proofs
+        .iter()
+        .map(|(proof, public_inputs)| pairing_inputs(vk, proof, public_inputs))
+        .collect();
+
+    // the r_j must not be predictable before every proof is fixed
+    let mut fs = FiatShamir::new(b"plonk-batch-verify");
+    for (proof, public_inputs) in proofs {
+        fs.absorb_bytes(&[proof.wire_boxes.len() as u8, proof.t_boxes.len() as u8]);
+        for point in proof.commitments() {
+            fs.absorb(&point);
+        }
+        for scalar in proof.evaluations().iter().chain(public_inputs) {
+            fs.absorb(scalar);
+        }
+    }
+
+    let (lhs_points, rhs_points): (Vec<E::G1>, Vec<E::G1>) =
+        prepared.iter().flatten().copied().unzip();
+    let weights: Vec<E::Fr> = (0..lhs_points.len())
+        .map(|_| {
+            let mut r = fs.challenge();
+            while r == F17::ZERO {
+                r = fs.challenge();
+            }
+            lift(r)
+        })
+        .collect();
+    let lhs = msm(&lhs_points, &weights);
+    let rhs = msm(&rhs_points, &weights);
+
+    let holds =
+        |lhs: E::G1, rhs: E::G1| E::pairing_product_is_one(&[(lhs, vk.x_g2), (rhs.neg(), vk.g2)]);
+    if prepared.iter().all(Option::is_some) && holds(lhs, rhs) {
+        return Ok(());
+    }
+
+    let invalid: Vec<usize> = prepared
+        .iter()
+        .enumerate()
+        .filter(|(_, inputs)| match inputs {
+            Some((lhs, rhs)) => !holds(*lhs, *rhs),
+            None => true,
+        })
+        .map(|(i, _)| i)
+        .collect();
+    Err(BatchVerifyError { invalid })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::round5::gen_round5_result;
+    use crate::{
+        custom_gate::{Column, CustomGate, Expression},
+        field::{Point, F101, G1},
+        lookup::LookupError,
+        plonk_circuit::{ProverError, ProvingKey},
+        prescribed_permutation::{coset_shifts, sigma_values},
+        pythagorean_circuit::{gen_plonk_circuit, gen_proving_key, Q_K, TABLE},
+        pythagorean_transcript::{gen_transcript, gen_transcript_for, wire_columns, Transcript},
+        round5::gen_round5_result,
+    };
 
     use super::*;
 
+    fn prove(transcript: Transcript) -> Plonk_Proof {
+        gen_round5_result(&gen_proving_key(), &wire_columns(transcript)).unwrap()
+    }
+
+    fn verifying_key() -> VerifyingKey {
+        gen_verifying_key(&gen_plonk_circuit())
+    }
+
     #[test]
     fn verifier_process_test() {
-        let proof = gen_round5_result();
-        verifier_process(proof);
+        let proof = prove(gen_transcript());
+        assert!(verifier_process(&verifying_key(), &proof));
+    }
+
+    #[test]
+    fn verifier_custom_witness_test() {
+        // 5^2 + 12^2 = 13^2
+        let transcript = gen_transcript_for(F17::new(5), F17::new(12), F17::new(13)).unwrap();
+        let proof = prove(transcript);
+        assert!(verifier_process(&verifying_key(), &proof));
+    }
+
+    #[test]
+    fn verifier_rejects_tampered_proof_test() {
+        let vk = verifying_key();
+        let proof = prove(gen_transcript());
+
+        let mut tampered = proof.clone();
+        tampered.wire_bars[0] = proof.wire_bars[0].add(F17::ONE);
+        assert!(!verifier_process(&vk, &tampered));
+
+        let mut tampered = proof.clone();
+        tampered.w_zeta_box = proof.w_zeta_box.add(G1);
+        assert!(!verifier_process(&vk, &tampered));
+
+        // the next-row opening of c
+        let mut tampered = proof.clone();
+        tampered.wire_omega_bars[2] = proof.wire_omega_bars[2].add(F17::ONE);
+        assert!(!verifier_process(&vk, &tampered));
+
+        // a verifier that does not know the q_O gate
+        let mut wrong_vk = vk.clone();
+        wrong_vk.gates.remove(2);
+        assert!(!verifier_process(&wrong_vk, &proof));
+
+        let mut tampered = proof.clone();
+        tampered.wire_boxes[2] = Point::Point {
+            x: F101(1),
+            y: F101(3),
+        };
+        assert!(!verifier_process(&vk, &tampered));
+    }
+
+    #[test]
+    fn verifier_rejects_wrong_wire_count_test() {
+        let vk = verifying_key();
+        let proof = prove(gen_transcript());
+
+        // a proof with a wire column missing
+        let mut tampered = proof.clone();
+        tampered.wire_boxes.pop();
+        tampered.wire_bars.pop();
+        tampered.wire_omega_bars.pop();
+        tampered.sigma_bars.pop();
+        assert!(!verifier_process(&vk, &tampered));
+
+        // the opened sigmas must be one fewer than the wires
+        let mut tampered = proof.clone();
+        tampered.sigma_bars.push(F17::ZERO);
+        assert!(!verifier_process(&vk, &tampered));
+
+        // a key for a fourth column the proof does not have
+        let mut wrong_vk = vk.clone();
+        wrong_vk.coset_shifts.push(coset_shifts(4).unwrap()[3]);
+        wrong_vk.sigma_boxes.push(G1);
+        assert!(!verifier_process(&wrong_vk, &proof));
+    }
+
+    fn prove_circuit(circuit: &PlonkCircuit, witness: &[[F17; 4]]) -> Plonk_Proof {
+        let proof = gen_round5_result(&ProvingKey::new(circuit.clone()), witness).unwrap();
+        assert_eq!(proof.wire_boxes.len(), circuit.num_wires);
+        assert_eq!(proof.t_boxes.len(), circuit.quotient_pieces());
+        proof
+    }
+
+    #[test]
+    fn verifier_other_wire_counts_test() {
+        let [q_k, table] = [Q_K, TABLE].map(|column| column.to_vec());
+        let circuit = |num_wires, gate, cycles: &[&[(usize, usize)]]| {
+            let sigmas = sigma_values(&coset_shifts(num_wires).unwrap(), cycles);
+            PlonkCircuit::new(num_wires, vec![gate], sigmas, q_k.clone(), table.clone()).unwrap()
+        };
+
+        // k = 2: b = a^2 on the first three rows, and each square is the next a
+        let square = CustomGate::new(
+            "square",
+            &[F17::ONE, F17::ONE, F17::ONE, F17::ZERO],
+            Expression::wire(Column(0))
+                .mul(Expression::wire(Column(0)))
+                .sub(Expression::wire(Column(1))),
+        );
+        let chain = circuit(
+            2,
+            square.clone(),
+            &[&[(1, 0), (0, 1)], &[(1, 1), (0, 2)], &[(1, 2), (0, 3)]],
+        );
+        assert_eq!(chain.quotient_pieces(), 3);
+        let vk = gen_verifying_key(&chain);
+        let witness = [[3, 9, 13, 16], [9, 13, 16, 0]].map(|column| column.map(F17));
+        assert!(verifier_process(&vk, &prove_circuit(&chain, &witness)));
+        // 2^2 = 4 but the next a is 2, proved without the first copy constraint
+        let loose = circuit(2, square, &[&[(1, 1), (0, 2)], &[(1, 2), (0, 3)]]);
+        let witness = [[2, 2, 4, 16], [4, 4, 16, 0]].map(|column| column.map(F17));
+        let proof = prove_circuit(&loose, &witness);
+        assert!(verifier_process(&gen_verifying_key(&loose), &proof));
+        assert!(!verifier_process(&vk, &proof));
+
+        // k = 4: d = a + b + c on every row, and each d is the next a
+        let sum = CustomGate::new(
+            "sum",
+            &[F17::ONE; 4],
+            Expression::wire(Column(0))
+                .add(Expression::wire(Column(1)))
+                .add(Expression::wire(Column(2)))
+                .sub(Expression::wire(Column(3))),
+        );
+        let chain = circuit(
+            4,
+            sum.clone(),
+            &[&[(3, 0), (0, 1)], &[(3, 1), (0, 2)], &[(3, 2), (0, 3)]],
+        );
+        // z(x) times four wires: 6 + 4 * 5
+        assert_eq!(chain.numerator_degree(), 26);
+        assert_eq!(chain.quotient_pieces(), 4);
+        let vk = gen_verifying_key(&chain);
+        assert_eq!(vk.coset_shifts.len(), 3);
+        assert_eq!(vk.sigma_boxes.len(), 4);
+        let witness =
+            [[1, 3, 6, 9], [1, 1, 1, 1], [1, 2, 2, 1], [3, 6, 9, 11]].map(|column| column.map(F17));
+        assert!(verifier_process(&vk, &prove_circuit(&chain, &witness)));
+        // a proof of the three-wire circuit
+        assert!(!verifier_process(&vk, &prove(gen_transcript())));
+        // the third d is not the last a, proved without that copy constraint
+        let loose = circuit(4, sum, &[&[(3, 0), (0, 1)], &[(3, 1), (0, 2)]]);
+        let witness = [[1, 3, 6, 10], [1, 1, 1, 1], [1, 2, 2, 1], [3, 6, 9, 12]]
+            .map(|column| column.map(F17));
+        let proof = prove_circuit(&loose, &witness);
+        assert!(verifier_process(&gen_verifying_key(&loose), &proof));
+        assert!(!verifier_process(&vk, &proof));
+    }
+
+    #[test]
+    fn verifier_custom_gate_circuit_test() {
+        // Four wires and three gates besides the arithmetic ones:
+        // row 0: a^5 = b, an S-box, and b_0 is copied to a_1
+        // row 1: a + b = c(omega x), the sum goes to the next row
+        // rows 2, 3: a + b + c = d, and d_2 is copied to a_3
+        let [a, b, c, d] = [0, 1, 2, 3].map(Column);
+        let sbox = |exponent| {
+            (1..exponent)
+                .fold(Expression::wire(a), |acc, _| acc.mul(Expression::wire(a)))
+                .sub(Expression::wire(b))
+        };
+        let gates = |exponent| {
+            vec![
+                CustomGate::new(
+                    "sbox",
+                    &[F17::ONE, F17::ZERO, F17::ZERO, F17::ZERO],
+                    sbox(exponent),
+                ),
+                CustomGate::new(
+                    "carry",
+                    &[F17::ZERO, F17::ONE, F17::ZERO, F17::ZERO],
+                    Expression::wire(a)
+                        .add(Expression::wire(b))
+                        .sub(Expression::next(c)),
+                ),
+                CustomGate::new(
+                    "sum",
+                    &[F17::ZERO, F17::ZERO, F17::ONE, F17::ONE],
+                    Expression::wire(a)
+                        .add(Expression::wire(b))
+                        .add(Expression::wire(c))
+                        .sub(Expression::wire(d)),
+                ),
+            ]
+        };
+        let sigmas = sigma_values(
+            &coset_shifts(4).unwrap(),
+            &[&[(1, 0), (0, 1)], &[(3, 2), (0, 3)]],
+        );
+        let circuit = |exponent| {
+            PlonkCircuit::new(
+                4,
+                gates(exponent),
+                sigmas.clone(),
+                Q_K.to_vec(),
+                TABLE.to_vec(),
+            )
+            .unwrap()
+        };
+        let sbox5 = circuit(5);
+        // the x^5 term, 3 + 5 * 5, is past the permutation term
+        assert_eq!(sbox5.numerator_degree(), 28);
+        assert_eq!(sbox5.quotient_pieces(), 5);
+
+        // 12^5 = 3, 3 + 1 = 4, 2 + 1 + 4 = 7 and 7 + 9 + 2 = 1
+        let witness =
+            [[12, 3, 2, 7], [3, 1, 1, 9], [1, 3, 4, 2], [4, 2, 7, 1]].map(|column| column.map(F17));
+        let vk = gen_verifying_key(&sbox5);
+        let proof = prove_circuit(&sbox5, &witness);
+        assert!(verifier_process(&vk, &proof));
+
+        // a verifier expecting a^3 = b on row 0
+        assert!(!verifier_process(&gen_verifying_key(&circuit(3)), &proof));
+        // the carry reads c at zeta omega
+        let mut tampered = proof.clone();
+        tampered.wire_omega_bars[2] = proof.wire_omega_bars[2].add(F17::ONE);
+        assert!(!verifier_process(&vk, &tampered));
+        // the sum reads the fourth wire
+        let mut tampered = proof.clone();
+        tampered.wire_bars[3] = proof.wire_bars[3].add(F17::ONE);
+        assert!(!verifier_process(&vk, &tampered));
+        let mut tampered = proof.clone();
+        tampered.t_boxes[4] = proof.t_boxes[4].add(G1);
+        assert!(!verifier_process(&vk, &tampered));
+    }
+
+    #[test]
+    fn verifier_rejects_tampered_lookup_test() {
+        let vk = verifying_key();
+        let proof = prove(gen_transcript());
+
+        let mut tampered = proof.clone();
+        tampered.z2_omega_bar = proof.z2_omega_bar.add(F17::ONE);
+        assert!(!verifier_process(&vk, &tampered));
+
+        let mut tampered = proof.clone();
+        tampered.f_bar = proof.f_bar.add(F17::ONE);
+        assert!(!verifier_process(&vk, &tampered));
+
+        let mut tampered = proof.clone();
+        tampered.h1_box = proof.h1_box.add(G1);
+        assert!(!verifier_process(&vk, &tampered));
+
+        // a table with a different order
+        let mut wrong_vk = vk.clone();
+        wrong_vk.table_box = vk.table_box.add(G1);
+        assert!(!verifier_process(&wrong_vk, &proof));
+    }
+
+    // c = 4a + b on every row, with a range checked against the two-bit table
+    // on the rows where q_K = 1. Row 3 has no lookup, so its a is unrestricted.
+    fn limb_circuit(table: [F17; 4]) -> PlonkCircuit {
+        let limbs = CustomGate::new(
+            "limbs",
+            &[F17::ONE; 4],
+            Expression::constant(F17(4))
+                .mul(Expression::wire(Column::A))
+                .add(Expression::wire(Column::B))
+                .sub(Expression::wire(Column::C)),
+        );
+        let sigmas = sigma_values(&coset_shifts(3).unwrap(), &[]);
+        let q_k = [F17::ONE, F17::ONE, F17::ONE, F17::ZERO];
+        PlonkCircuit::new(3, vec![limbs], sigmas, q_k.to_vec(), table.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn verifier_lookup_circuit_test() {
+        let circuit = limb_circuit(TABLE);
+        let vk = gen_verifying_key(&circuit);
+        let witness =
+            [[3, 1, 2, 5], [1, 0, 3, 2], [13, 4, 11, 22]].map(|column| column.map(F17::new));
+        let proof = prove_circuit(&circuit, &witness);
+        assert!(verifier_process(&vk, &proof));
+
+        // a_0 = 3 is looked up, so the proof does not hold for a table without it
+        let other = limb_circuit([0, 1, 2, 5].map(F17));
+        assert!(!verifier_process(&gen_verifying_key(&other), &proof));
+    }
+
+    #[test]
+    fn verifier_rejects_value_outside_table_test() {
+        // a_0 = 5 is not a two-bit value, but it is in the table the proof uses
+        let witness =
+            [[5, 1, 2, 3], [1, 0, 3, 2], [21, 4, 11, 14]].map(|column| column.map(F17::new));
+        let other = limb_circuit([0, 1, 2, 5].map(F17));
+        let proof = prove_circuit(&other, &witness);
+        assert!(verifier_process(&gen_verifying_key(&other), &proof));
+        assert!(!verifier_process(
+            &gen_verifying_key(&limb_circuit(TABLE)),
+            &proof
+        ));
+    }
+
+    #[test]
+    fn prover_rejects_value_outside_table_test() {
+        let witness =
+            [[5, 1, 2, 3], [1, 0, 3, 2], [21, 4, 11, 14]].map(|column| column.map(F17::new));
+        let pk = ProvingKey::new(limb_circuit(TABLE));
+        assert_eq!(
+            gen_round5_result(&pk, &witness),
+            Err(ProverError::LookupValue(LookupError {
+                row: 0,
+                value: F17(5)
+            }))
+        );
+    }
+
+    #[test]
+    fn fixed_base_tables_test() {
+        use crate::{fixed_base::table_muls, kzg, polynomial::Polynomial};
+
+        // SRS::new takes every power of tau from the G1 table
+        let muls = table_muls();
+        let srs = SRS::<Toy>::new(6, F17(2));
+        assert_eq!(table_muls() - muls, 7);
+
+        // ProvingKey::commit goes through the SRS tables, one lookup per coefficient
+        let pk = ProvingKey::with_srs(gen_plonk_circuit(), srs.clone());
+        let poly = Polynomial {
+            coeffs: vec![F17(1), F17(2), F17(3)],
+        };
+        let muls = table_muls();
+        assert_eq!(pk.commit(&poly), commit(&srs, &poly).unwrap());
+        assert_eq!(table_muls() - muls, 3);
+
+        // [E] in step 10
+        let vk = VerifyingKey::new(&gen_plonk_circuit(), &srs);
+        let proof = gen_round5_result(&pk, &wire_columns(gen_transcript())).unwrap();
+        let muls = table_muls();
+        assert!(verifier_process(&vk, &proof));
+        assert_eq!(table_muls() - muls, 1);
+
+        // [v]_1 in a single KZG opening
+        let commitment = pk.commit(&poly);
+        let (value, witness) = kzg::open(&srs, &poly, F17(5)).unwrap();
+        let muls = table_muls();
+        assert!(kzg::verify(&srs, commitment, F17(5), value, witness));
+        assert_eq!(table_muls() - muls, 1);
+    }
+
+    #[test]
+    fn verifier_rejects_small_order_points_test() {
+        let vk = verifying_key();
+        let proof = prove(gen_transcript());
+        // (48, 0) satisfies y^2 = x^3 + 3 but has order 2
+        let small_order = Point::Point {
+            x: F101(48),
+            y: F101(0),
+        };
+        assert!(small_order.is_on_curve());
+
+        let mut tampered = proof.clone();
+        tampered.t_boxes[0] = proof.t_boxes[0].add(small_order);
+        assert!(tampered.t_boxes[0].is_on_curve());
+        assert!(!verifier_process(&vk, &tampered));
+
+        let mut tampered = proof.clone();
+        tampered.w_zeta_omega_box = small_order;
+        assert!(!verifier_process(&vk, &tampered));
+    }
+
+    #[test]
+    fn verifier_public_inputs_test() {
+        let vk = verifying_key();
+        let proof = prove(gen_transcript());
+        assert!(verifier_process_with_public_inputs(&vk, &proof, &[]));
+        // the Pythagorean circuit has no public inputs, so any non-zero claim is rejected
+        assert!(!verifier_process_with_public_inputs(&vk, &proof, &[F17(3)]));
+        assert!(verifier_process_with_public_inputs(
+            &vk,
+            &proof,
+            &[F17::ZERO]
+        ));
+        assert!(!verifier_process_with_public_inputs(
+            &vk,
+            &proof,
+            &[F17::ZERO; 5]
+        ));
+    }
+
+    #[test]
+    fn verifier_every_triple_test() {
+        // beta and gamma hit a zero factor for some of these, and the prover
+        // re-blinds until they do not
+        let vk = verifying_key();
+        let mut proved = 0;
+        for d in 0..17 {
+            for e in 0..17 {
+                for f in 0..17 {
+                    let Ok(transcript) = gen_transcript_for(F17(d), F17(e), F17(f)) else {
+                        continue;
+                    };
+                    assert!(verifier_process(&vk, &prove(transcript)), "{d} {e} {f}");
+                    proved += 1;
+                }
+            }
+        }
+        assert_eq!(proved, 289);
+    }
+
+    #[test]
+    fn verify_batch_test() {
+        let vk = verifying_key();
+        let triples = [(3, 4, 5), (5, 12, 13), (8, 15, 17), (20, 21, 29), (2, 3, 8)];
+        let proofs: Vec<(Plonk_Proof, PublicInputs)> = triples
+            .iter()
+            .map(|&(d, e, f)| {
+                let transcript = gen_transcript_for(F17::new(d), F17::new(e), F17::new(f)).unwrap();
+                (prove(transcript), vec![])
+            })
+            .collect();
+        assert_eq!(verify_batch(&vk, &proofs), Ok(()));
+        assert_eq!(verify_batch(&vk, &[]), Ok(()));
+
+        // one bad proof at a time, two can cancel in a group of order 17
+        let mut tampered = proofs.clone();
+        tampered[1].0.w_zeta_omega_box = tampered[1].0.w_zeta_omega_box.add(G1);
+        assert_eq!(
+            verify_batch(&vk, &tampered),
+            Err(BatchVerifyError { invalid: vec![1] })
+        );
+        let mut tampered = proofs.clone();
+        tampered[3].1 = vec![F17(1)];
+        assert_eq!(
+            verify_batch(&vk, &tampered),
+            Err(BatchVerifyError { invalid: vec![3] })
+        );
+
+        // malformed proofs are reported without taking part in the pairing
+        let mut tampered = proofs.clone();
+        tampered[4].0.z_box = Point::Point {
+            x: F101(1),
+            y: F101(3),
+        };
+        assert_eq!(
+            verify_batch(&vk, &tampered),
+            Err(BatchVerifyError { invalid: vec![4] })
+        );
+    }
+
+    #[cfg(feature = "bn254")]
+    #[test]
+    fn verifier_bn254_test() {
+        use crate::{
+            bn254::{curve::G1Point, engine::Bn254, field::Fr},
+            custom_gate::arithmetic_gates,
+            field::lift,
+            pythagorean_circuit::{Q_C, Q_L, Q_M, Q_R},
+            srs::SRS,
+        };
+
+        // the Pythagorean circuit and its copy cycles, read over Fr
+        let lift_all = |column: &[F17]| column.iter().map(|&x| lift::<Fr>(x)).collect::<Vec<_>>();
+        // Q_O is -1 = 16 in F17, which lifts to 16
+        let q_o = vec![Fr::ONE.neg(); 4];
+        let cycles: [&[(usize, usize)]; 6] = [
+            &[(0, 0), (1, 0)],
+            &[(0, 1), (1, 1)],
+            &[(0, 2), (1, 2)],
+            &[(0, 3), (2, 0)],
+            &[(1, 3), (2, 1)],
+            &[(2, 2), (2, 3)],
+        ];
+        let circuit = PlonkCircuit::new(
+            3,
+            arithmetic_gates(
+                &lift_all(&Q_L),
+                &lift_all(&Q_R),
+                &q_o,
+                &lift_all(&Q_M),
+                &lift_all(&Q_C),
+            ),
+            sigma_values(&coset_shifts::<Fr>(3).unwrap(), &cycles),
+            lift_all(&Q_K),
+            lift_all(&TABLE),
+        )
+        .unwrap();
+
+        let srs = SRS::<Bn254>::new(6, Fr::from_u64(0x1234_5678_9abc_def0));
+        let vk = VerifyingKey::new(&circuit, &srs);
+        let pk = ProvingKey::with_srs(circuit, srs);
+
+        // 3^2 + 4^2 = 5^2 without the reduction mod 17
+        let witness =
+            [[3, 4, 5, 9], [3, 4, 5, 16], [9, 16, 25, 25]].map(|column| column.map(Fr::from_u64));
+        let proof = gen_round5_result(&pk, &witness).unwrap();
+        assert!(verifier_process(&vk, &proof));
+
+        let mut tampered = proof.clone();
+        tampered.wire_bars[0] = proof.wire_bars[0].add(Fr::ONE);
+        assert!(!verifier_process(&vk, &tampered));
+
+        let mut tampered = proof.clone();
+        tampered.w_zeta_box = proof.w_zeta_box.add(G1Point::generator());
+        assert!(!verifier_process(&vk, &tampered));
+
+        // the batch goes through the same engine
+        let batch = vec![(proof.clone(), vec![]), (tampered, vec![]), (proof, vec![])];
+        assert_eq!(verify_batch(&vk, &batch[..1]), Ok(()));
+        assert_eq!(
+            verify_batch(&vk, &batch),
+            Err(BatchVerifyError { invalid: vec![1] })
+        );
     }
 }
@@ -0,0 +1,241 @@
+// Witness generation: a circuit is a list of gates over numbered variables.
+// The first `num_inputs` variables are supplied by the user, every other
+// variable is assigned by evaluating the gates in order.
+// Gate i contributes one row to the transcript: a_i = left, b_i = right, c_i = out.
+
+use std::fmt;
+
+use crate::field::F17;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Variable(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GateOp {
+    Add,
+    Mul,
+}
+
+impl GateOp {
+    pub fn eval(self, left: F17, right: F17) -> F17 {
+        match self {
+            GateOp::Add => left.add(right),
+            GateOp::Mul => left.mul(right),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Gate {
+    pub op: GateOp,
+    pub left: Variable,
+    pub right: Variable,
+    pub out: Variable,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Circuit {
+    pub num_inputs: usize,
+    pub num_variables: usize,
+    pub gates: Vec<Gate>,
+}
+
+impl Circuit {
+    pub fn new(num_inputs: usize) -> Self {
+        Circuit {
+            num_inputs,
+            num_variables: num_inputs,
+            gates: Vec::new(),
+        }
+    }
+
+    pub fn input(&self, i: usize) -> Variable {
+        assert!(i < self.num_inputs, "input index out of range");
+        Variable(i)
+    }
+
+    pub fn new_variable(&mut self) -> Variable {
+        self.num_variables += 1;
+        Variable(self.num_variables - 1)
+    }
+
+    // out = left <op> right, where `out` may already be the output of an earlier gate.
+    // Reusing an output is how a circuit asserts that two values are equal.
+    pub fn gate(&mut self, op: GateOp, left: Variable, right: Variable, out: Variable) {
+        self.gates.push(Gate {
+            op,
+            left,
+            right,
+            out,
+        });
+    }
+
+    pub fn add(&mut self, left: Variable, right: Variable) -> Variable {
+        let out = self.new_variable();
+        self.gate(GateOp::Add, left, right, out);
+        out
+    }
+
+    pub fn mul(&mut self, left: Variable, right: Variable) -> Variable {
+        let out = self.new_variable();
+        self.gate(GateOp::Mul, left, right, out);
+        out
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Witness {
+    pub a: Vec<F17>,
+    pub b: Vec<F17>,
+    pub c: Vec<F17>,
+    pub values: Vec<F17>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WitnessError {
+    WrongInputCount {
+        expected: usize,
+        got: usize,
+    },
+    UnassignedVariable {
+        gate: usize,
+        variable: Variable,
+    },
+    Unsatisfied {
+        gate: usize,
+        expected: F17,
+        got: F17,
+    },
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::WrongInputCount { expected, got } => {
+                write!(f, "expected {} inputs, got {}", expected, got)
+            }
+            WitnessError::UnassignedVariable { gate, variable } => {
+                write!(f, "gate {} reads unassigned variable {}", gate, variable.0)
+            }
+            WitnessError::Unsatisfied {
+                gate,
+                expected,
+                got,
+            } => write!(
+                f,
+                "gate {} is unsatisfied: output is {} but evaluates to {}",
+                gate, expected.0, got.0
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WitnessError {}
+
+pub fn generate_witness(circuit: &Circuit, inputs: &[F17]) -> Result<Witness, WitnessError> {
+    if inputs.len() != circuit.num_inputs {
+        return Err(WitnessError::WrongInputCount {
+            expected: circuit.num_inputs,
+            got: inputs.len(),
+        });
+    }
+
+    let mut values: Vec<Option<F17>> = vec![None; circuit.num_variables];
+    for (i, &input) in inputs.iter().enumerate() {
+        values[i] = Some(F17::new(input.0));
+    }
+
+    let mut a = Vec::with_capacity(circuit.gates.len());
+    let mut b = Vec::with_capacity(circuit.gates.len());
+    let mut c = Vec::with_capacity(circuit.gates.len());
+    for (i, gate) in circuit.gates.iter().enumerate() {
+        let read = |variable: Variable| {
+            values[variable.0].ok_or(WitnessError::UnassignedVariable { gate: i, variable })
+        };
+        let left = read(gate.left)?;
+        let right = read(gate.right)?;
+        let out = gate.op.eval(left, right);
+
+        // an output that is already assigned is a copy constraint, check it holds
+        match values[gate.out.0] {
+            Some(expected) if expected != out => {
+                return Err(WitnessError::Unsatisfied {
+                    gate: i,
+                    expected,
+                    got: out,
+                });
+            }
+            _ => values[gate.out.0] = Some(out),
+        }
+
+        a.push(left);
+        b.push(right);
+        c.push(out);
+    }
+
+    Ok(Witness {
+        a,
+        b,
+        c,
+        values: values.into_iter().map(|v| v.unwrap_or(F17::ZERO)).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_witness_test() {
+        // x * y + x
+        let mut circuit = Circuit::new(2);
+        let x = circuit.input(0);
+        let y = circuit.input(1);
+        let xy = circuit.mul(x, y);
+        circuit.add(xy, x);
+
+        let witness = generate_witness(&circuit, &[F17::new(5), F17::new(6)]).unwrap();
+        assert_eq!(witness.a, vec![F17(5), F17(13)]);
+        assert_eq!(witness.b, vec![F17(6), F17(5)]);
+        // 30 = 13 mod 17, 13 + 5 = 1 mod 17
+        assert_eq!(witness.c, vec![F17(13), F17(1)]);
+    }
+
+    #[test]
+    fn generate_witness_errors_test() {
+        let mut circuit = Circuit::new(2);
+        let x = circuit.input(0);
+        let y = circuit.input(1);
+        // assert x + x = y
+        circuit.gate(GateOp::Add, x, x, y);
+
+        assert_eq!(
+            generate_witness(&circuit, &[F17::new(1)]),
+            Err(WitnessError::WrongInputCount {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert!(generate_witness(&circuit, &[F17::new(3), F17::new(6)]).is_ok());
+        assert_eq!(
+            generate_witness(&circuit, &[F17::new(3), F17::new(7)]),
+            Err(WitnessError::Unsatisfied {
+                gate: 0,
+                expected: F17(7),
+                got: F17(6)
+            })
+        );
+
+        let mut circuit = Circuit::new(1);
+        let x = circuit.input(0);
+        let dangling = circuit.new_variable();
+        circuit.mul(x, dangling);
+        assert_eq!(
+            generate_witness(&circuit, &[F17::new(3)]),
+            Err(WitnessError::UnassignedVariable {
+                gate: 0,
+                variable: dangling
+            })
+        );
+    }
+}
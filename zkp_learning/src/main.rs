@@ -1,15 +1,7 @@
-use lib::{
-    field::{print_elliptic_curve_points, scalar_mult, Point, F101},
-    field_extension::F1012,
-};
+use lib::field::{print_elliptic_curve_points, scalar_mult, G1};
 
 fn main() {
-    let g1 = Point::Point {
-        x: F101::new(1),
-        y: F101::new(2),
-    };
-
-    print_elliptic_curve_points(g1);
+    print_elliptic_curve_points(G1);
 
     let mut srs = Vec::new();
 
@@ -17,7 +9,7 @@ fn main() {
     // tau^0*G1, tau^1*G1...
     for i in 0..=6 {
         let k = 2u32.pow(i);
-        let point = scalar_mult(k, g1);
+        let point = scalar_mult(k, G1);
         println!("2^{} * G1 = {:?}", i, point);
         srs.push(point);
     }